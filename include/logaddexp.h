@@ -0,0 +1,45 @@
+/* Generated with cbindgen from src/ffi.rs, don't edit by hand */
+
+#ifndef LOGADDEXP_H
+#define LOGADDEXP_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Compute `ln(exp(a) + exp(b))`
+double logaddexp_f64(double a, double b);
+
+// Compute `ln(sum(exp(values)))` of `len` values
+//
+// An empty array returns negative infinity. `values` may be null if `len` is zero, and a null
+// `values` with a nonzero `len` returns NaN.
+//
+// # Safety
+//
+// If `values` isn't null, it must point to `len` initialized `double`s that aren't modified
+// during the call.
+double logsumexp_f64(const double *values, size_t len);
+
+// Replace `len` values with their log softmax and return their `ln_sum_exp`
+//
+// After this, `values[i]` is the old `values[i]` minus the returned value. `values` may be null
+// if `len` is zero, and a null `values` with a nonzero `len` returns NaN and writes nothing.
+//
+// # Safety
+//
+// If `values` isn't null, it must point to `len` initialized `double`s that nothing else
+// accesses during the call.
+double log_softmax_f64(double *values, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LOGADDEXP_H */
@@ -0,0 +1,248 @@
+//! Fixed arity versions of `ln_add_exp`
+use super::math::{exp, ln_1p};
+use super::LogAddExp;
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of a fixed size array
+///
+/// Since the length is known at compile time, the max and sum loops are fully unrolled for small
+/// arrays, avoiding iterator overhead. All values except the maximum are summed and added with a
+/// single `ln_1p`, which is slightly more accurate than shifting by the maximum and taking `ln`.
+/// Arrays of two values use [`ln_add_exp`][LogAddExp::ln_add_exp] directly.
+///
+/// This is a function instead of a [`LogSumExp`][crate::LogSumExp] implementation because arrays
+/// can't implement the trait alongside the implementation for iterators.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_array;
+/// ln_sum_exp_array([1.0, 2.0, 4.0]);
+/// ```
+#[must_use]
+pub fn ln_sum_exp_array<T, const N: usize>(vals: [T; N]) -> T
+where
+    T: Float,
+{
+    if let [first, second] = vals[..] {
+        return first.ln_add_exp(second);
+    }
+    let mut index = 0;
+    let mut max = T::neg_infinity();
+    for (i, &val) in vals.iter().enumerate() {
+        if val > max {
+            index = i;
+            max = val;
+        }
+    }
+    if max.is_finite() {
+        let rest = vals
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .fold(T::zero(), |sum, (_, &val)| sum + exp(val - max));
+        max + ln_1p(rest)
+    } else if max == T::neg_infinity() && vals.iter().any(|val| val.is_nan()) {
+        T::nan()
+    } else {
+        max
+    }
+}
+
+/// Compute `ln_add_exp` of three values
+///
+/// This is equivalent to `a.ln_add_exp(b).ln_add_exp(c)`, but only selects the maximum once and
+/// only calls `ln_1p` once, so it's faster and has less rounding error.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp3;
+/// ln_add_exp3(0.0, 0.0, 0.0); // 3_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_add_exp3<T>(a: T, b: T, c: T) -> T
+where
+    T: Float,
+{
+    ln_sum_exp_array([a, b, c])
+}
+
+/// Compute `ln_add_exp` of four values
+///
+/// See [`ln_add_exp3`] for details.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp4;
+/// ln_add_exp4(0.0, 0.0, 0.0, 0.0); // 4_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_add_exp4<T>(a: T, b: T, c: T, d: T) -> T
+where
+    T: Float,
+{
+    ln_sum_exp_array([a, b, c, d])
+}
+
+/// Compute `ln_add_exp` of any number of values
+///
+/// This expands to the best kernel for the number of arguments:
+/// [`ln_add_exp`][crate::LogAddExp::ln_add_exp] for two, [`ln_add_exp3`] for three,
+/// [`ln_add_exp4`] for four, and [`ln_sum_exp_array`] for more. A single argument is returned
+/// unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp;
+///
+/// let (a, b, c): (f64, f64, f64) = (0.0, 1.0, 2.0);
+/// ln_add_exp!(a, b); // a.ln_add_exp(b)
+/// ln_add_exp!(a, b, c); // ln_add_exp3(a, b, c)
+/// ln_add_exp!(a, b, c, a, b, c); // ln_sum_exp_array([a, b, c, a, b, c])
+/// ```
+#[macro_export]
+macro_rules! ln_add_exp {
+    ($a:expr $(,)?) => {
+        $a
+    };
+    ($a:expr, $b:expr $(,)?) => {
+        $crate::LogAddExp::ln_add_exp($a, $b)
+    };
+    ($a:expr, $b:expr, $c:expr $(,)?) => {
+        $crate::ln_add_exp3($a, $b, $c)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr $(,)?) => {
+        $crate::ln_add_exp4($a, $b, $c, $d)
+    };
+    ($($vals:expr),+ $(,)?) => {
+        $crate::ln_sum_exp_array([$($vals),+])
+    };
+}
+
+/// A trait for computing `ln_add_exp` of tuples of log values element-wise
+///
+/// This is implemented for tuples of two to eight values of the same float type, so a small
+/// fixed group of scores, like per-class log probabilities, can be combined in one expression.
+/// It's a separate trait from [`LogAddExp`] because tuples can't implement it alongside the
+/// implementation for floats.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::TupleLogAddExp;
+///
+/// let (a, b, c) = (0.0, 1.0, 2.0).ln_add_exp((0.0, 1.0, 2.0));
+/// // (2_f64.ln(), 1.0 + 2_f64.ln(), 2.0 + 2_f64.ln())
+/// # let _: f64 = a + b + c;
+/// ```
+pub trait TupleLogAddExp {
+    /// Compute [`ln_add_exp`][LogAddExp::ln_add_exp] of each pair of corresponding elements
+    #[must_use]
+    fn ln_add_exp(self, other: Self) -> Self;
+}
+
+macro_rules! impl_tuple_log_add_exp {
+    ($($name:ident: $index:tt),+) => {
+        impl<T> TupleLogAddExp for ($($name,)+)
+        where
+            T: Float,
+        {
+            fn ln_add_exp(self, other: Self) -> Self {
+                ($(self.$index.ln_add_exp(other.$index),)+)
+            }
+        }
+    };
+}
+
+impl_tuple_log_add_exp!(T: 0, T: 1);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2, T: 3);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2, T: 3, T: 4);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2, T: 3, T: 4, T: 5);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2, T: 3, T: 4, T: 5, T: 6);
+impl_tuple_log_add_exp!(T: 0, T: 1, T: 2, T: 3, T: 4, T: 5, T: 6, T: 7);
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_add_exp3, ln_add_exp4, ln_sum_exp_array, TupleLogAddExp};
+    use crate::{LogAddExp, LogSumExp};
+
+    #[test]
+    fn test_ln_sum_exp_array() {
+        let vals: [f64; 9] = [1.0, -2.0, 3.0, 0.5, 10.0, -7.0, 2.5, 0.0, 1.5];
+        assert_close!(ln_sum_exp_array(vals), vals.into_iter().ln_sum_exp());
+        assert_eq!(ln_sum_exp_array([2.0]), 2.0);
+        assert_eq!(ln_sum_exp_array([1.0, 2.0]), 1.0.ln_add_exp(2.0));
+        assert_eq!(ln_sum_exp_array::<f64, 0>([]), f64::NEG_INFINITY);
+        assert_eq!(ln_sum_exp_array([f64::NEG_INFINITY; 3]), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_ln_add_exp3() {
+        assert_close!(ln_add_exp3(0.0, 0.0, 0.0), 3_f64.ln());
+        assert_close!(
+            ln_add_exp3(1.0, -2.0, 3.0_f64),
+            1.0.ln_add_exp(-2.0).ln_add_exp(3.0)
+        );
+        assert_close!(ln_add_exp3(-1000.0, 1000.0, -1000.0_f64), 1000.0);
+        assert_eq!(ln_add_exp3(f64::NEG_INFINITY, 1.0, f64::NEG_INFINITY), 1.0);
+        assert_eq!(
+            ln_add_exp3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            ln_add_exp3(f64::INFINITY, 1.0, f64::INFINITY),
+            f64::INFINITY
+        );
+        assert_eq!(ln_add_exp3(f64::INFINITY, 1.0, f64::NAN), f64::INFINITY);
+        assert!(ln_add_exp3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn test_ln_add_exp_macro() {
+        let [a, b, c, d] = [1.0_f64, -2.0, 3.0, 0.5];
+        assert_eq!(ln_add_exp!(a), a);
+        assert_eq!(ln_add_exp!(a, b), a.ln_add_exp(b));
+        assert_eq!(ln_add_exp!(a, b, c), ln_add_exp3(a, b, c));
+        assert_eq!(ln_add_exp!(a, b, c, d,), ln_add_exp4(a, b, c, d));
+        assert_close!(
+            ln_add_exp!(a, b, c, d, 0.25),
+            ln_add_exp4(a, b, c, d).ln_add_exp(0.25)
+        );
+    }
+
+    #[test]
+    fn test_ln_add_exp4() {
+        assert_close!(ln_add_exp4(0.0, 0.0, 0.0, 0.0), 4_f64.ln());
+        assert_close!(
+            ln_add_exp4(1.0, -2.0, 3.0, 0.5_f64),
+            1.0.ln_add_exp(-2.0).ln_add_exp(3.0).ln_add_exp(0.5)
+        );
+        assert_eq!(
+            ln_add_exp4(f64::NEG_INFINITY, f64::INFINITY, 1.0, 2.0),
+            f64::INFINITY
+        );
+        assert!(ln_add_exp4(f64::NAN, 1.0, 2.0, 3.0).is_nan());
+    }
+
+    #[test]
+    fn test_tuple_ln_add_exp() {
+        let (a, b) = (1_f64, -2.0).ln_add_exp((3.0, -2.0));
+        assert_eq!(a, 1.0.ln_add_exp(3.0));
+        assert_close!(b, -2.0 + 2_f64.ln());
+
+        let res = (0_f32, f32::NEG_INFINITY, 1000.0).ln_add_exp((0.0, 0.0, 1000.0));
+        assert_close!(res.0, 2_f32.ln());
+        assert_eq!(res.1, 0.0);
+        assert_close!(res.2, 1000.0 + 2_f32.ln());
+
+        let vals = (0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0);
+        let res = vals.ln_add_exp(vals);
+        assert_close!(res.7, 7.0 + 2_f64.ln());
+        assert_close!(res.0, 2_f64.ln());
+    }
+}
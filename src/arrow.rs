@@ -0,0 +1,128 @@
+//! Kernels for Apache Arrow arrays
+//!
+//! These operate on any float [`PrimitiveArray`], e.g. a [`Float64Array`][arrow_array::Float64Array]
+//! or [`Float32Array`][arrow_array::Float32Array], and skip null values. Arrays without nulls are
+//! reduced with the same kernels as [`ln_sum_exp_slice`].
+//!
+//! To use [`ln_sum_exp`] as an aggregate, e.g. in a `DataFusion` UDAF, keep the `ln_sum_exp` of
+//! the batches seen so far as the accumulator state. Updating with a batch and merging partial
+//! states are then both [`ln_add_exp`][crate::LogAddExp::ln_add_exp] of the state with the
+//! `ln_sum_exp` of an array.
+use super::math::ln;
+use super::{ln_sum_exp_masked_iter, ln_sum_exp_slice};
+use arrow_array::types::ArrowPrimitiveType;
+use arrow_array::{Array, PrimitiveArray};
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of the non-null values in an array
+///
+/// An array that's empty or all null returns negative infinity.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::Float64Array;
+/// use logaddexp::arrow::ln_sum_exp;
+///
+/// let array = Float64Array::from(vec![Some(1.0), None, Some(2.0)]);
+/// ln_sum_exp(&array); // 1_f64.ln_add_exp(2.0)
+/// ```
+#[must_use]
+pub fn ln_sum_exp<T>(array: &PrimitiveArray<T>) -> T::Native
+where
+    T: ArrowPrimitiveType,
+    T::Native: Float,
+{
+    match array.nulls() {
+        Some(nulls) if nulls.null_count() > 0 => {
+            ln_sum_exp_masked_iter(array.values().iter().copied(), nulls.iter())
+        }
+        _ => ln_sum_exp_slice(array.values()),
+    }
+}
+
+/// Compute `ln_sum_exp` of the non-null values in an array minus the log of how many there are
+///
+/// This returns `None` if there aren't any non-null values.
+#[must_use]
+pub fn ln_mean_exp<T>(array: &PrimitiveArray<T>) -> Option<T::Native>
+where
+    T: ArrowPrimitiveType,
+    T::Native: Float,
+{
+    let count = array.len() - array.null_count();
+    if count == 0 {
+        None
+    } else {
+        let count = <T::Native as num_traits::NumCast>::from(count)?;
+        Some(ln_sum_exp(array) - ln(count))
+    }
+}
+
+/// Subtract the `ln_sum_exp` of the non-null values from every value
+///
+/// The result has the same nulls as the input.
+///
+/// # Examples
+///
+/// ```
+/// use arrow_array::Float64Array;
+/// use logaddexp::arrow::log_softmax;
+///
+/// let array = Float64Array::from(vec![Some(0.0), None, Some(0.0)]);
+/// log_softmax(&array); // [ln(0.5), null, ln(0.5)]
+/// ```
+#[must_use]
+pub fn log_softmax<T>(array: &PrimitiveArray<T>) -> PrimitiveArray<T>
+where
+    T: ArrowPrimitiveType,
+    T::Native: Float,
+{
+    let total = ln_sum_exp(array);
+    array.unary(|val| val - total)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_mean_exp, ln_sum_exp, log_softmax};
+    use crate::LogSumExp;
+    use arrow_array::{Array, Float32Array, Float64Array};
+
+    #[test]
+    fn test_ln_sum_exp() {
+        let array = Float64Array::from(vec![Some(1.0), None, Some(2.0), Some(4.0)]);
+        let expected = [1.0, 2.0, 4.0].into_iter().ln_sum_exp();
+        assert_close!(ln_sum_exp(&array), expected);
+        assert_close!(
+            ln_sum_exp(&array.slice(2, 2)),
+            [2.0, 4.0].into_iter().ln_sum_exp()
+        );
+
+        let array = Float32Array::from(vec![1.0, 2.0]);
+        assert_close!(ln_sum_exp(&array), [1_f32, 2.0].into_iter().ln_sum_exp());
+
+        let array = Float64Array::from(vec![None, None]);
+        assert_eq!(ln_sum_exp(&array), f64::NEG_INFINITY);
+        // the null's value is ignored even though it's stored
+        let array = Float64Array::from(vec![Some(1.0), None]);
+        assert_eq!(ln_sum_exp(&array), 1.0);
+    }
+
+    #[test]
+    fn test_ln_mean_exp() {
+        let array = Float64Array::from(vec![Some(1.0), None, Some(1.0)]);
+        assert_close!(ln_mean_exp(&array).unwrap(), 1.0);
+        assert_eq!(ln_mean_exp(&Float64Array::from(vec![None])), None);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let array = Float64Array::from(vec![Some(0.0), None, Some(0.0)]);
+        let res = log_softmax(&array);
+        assert_eq!(res.len(), 3);
+        assert_close!(res.value(0), 0.5_f64.ln());
+        assert!(res.is_null(1));
+        assert_close!(res.value(2), 0.5_f64.ln());
+    }
+}
@@ -0,0 +1,126 @@
+//! An accumulator that can be shared between threads without a lock
+use super::LogAddExp;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// An `f64` accumulator of `ln_sum_exp` that threads can push into concurrently
+///
+/// The running total is stored as the bits of an [`AtomicU64`], and every push is a
+/// compare-exchange loop that replaces the total with its [`ln_add_exp`][LogAddExp::ln_add_exp]
+/// with the new value, so no update is ever lost. Updates are relaxed: the total is always the
+/// combination of some set of pushes, but it doesn't synchronize any other memory.
+///
+/// Since every push is a pairwise `ln_add_exp`, the result is slightly less accurate than
+/// [`StreamingLnSumExp`][crate::StreamingLnSumExp], and the rounding depends on the order that
+/// threads happen to push in.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::AtomicLnSumExp;
+/// use std::thread;
+///
+/// let acc = AtomicLnSumExp::new();
+/// thread::scope(|scope| {
+///     scope.spawn(|| acc.push(0.0));
+///     scope.spawn(|| acc.push(0.0));
+/// });
+/// acc.ln_sum_exp(); // 2_f64.ln()
+/// ```
+pub struct AtomicLnSumExp {
+    bits: AtomicU64,
+}
+
+impl AtomicLnSumExp {
+    /// Create a new empty accumulator
+    #[must_use]
+    pub const fn new() -> Self {
+        AtomicLnSumExp {
+            bits: AtomicU64::new(f64::NEG_INFINITY.to_bits()),
+        }
+    }
+
+    /// Add a value to the accumulator
+    pub fn push(&self, value: f64) {
+        if value == f64::NEG_INFINITY {
+            return;
+        }
+        // the closure always returns some, so this can't fail
+        let _ = self
+            .bits
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some(f64::from_bits(bits).ln_add_exp(value).to_bits())
+            });
+    }
+
+    /// The `ln_sum_exp` of the values pushed so far
+    ///
+    /// An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    /// Consume the accumulator and return its `ln_sum_exp`
+    #[must_use]
+    pub fn into_inner(self) -> f64 {
+        f64::from_bits(self.bits.into_inner())
+    }
+}
+
+impl Default for AtomicLnSumExp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AtomicLnSumExp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicLnSumExp")
+            .field("ln_sum_exp", &self.ln_sum_exp())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::AtomicLnSumExp;
+    use crate::LogSumExp;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_push() {
+        let acc = AtomicLnSumExp::new();
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        thread::scope(|scope| {
+            for t in 0..4 {
+                let acc = &acc;
+                scope.spawn(move || {
+                    for n in 0..1000 {
+                        acc.push(f64::from(t * 1000 + n) * -0.01);
+                    }
+                });
+            }
+        });
+        let expected = (0..4000).map(|n| f64::from(n) * -0.01).ln_sum_exp();
+        assert_close!(acc.into_inner(), expected, rtol = 1e-12);
+    }
+
+    #[test]
+    fn test_special() {
+        let acc = AtomicLnSumExp::default();
+        acc.push(f64::NEG_INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        acc.push(1.0);
+        acc.push(f64::INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+        acc.push(f64::NAN);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+
+        let acc = AtomicLnSumExp::default();
+        acc.push(1.0);
+        acc.push(f64::NAN);
+        assert!(acc.ln_sum_exp().is_nan());
+    }
+}
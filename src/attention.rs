@@ -0,0 +1,199 @@
+//! Normalized attention scores
+use super::ln_sum_exp_slice;
+use super::math::cast;
+use num_traits::Float;
+
+/// The dimensions of [`attention_log_softmax`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AttentionShape {
+    /// The number of independent attention problems, e.g. batch times heads
+    pub batch: usize,
+    /// The number of queries in each problem
+    pub queries: usize,
+    /// The number of keys in each problem
+    pub keys: usize,
+    /// The dimension of every query and key
+    pub dim: usize,
+}
+
+/// Compute the log attention weights `log_softmax(Q·Kᵀ/√d + mask)` of every query into `out`
+///
+/// `queries` is a row-major `batch × queries × dim` tensor, `keys` is a `batch × keys × dim`
+/// tensor, and `out` is written as a `batch × queries × keys` tensor where every row is the log
+/// softmax over keys of the scaled dot products, plus `mask`. The mask is additive, typically zero
+/// or negative infinity, and either has `queries × keys` values shared by the whole batch or
+/// `batch × queries × keys` values. Scaling, masking, and normalization are done in place in one
+/// pass over each row, without any intermediate allocation.
+///
+/// Masked keys get a log weight of negative infinity, and don't affect the stability of the
+/// normalization. A row where every key is masked has no valid distribution, and instead of NaN
+/// is left as all negative infinity, so its exponentiated weights are zero.
+///
+/// # Panics
+///
+/// If `dim` is zero, or `queries`, `keys`, `mask`, or `out` don't match `shape`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::{attention_log_softmax, AttentionShape};
+///
+/// let shape = AttentionShape { batch: 1, queries: 2, keys: 2, dim: 1 };
+/// let mask = [0.0, f64::NEG_INFINITY, 0.0, 0.0]; // causal
+/// let mut out = [0.0; 4];
+/// attention_log_softmax(&[1.0, 1.0], &[1.0, 1.0], Some(&mask), shape, &mut out);
+/// // out == [0.0, f64::NEG_INFINITY, 0.5_f64.ln(), 0.5_f64.ln()]
+/// ```
+pub fn attention_log_softmax<T>(
+    queries: &[T],
+    keys: &[T],
+    mask: Option<&[T]>,
+    shape: AttentionShape,
+    out: &mut [T],
+) where
+    T: Float,
+{
+    let AttentionShape {
+        batch,
+        queries: num_queries,
+        keys: num_keys,
+        dim,
+    } = shape;
+    assert!(dim > 0, "dim must be positive");
+    assert_eq!(
+        queries.len(),
+        batch * num_queries * dim,
+        "queries must have batch * queries * dim values"
+    );
+    assert_eq!(
+        keys.len(),
+        batch * num_keys * dim,
+        "keys must have batch * keys * dim values"
+    );
+    assert_eq!(
+        out.len(),
+        batch * num_queries * num_keys,
+        "out must have batch * queries * keys values"
+    );
+    let scores = num_queries * num_keys;
+    if let Some(mask) = mask {
+        assert!(
+            mask.len() == scores || mask.len() == batch * scores,
+            "mask must have queries * keys or batch * queries * keys values"
+        );
+    }
+    if num_keys == 0 {
+        return;
+    }
+    let scale = cast::<T>(dim).sqrt().recip();
+    for (ind, row) in out.chunks_exact_mut(num_keys).enumerate() {
+        let query = &queries[ind * dim..][..dim];
+        let keys = &keys[ind / num_queries * num_keys * dim..][..num_keys * dim];
+        for (score, key) in row.iter_mut().zip(keys.chunks_exact(dim)) {
+            let dot = query
+                .iter()
+                .zip(key)
+                .fold(T::zero(), |acc, (&q, &k)| acc + q * k);
+            *score = dot * scale;
+        }
+        if let Some(mask) = mask {
+            let mask = &mask[ind * num_keys % mask.len()..][..num_keys];
+            for (score, &masked) in row.iter_mut().zip(mask) {
+                *score = *score + masked;
+            }
+        }
+        let total = ln_sum_exp_slice(row);
+        if total != T::neg_infinity() {
+            for score in row.iter_mut() {
+                *score = *score - total;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{attention_log_softmax, AttentionShape};
+    use crate::ln_sum_exp_slice;
+
+    #[test]
+    fn test_attention_log_softmax() {
+        let shape = AttentionShape {
+            batch: 2,
+            queries: 3,
+            keys: 4,
+            dim: 5,
+        };
+        let queries: Vec<f64> = (0..30).map(|n| f64::from(n).sin() * 3.0).collect();
+        let keys: Vec<f64> = (0..40).map(|n| f64::from(n).cos() * 3.0).collect();
+        let mut out = vec![0.0; 24];
+        attention_log_softmax(&queries, &keys, None, shape, &mut out);
+        for b in 0..2 {
+            for q in 0..3 {
+                let scores: Vec<f64> = (0..4)
+                    .map(|k| {
+                        let query = &queries[(b * 3 + q) * 5..][..5];
+                        let key = &keys[(b * 4 + k) * 5..][..5];
+                        query.iter().zip(key).map(|(a, b)| a * b).sum::<f64>() / 5_f64.sqrt()
+                    })
+                    .collect();
+                let total = ln_sum_exp_slice(&scores);
+                for (k, &score) in scores.iter().enumerate() {
+                    assert_close!(out[(b * 3 + q) * 4 + k], score - total);
+                }
+            }
+        }
+
+        // a shared mask matches a mask repeated for every batch
+        let neg = f64::NEG_INFINITY;
+        let mask: Vec<f64> = (0..12)
+            .map(|n| if n % 4 > n / 4 { neg } else { 0.0 })
+            .collect();
+        let mut shared = vec![0.0; 24];
+        attention_log_softmax(&queries, &keys, Some(&mask), shape, &mut shared);
+        let mut full = vec![0.0; 24];
+        attention_log_softmax(&queries, &keys, Some(&mask.repeat(2)), shape, &mut full);
+        assert_eq!(shared, full);
+        for (row, mask) in shared.chunks_exact(4).zip(mask.chunks_exact(4).cycle()) {
+            assert_close!(ln_sum_exp_slice(row), 0.0);
+            for (&val, &masked) in row.iter().zip(mask) {
+                assert_eq!(val == neg, masked == neg);
+            }
+        }
+    }
+
+    #[test]
+    fn test_attention_log_softmax_masked() {
+        let shape = AttentionShape {
+            batch: 1,
+            queries: 2,
+            keys: 2,
+            dim: 1,
+        };
+        let neg = f64::NEG_INFINITY;
+        // large scores that would overflow without the shift
+        let mut out = [0.0; 4];
+        let mask = [neg, neg, 0.0, neg];
+        attention_log_softmax(
+            &[1000.0, 1000.0],
+            &[1000.0, 1.0],
+            Some(&mask),
+            shape,
+            &mut out,
+        );
+        assert_eq!(out, [neg, neg, 0.0, neg]);
+    }
+
+    #[test]
+    #[should_panic(expected = "mask must have queries * keys or batch * queries * keys values")]
+    fn test_attention_log_softmax_mask_shape() {
+        let shape = AttentionShape {
+            batch: 1,
+            queries: 1,
+            keys: 2,
+            dim: 1,
+        };
+        attention_log_softmax(&[1.0], &[1.0, 1.0], Some(&[0.0]), shape, &mut [0.0; 2]);
+    }
+}
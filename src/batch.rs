@@ -0,0 +1,78 @@
+//! Batched `ln_sum_exp` over ragged rows of a flat buffer
+use super::ln_sum_exp_slice;
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` for every row of a flat buffer
+///
+/// Rows are described by `offsets` in compressed sparse row layout, so row `i` is
+/// `values[offsets[i]..offsets[i + 1]]` and there is one fewer row than offsets. Empty rows are
+/// negative infinity.
+///
+/// # Panics
+///
+/// If the offsets are decreasing or out of bounds of `values`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_offsets;
+///
+/// let res = ln_sum_exp_offsets(&[1.0, 2.0, 3.0], &[0, 2, 2, 3]);
+/// // [(1_f64.exp() + 2_f64.exp()).ln(), f64::NEG_INFINITY, 3.0]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_offsets<T>(values: &[T], offsets: &[usize]) -> Vec<T>
+where
+    T: Float,
+{
+    offsets
+        .windows(2)
+        .map(|bounds| ln_sum_exp_slice(&values[bounds[0]..bounds[1]]))
+        .collect()
+}
+
+/// Compute `ln_sum_exp` for every row of a flat buffer in parallel
+///
+/// This is identical to [`ln_sum_exp_offsets`] but distributes rows over the rayon thread pool.
+///
+/// # Panics
+///
+/// If the offsets are decreasing or out of bounds of `values`.
+#[cfg(feature = "parallel")]
+#[must_use]
+pub fn par_ln_sum_exp_offsets<T>(values: &[T], offsets: &[usize]) -> Vec<T>
+where
+    T: Float + Send + Sync,
+{
+    use rayon::prelude::*;
+
+    offsets
+        .par_windows(2)
+        .map(|bounds| ln_sum_exp_slice(&values[bounds[0]..bounds[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ln_sum_exp_offsets;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_offsets() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let offsets = [0, 3, 3, 10, 19];
+        let res = ln_sum_exp_offsets(&vals, &offsets);
+        assert_eq!(res.len(), 4);
+        assert_close!(res[0], vals[..3].iter().copied().ln_sum_exp());
+        assert_eq!(res[1], f64::NEG_INFINITY);
+        assert_close!(res[2], vals[3..10].iter().copied().ln_sum_exp());
+        assert_close!(res[3], vals[10..].iter().copied().ln_sum_exp());
+
+        assert!(ln_sum_exp_offsets(&vals, &[0]).is_empty());
+        assert!(ln_sum_exp_offsets(&vals, &[]).is_empty());
+
+        #[cfg(feature = "parallel")]
+        assert_eq!(super::par_ln_sum_exp_offsets(&vals, &offsets), res);
+    }
+}
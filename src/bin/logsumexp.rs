@@ -0,0 +1,281 @@
+//! Compute `ln_sum_exp`, `ln_mean_exp`, or softmax of numbers from the command line
+//!
+//! Numbers are read from the files passed as arguments, or from stdin if there are none, and are
+//! separated by whitespace or commas.
+#![warn(missing_docs)]
+#![warn(clippy::pedantic)]
+
+use logaddexp::{ln_sum_exp_slice, softmax_collect};
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: logsumexp [OPTIONS] [FILE]...
+
+Read numbers separated by whitespace or commas from each FILE, or stdin if there are no files, and
+print their log-sum-exp.
+
+options:
+  -m, --mean          print the log-mean-exp instead
+  -s, --softmax       print the softmax of every number, one per line, instead
+  -b, --base BASE     treat the numbers as logarithms in BASE instead of e, and print the
+                      log-sum-exp and log-mean-exp in BASE too
+  -c, --column N      only read the Nth field of every line, counting from one
+  -h, --help          print this message
+";
+
+/// What to print
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Sum,
+    Mean,
+    Softmax,
+}
+
+/// Parsed command line arguments
+#[derive(Debug, PartialEq)]
+struct Args {
+    mode: Mode,
+    base: f64,
+    column: Option<usize>,
+    files: Vec<String>,
+}
+
+/// An error from reading the arguments or the input
+#[derive(Debug)]
+enum Error {
+    Usage(String),
+    Parse { line: usize, field: String },
+    MissingColumn { line: usize, column: usize },
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg) => write!(f, "{msg}\n\n{USAGE}"),
+            Error::Parse { line, field } => write!(f, "line {line}: invalid number: {field:?}"),
+            Error::MissingColumn { line, column } => {
+                write!(f, "line {line}: no column {column}")
+            }
+            Error::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// Parse the command line arguments, without the program name
+///
+/// This returns `None` if help was requested.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, Error> {
+    let mut parsed = Args {
+        mode: Mode::Sum,
+        base: std::f64::consts::E,
+        column: None,
+        files: Vec::new(),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| Error::Usage(format!("{name} needs a value")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-m" | "--mean" => parsed.mode = Mode::Mean,
+            "-s" | "--softmax" => parsed.mode = Mode::Softmax,
+            "-b" | "--base" => {
+                let base = value(&arg)?;
+                parsed.base = match base.parse::<f64>() {
+                    // rules out bases that are negative, zero, one, or infinite
+                    Ok(base) if base.ln().is_normal() => base,
+                    _ => return Err(Error::Usage(format!("invalid base: {base}"))),
+                }
+            }
+            "-c" | "--column" => {
+                let column = value(&arg)?;
+                parsed.column = match column.parse() {
+                    Ok(column) if column > 0 => Some(column),
+                    _ => return Err(Error::Usage(format!("invalid column: {column}"))),
+                }
+            }
+            "-" => parsed.files.push(arg),
+            _ if arg.starts_with('-') => {
+                return Err(Error::Usage(format!("unknown option: {arg}")))
+            }
+            _ => parsed.files.push(arg),
+        }
+    }
+    Ok(Some(parsed))
+}
+
+/// Append the numbers in `reader` to `values`, scaled by `scale`
+fn read_values(
+    reader: impl BufRead,
+    column: Option<usize>,
+    scale: f64,
+    values: &mut Vec<f64>,
+) -> Result<(), Error> {
+    for (num, line) in reader.lines().enumerate() {
+        let line = line?;
+        let mut fields = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty())
+            .peekable();
+        if fields.peek().is_none() {
+            continue;
+        }
+        let parse = |field: &str| {
+            field
+                .parse::<f64>()
+                .map(|val| val * scale)
+                .map_err(|_| Error::Parse {
+                    line: num + 1,
+                    field: field.into(),
+                })
+        };
+        if let Some(column) = column {
+            let field = fields.nth(column - 1).ok_or(Error::MissingColumn {
+                line: num + 1,
+                column,
+            })?;
+            values.push(parse(field)?);
+        } else {
+            for field in fields {
+                values.push(parse(field)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compute the output for a mode from values in natural log space
+///
+/// The outputs for sum and mean are converted back to the base by dividing by `scale`.
+fn compute(mode: Mode, values: &[f64], scale: f64) -> Vec<f64> {
+    match mode {
+        Mode::Sum => vec![ln_sum_exp_slice(values) / scale],
+        Mode::Mean => {
+            #[allow(clippy::cast_precision_loss)]
+            let count = values.len() as f64;
+            vec![(ln_sum_exp_slice(values) - count.ln()) / scale]
+        }
+        Mode::Softmax => softmax_collect(values).1,
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let scale = args.base.ln();
+    let mut values = Vec::new();
+    if args.files.is_empty() {
+        read_values(io::stdin().lock(), args.column, scale, &mut values)?;
+    }
+    for file in &args.files {
+        if file == "-" {
+            read_values(io::stdin().lock(), args.column, scale, &mut values)?;
+        } else {
+            let reader = BufReader::new(
+                File::open(file)
+                    .map_err(|err| io::Error::new(err.kind(), format!("{file}: {err}")))?,
+            );
+            read_values(reader, args.column, scale, &mut values)?;
+        }
+    }
+    let mut out = BufWriter::new(io::stdout().lock());
+    for val in compute(args.mode, &values, scale) {
+        writeln!(out, "{val}")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let res = parse_args(env::args().skip(1)).and_then(|args| {
+        if let Some(args) = args {
+            run(&args)
+        } else {
+            print!("{USAGE}");
+            Ok(())
+        }
+    });
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        // a closed pipe, e.g. from piping to head, isn't an error
+        Err(Error::Io(err)) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("logsumexp: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{compute, parse_args, read_values, Args, Error, Mode};
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-12 * b.abs().max(1.0), "{a} != {b}");
+    }
+
+    fn args(args: &[&str]) -> Result<Option<Args>, Error> {
+        parse_args(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = args(&["-m", "--base", "2", "-c", "3", "a.csv", "-"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            parsed,
+            Args {
+                mode: Mode::Mean,
+                base: 2.0,
+                column: Some(3),
+                files: vec!["a.csv".into(), "-".into()],
+            }
+        );
+        assert!(args(&["--help"]).unwrap().is_none());
+        assert!(matches!(args(&["-c", "0"]), Err(Error::Usage(_))));
+        assert!(matches!(args(&["-b", "1"]), Err(Error::Usage(_))));
+        assert!(matches!(args(&["-b"]), Err(Error::Usage(_))));
+        assert!(matches!(args(&["-x"]), Err(Error::Usage(_))));
+    }
+
+    #[test]
+    fn test_read_values() {
+        let input = "1 2,3\n\n 4,\t5 \n";
+        let mut values = Vec::new();
+        read_values(input.as_bytes(), None, 1.0, &mut values).unwrap();
+        assert_eq!(values, [1.0, 2.0, 3.0, 4.0, 5.0]);
+
+        values.clear();
+        read_values(input.as_bytes(), Some(2), 2.0, &mut values).unwrap();
+        assert_eq!(values, [4.0, 10.0]);
+
+        let err = read_values("1\n2 x\n".as_bytes(), None, 1.0, &mut values).unwrap_err();
+        assert!(matches!(err, Error::Parse { line: 2, .. }));
+        let err = read_values("1 2\n3\n".as_bytes(), Some(2), 1.0, &mut values).unwrap_err();
+        assert!(matches!(err, Error::MissingColumn { line: 2, column: 2 }));
+    }
+
+    #[test]
+    fn test_compute() {
+        let ln_2 = 2_f64.ln();
+        let values = [ln_2, ln_2];
+        assert_close(compute(Mode::Sum, &values, 1.0)[0], 4_f64.ln());
+        assert_close(compute(Mode::Mean, &values, 1.0)[0], ln_2);
+        assert_eq!(compute(Mode::Softmax, &values, 1.0), [0.5, 0.5]);
+        // two ones in base two sum to two
+        assert_close(compute(Mode::Sum, &values, ln_2)[0], 2.0);
+    }
+}
@@ -0,0 +1,112 @@
+//! Stable ops for burn tensors
+//!
+//! [`logsumexp`] and [`log_softmax`] are composed from burn tensor ops, so they run on any
+//! backend, including the ndarray backend, and are differentiable under autodiff. The maximum is
+//! only used as a shift when it's finite, so like [`ln_sum_exp_slice`][crate::ln_sum_exp_slice]
+//! a dimension that's all negative infinity reduces to negative infinity instead of NaN, and a
+//! positive infinity produces positive infinity. The shift is detached from the graph since the
+//! result doesn't depend on it.
+//!
+//! # Examples
+//!
+//! ```
+//! use burn::backend::NdArray;
+//! use burn::tensor::Tensor;
+//! use logaddexp::burn::{log_softmax, logsumexp};
+//!
+//! let tensor = Tensor::<NdArray, 2>::from_floats([[1.0, 2.0], [3.0, 4.0]], &Default::default());
+//! let totals = logsumexp(tensor.clone(), 1); // shape [2, 1]
+//! let log_probs = log_softmax(tensor, 1); // shape [2, 2]
+//! ```
+use burn::tensor::backend::Backend;
+use burn::tensor::Tensor;
+
+/// Compute `ln_sum_exp` along `dim`
+///
+/// Like burn's other reductions, `dim` is kept with a size of one. Use
+/// [`squeeze`][Tensor::squeeze] to remove it.
+///
+/// # Panics
+///
+/// If `dim` is out of range.
+#[must_use]
+pub fn logsumexp<B: Backend, const D: usize>(tensor: Tensor<B, D>, dim: usize) -> Tensor<B, D> {
+    let max = tensor.clone().max_dim(dim).detach();
+    let shift = max.clone().mask_fill(max.is_inf(), 0.0);
+    tensor
+        .sub(shift.clone())
+        .exp()
+        .sum_dim(dim)
+        .log()
+        .add(shift)
+}
+
+/// Subtract the `ln_sum_exp` along `dim` from every value
+///
+/// # Panics
+///
+/// If `dim` is out of range.
+#[must_use]
+pub fn log_softmax<B: Backend, const D: usize>(tensor: Tensor<B, D>, dim: usize) -> Tensor<B, D> {
+    let total = logsumexp(tensor.clone(), dim);
+    tensor.sub(total)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{log_softmax, logsumexp};
+    use crate::ln_sum_exp_slice;
+    use burn::backend::{Autodiff, NdArray};
+    use burn::tensor::Tensor;
+
+    fn values<const D: usize>(tensor: Tensor<NdArray, D>) -> Vec<f32> {
+        tensor.into_data().to_vec().unwrap()
+    }
+
+    #[test]
+    fn test_logsumexp() {
+        let device = Default::default();
+        let tensor =
+            Tensor::<NdArray, 2>::from_floats([[1.0, 2.0, 4.0], [100.0, 0.0, -50.0]], &device);
+        let res = values(logsumexp(tensor.clone(), 1));
+        assert_close!(res[0], ln_sum_exp_slice(&[1_f32, 2.0, 4.0]));
+        assert_close!(res[1], ln_sum_exp_slice(&[100_f32, 0.0, -50.0]));
+
+        let res = values(logsumexp(tensor, 0));
+        assert_eq!(res.len(), 3);
+        assert_close!(res[0], 100.0);
+
+        let special = Tensor::<NdArray, 2>::from_floats(
+            [[f32::NEG_INFINITY, f32::NEG_INFINITY], [f32::INFINITY, 1.0]],
+            &device,
+        );
+        assert_eq!(
+            values(logsumexp(special, 1)),
+            [f32::NEG_INFINITY, f32::INFINITY]
+        );
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let tensor =
+            Tensor::<NdArray, 1>::from_floats([0.0, 0.0, f32::NEG_INFINITY], &Default::default());
+        let res = values(log_softmax(tensor, 0));
+        assert_close!(res[0], 0.5_f32.ln());
+        assert_close!(res[1], 0.5_f32.ln());
+        assert_eq!(res[2], f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_backward() {
+        let tensor =
+            Tensor::<Autodiff<NdArray>, 1>::from_floats([1.0, 2.0, 3.0], &Default::default())
+                .require_grad();
+        let grads = logsumexp(tensor.clone(), 0).backward();
+        let grad: Vec<f32> = tensor.grad(&grads).unwrap().into_data().to_vec().unwrap();
+        let total = ln_sum_exp_slice(&[1_f32, 2.0, 3.0]);
+        for (&val, res) in [1_f32, 2.0, 3.0].iter().zip(grad) {
+            assert_close!(res, (val - total).exp());
+        }
+    }
+}
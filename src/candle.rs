@@ -0,0 +1,241 @@
+//! Custom ops for candle tensors
+//!
+//! [`logsumexp`] and [`log_softmax`] reduce CPU tensors along a dimension with the slice kernels,
+//! so they get the same handling of infinities and NaN as [`ln_sum_exp_slice`]. Both ops define a
+//! backward pass, so they can be used while training. Tensors of other devices or of dtypes other
+//! than `f32` and `f64` return an error.
+//!
+//! # Examples
+//!
+//! ```
+//! use candle_core::{Device, Tensor, D};
+//! use logaddexp::candle::{log_softmax, logsumexp};
+//!
+//! let tensor = Tensor::new(&[[1_f32, 2.0], [3.0, 4.0]], &Device::Cpu)?;
+//! let totals = logsumexp(&tensor, D::Minus1)?; // shape [2]
+//! let log_probs = log_softmax(&tensor, 0)?; // shape [2, 2]
+//! # Ok::<(), candle_core::Error>(())
+//! ```
+use super::ln_sum_exp_slice;
+use candle_core::backend::BackendStorage;
+use candle_core::shape::Dim;
+use candle_core::{bail, CpuStorage, CustomOp1, Layout, Result, Shape, Tensor};
+use num_traits::Float;
+
+/// The sizes of the dimensions before, at, and after `dim`
+fn split_dims(dims: &[usize], dim: usize) -> (usize, usize, usize) {
+    let outer = dims[..dim].iter().product();
+    let inner = dims[dim + 1..].iter().product();
+    (outer, dims[dim], inner)
+}
+
+/// Call `f` with every lane along `dim` and its index in the output of a reduction
+///
+/// Lanes that aren't contiguous are copied into a buffer first.
+fn for_each_lane<T: Float>(
+    values: &[T],
+    dims: &[usize],
+    dim: usize,
+    mut f: impl FnMut(&[T], usize),
+) {
+    let (outer, size, inner) = split_dims(dims, dim);
+    if inner == 1 {
+        for (ind, lane) in values.chunks_exact(size).enumerate() {
+            f(lane, ind);
+        }
+    } else {
+        let mut buffer = Vec::with_capacity(size);
+        for out in 0..outer {
+            for ind in 0..inner {
+                let start = out * size * inner + ind;
+                buffer.clear();
+                buffer.extend(values[start..].iter().step_by(inner).take(size).copied());
+                f(&buffer, out * inner + ind);
+            }
+        }
+    }
+}
+
+/// `ln_sum_exp` of every lane along `dim`
+fn lane_ln_sum_exp<T: Float>(values: &[T], dims: &[usize], dim: usize) -> Vec<T> {
+    let (outer, _, inner) = split_dims(dims, dim);
+    let mut out = vec![T::neg_infinity(); outer * inner];
+    if dims[dim] > 0 {
+        for_each_lane(values, dims, dim, |lane, ind| {
+            out[ind] = ln_sum_exp_slice(lane);
+        });
+    }
+    out
+}
+
+/// `log_softmax` of every lane along `dim`
+fn lane_log_softmax<T: Float>(values: &[T], dims: &[usize], dim: usize) -> Vec<T> {
+    let (_, size, inner) = split_dims(dims, dim);
+    let totals = lane_ln_sum_exp(values, dims, dim);
+    values
+        .iter()
+        .enumerate()
+        .map(|(ind, &val)| {
+            let out = ind / (size * inner);
+            val - totals[out * inner + ind % inner]
+        })
+        .collect()
+}
+
+/// The op behind [`logsumexp`]
+struct LogSumExpOp {
+    dim: usize,
+}
+
+impl CustomOp1 for LogSumExpOp {
+    fn name(&self) -> &'static str {
+        "logaddexp-logsumexp"
+    }
+
+    fn cpu_fwd(&self, storage: &CpuStorage, layout: &Layout) -> Result<(CpuStorage, Shape)> {
+        let Some((start, end)) = layout.contiguous_offsets() else {
+            bail!("logsumexp requires a contiguous tensor")
+        };
+        let dims = layout.dims();
+        let res = match storage {
+            CpuStorage::F32(values) => {
+                CpuStorage::F32(lane_ln_sum_exp(&values[start..end], dims, self.dim))
+            }
+            CpuStorage::F64(values) => {
+                CpuStorage::F64(lane_ln_sum_exp(&values[start..end], dims, self.dim))
+            }
+            _ => bail!("logsumexp doesn't support {:?}", storage.dtype()),
+        };
+        let mut shape = dims.to_vec();
+        shape.remove(self.dim);
+        Ok((res, Shape::from(shape)))
+    }
+
+    fn bwd(&self, arg: &Tensor, res: &Tensor, grad_res: &Tensor) -> Result<Option<Tensor>> {
+        // the gradient of logsumexp is the softmax of its input
+        let probs = arg.broadcast_sub(&res.unsqueeze(self.dim)?)?.exp()?;
+        Ok(Some(probs.broadcast_mul(&grad_res.unsqueeze(self.dim)?)?))
+    }
+}
+
+/// The op behind [`log_softmax`]
+struct LogSoftmaxOp {
+    dim: usize,
+}
+
+impl CustomOp1 for LogSoftmaxOp {
+    fn name(&self) -> &'static str {
+        "logaddexp-log-softmax"
+    }
+
+    fn cpu_fwd(&self, storage: &CpuStorage, layout: &Layout) -> Result<(CpuStorage, Shape)> {
+        let Some((start, end)) = layout.contiguous_offsets() else {
+            bail!("log_softmax requires a contiguous tensor")
+        };
+        let dims = layout.dims();
+        let res = match storage {
+            CpuStorage::F32(values) => {
+                CpuStorage::F32(lane_log_softmax(&values[start..end], dims, self.dim))
+            }
+            CpuStorage::F64(values) => {
+                CpuStorage::F64(lane_log_softmax(&values[start..end], dims, self.dim))
+            }
+            _ => bail!("log_softmax doesn't support {:?}", storage.dtype()),
+        };
+        Ok((res, layout.shape().clone()))
+    }
+
+    fn bwd(&self, _arg: &Tensor, res: &Tensor, grad_res: &Tensor) -> Result<Option<Tensor>> {
+        let total = grad_res.sum_keepdim(self.dim)?;
+        Ok(Some(grad_res.sub(&res.exp()?.broadcast_mul(&total)?)?))
+    }
+}
+
+/// Compute `ln_sum_exp` along `dim`, removing that dimension
+///
+/// Reducing a dimension of size zero produces negative infinity.
+///
+/// # Errors
+///
+/// If `dim` is out of range, or the tensor isn't an `f32` or `f64` tensor on the CPU.
+// dims are taken by value like candle's own reductions
+#[allow(clippy::needless_pass_by_value)]
+pub fn logsumexp<D: Dim>(tensor: &Tensor, dim: D) -> Result<Tensor> {
+    let dim = dim.to_index(tensor.shape(), "logsumexp")?;
+    tensor.contiguous()?.apply_op1(LogSumExpOp { dim })
+}
+
+/// Subtract the `ln_sum_exp` along `dim` from every value
+///
+/// # Errors
+///
+/// If `dim` is out of range, or the tensor isn't an `f32` or `f64` tensor on the CPU.
+// dims are taken by value like candle's own reductions
+#[allow(clippy::needless_pass_by_value)]
+pub fn log_softmax<D: Dim>(tensor: &Tensor, dim: D) -> Result<Tensor> {
+    let dim = dim.to_index(tensor.shape(), "log_softmax")?;
+    tensor.contiguous()?.apply_op1(LogSoftmaxOp { dim })
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{log_softmax, logsumexp};
+    use crate::LogSumExp;
+    use candle_core::{DType, Device, Tensor, Var, D};
+
+    #[test]
+    fn test_logsumexp() {
+        let vals: Vec<f64> = (0..24).map(|n| f64::from(n).sin() * 10.0).collect();
+        let tensor = Tensor::from_vec(vals.clone(), (2, 3, 4), &Device::Cpu).unwrap();
+
+        let res: Vec<Vec<f64>> = logsumexp(&tensor, D::Minus1).unwrap().to_vec2().unwrap();
+        assert_close!(res[1][2], vals[20..24].iter().copied().ln_sum_exp());
+
+        let res: Vec<Vec<f64>> = logsumexp(&tensor, 1).unwrap().to_vec2().unwrap();
+        let lane = [vals[13], vals[17], vals[21]];
+        assert_close!(res[1][1], lane.into_iter().ln_sum_exp());
+
+        let transposed = tensor.transpose(0, 2).unwrap();
+        let res: Vec<Vec<f64>> = logsumexp(&transposed, 2).unwrap().to_vec2().unwrap();
+        assert_close!(res[1][2], [vals[9], vals[21]].into_iter().ln_sum_exp());
+
+        let empty = Tensor::zeros((2, 0), DType::F32, &Device::Cpu).unwrap();
+        let res: Vec<f32> = logsumexp(&empty, 1).unwrap().to_vec1().unwrap();
+        assert_eq!(res, [f32::NEG_INFINITY; 2]);
+
+        let ints = Tensor::new(&[1_u32, 2], &Device::Cpu).unwrap();
+        assert!(logsumexp(&ints, 0).is_err());
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let tensor = Tensor::new(&[[0_f32, 0.0], [f32::NEG_INFINITY, 1.0]], &Device::Cpu).unwrap();
+        let res: Vec<Vec<f32>> = log_softmax(&tensor, 1).unwrap().to_vec2().unwrap();
+        assert_close!(res[0][0], 0.5_f32.ln());
+        assert_close!(res[0][1], 0.5_f32.ln());
+        assert_eq!(res[1], [f32::NEG_INFINITY, 0.0]);
+
+        let res: Vec<Vec<f32>> = log_softmax(&tensor, 0).unwrap().to_vec2().unwrap();
+        assert_eq!(res[0][0], 0.0);
+        assert_close!(res[0][1], -1_f32.exp().ln_1p());
+    }
+
+    #[test]
+    fn test_backward() {
+        let var = Var::new(&[1_f64, 2.0, 3.0], &Device::Cpu).unwrap();
+        let grads = logsumexp(&var, 0).unwrap().backward().unwrap();
+        let grad: Vec<f64> = grads.get(&var).unwrap().to_vec1().unwrap();
+        let total = [1_f64, 2.0, 3.0].into_iter().ln_sum_exp();
+        assert_close!(grad[0], (1.0 - total).exp());
+
+        let grads = log_softmax(&var, 0)
+            .unwrap()
+            .sum_all()
+            .unwrap()
+            .backward()
+            .unwrap();
+        let grad: Vec<f64> = grads.get(&var).unwrap().to_vec1().unwrap();
+        assert_close!(grad[2], 1.0 - 3.0 * (3.0 - total).exp());
+    }
+}
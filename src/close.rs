@@ -0,0 +1,50 @@
+//! Approximate equality of log values
+use num_traits::Float;
+
+/// True if two log values are equal within a tolerance
+///
+/// This is true if `|a - b| <= atol + rtol * |b|`, the same test as `numpy.isclose`, or if `a`
+/// and `b` are equal, so matching infinities, e.g. two log probabilities of zero, compare as close
+/// even though their difference is NaN. Otherwise an infinity isn't close to anything, and NaN is
+/// never close to anything.
+///
+/// Since the values are logs, `atol` bounds the ratio of the exponentials: an `atol` of `1e-9`
+/// means the probabilities agree to a relative tolerance of about `1e-9`, regardless of how small
+/// they are. `rtol` is mostly useful for large log values whose rounding error grows with their
+/// magnitude.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::{ln_is_close, LogAddExp};
+///
+/// assert!(ln_is_close(1_f64.ln_add_exp(1.0), 1.0 + 2_f64.ln(), 1e-12, 1e-12));
+/// assert!(ln_is_close(f64::NEG_INFINITY, f64::NEG_INFINITY, 0.0, 0.0));
+/// assert!(!ln_is_close(-1000.0, -1000.1, 1e-9, 1e-9));
+/// ```
+#[must_use]
+pub fn ln_is_close<T>(a: T, b: T, rtol: T, atol: T) -> bool
+where
+    T: Float,
+{
+    a == b || (a.is_finite() && b.is_finite() && (a - b).abs() <= atol + rtol * b.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ln_is_close;
+
+    #[test]
+    fn test_ln_is_close() {
+        assert!(ln_is_close(1.0, 1.0 + 1e-10, 0.0, 1e-9));
+        assert!(!ln_is_close(1.0, 1.0 + 1e-8, 0.0, 1e-9));
+        assert!(ln_is_close(1e6_f32, 1e6 + 1.0, 1e-5, 0.0));
+        assert!(!ln_is_close(1e6_f32, 1e6 + 1.0, 1e-7, 0.0));
+
+        assert!(ln_is_close(f64::INFINITY, f64::INFINITY, 0.0, 0.0));
+        assert!(!ln_is_close(f64::NEG_INFINITY, f64::INFINITY, 1.0, 1.0));
+        assert!(!ln_is_close(f64::NEG_INFINITY, -1e300, 1.0, 1.0));
+        assert!(!ln_is_close(f64::NAN, f64::NAN, 1.0, 1.0));
+        assert!(!ln_is_close(0.0, f64::NAN, 1.0, 1.0));
+    }
+}
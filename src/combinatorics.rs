@@ -0,0 +1,342 @@
+//! Log binomial and multinomial coefficients
+//!
+//! Differences of `ln Γ` lose all their precision for large arguments, since `ln Γ(n + 1)` is far
+//! larger than the coefficient. Instead these use Stirling's approximation with an explicit error
+//! term, so the large parts cancel analytically and only the small remainders are computed,
+//! which keeps the result accurate to near machine precision even for counts near `u64::MAX`.
+use super::ln_sum_exp_slice;
+use super::math::{cast, ln, ln_1p};
+use num_traits::Float;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// The `g` parameter of the Lanczos approximation
+const LANCZOS_G: f64 = 7.0;
+
+/// The coefficients of the Lanczos approximation for `g = 7`
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Below this the Stirling error is computed from `ln Γ` instead of its series
+const STIRLING_SERIES_MIN: u64 = 16;
+
+/// `ln Γ(x)` for `x >= 1` with the Lanczos approximation
+fn ln_gamma<T: Float>(x: T) -> T {
+    let x = x - T::one();
+    let sum = LANCZOS[1..]
+        .iter()
+        .zip(1..)
+        .fold(cast::<T>(LANCZOS[0]), |acc, (&coef, ind)| {
+            acc + cast::<T>(coef) / (x + cast(ind))
+        });
+    let half = cast::<T>(0.5);
+    let base = x + cast(LANCZOS_G) + half;
+    half * ln(cast::<T>(2.0 * PI)) + (x + half) * ln(base) - base + ln(sum)
+}
+
+/// The error of Stirling's approximation, `ln(n!) - (n ln(n) - n + ln(2 π n) / 2)`, for `n >= 1`
+fn stirling_error<T: Float>(n: u64) -> T {
+    let val: T = cast(n);
+    if n < STIRLING_SERIES_MIN {
+        let half = cast::<T>(0.5);
+        ln_gamma(val + T::one()) - (val * ln(val) - val + half * ln(cast::<T>(2.0 * PI) * val))
+    } else {
+        let inv = val.recip();
+        let inv_sq = inv * inv;
+        let series = cast::<T>(1.0 / 12.0)
+            - inv_sq
+                * (cast::<T>(1.0 / 360.0)
+                    - inv_sq * (cast::<T>(1.0 / 1260.0) - inv_sq * cast(1.0 / 1680.0)));
+        series * inv
+    }
+}
+
+/// Compute the log of the binomial coefficient `n` choose `k`
+///
+/// If `k` is larger than `n` the coefficient is zero, so this returns negative infinity.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_binomial;
+///
+/// let res: f64 = ln_binomial(10, 3); // 120_f64.ln()
+/// let large: f64 = ln_binomial(1 << 40, 1 << 39); // doesn't overflow
+/// ```
+#[must_use]
+pub fn ln_binomial<T>(n: u64, k: u64) -> T
+where
+    T: Float,
+{
+    if k > n {
+        return T::neg_infinity();
+    }
+    // the smaller of the two is more accurate, and makes the result symmetric
+    let (k, rest) = (k.min(n - k), k.max(n - k));
+    if k == 0 {
+        T::zero()
+    } else {
+        let (nf, kf, rf): (T, T, T) = (cast(n), cast(k), cast(rest));
+        let frac = kf / nf;
+        // the n ln(n) - k ln(k) - (n - k) ln(n - k) part of Stirling's approximation
+        let entropy = -(kf * ln(frac) + rf * ln_1p(-frac));
+        let half = cast::<T>(0.5);
+        entropy - half * ln(cast::<T>(2.0 * PI) * kf * (rf / nf)) + stirling_error(n)
+            - stirling_error(k)
+            - stirling_error(rest)
+    }
+}
+
+/// Compute the log of the multinomial coefficient of some counts
+///
+/// This is `ln(n! / Π c!)` where `n = Σ c`, the number of distinct orderings of the counts. It's
+/// computed as a sum of [`ln_binomial`] terms, so it's accurate for large counts.
+///
+/// # Panics
+///
+/// If the total count overflows a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_multinomial;
+///
+/// let res: f64 = ln_multinomial(&[2, 3, 5]); // 2520_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_multinomial<T>(counts: &[u64]) -> T
+where
+    T: Float,
+{
+    let mut total = 0_u64;
+    counts.iter().fold(T::zero(), |acc, &count| {
+        total = total.checked_add(count).expect("total count overflowed");
+        acc + ln_binomial(total, count)
+    })
+}
+
+/// The log probabilities given to [`multinomial_ln_pmf`] don't sum to one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotNormalized<T> {
+    /// The `ln_sum_exp` of the log probabilities, which should be zero
+    pub ln_total: T,
+}
+
+impl<T: fmt::Display> fmt::Display for NotNormalized<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "log probabilities aren't normalized, their ln_sum_exp is {}",
+            self.ln_total
+        )
+    }
+}
+
+impl<T: fmt::Debug + fmt::Display> Error for NotNormalized<T> {}
+
+/// `ln(n! / Π c!) + Σ c ln(p)`, skipping zero counts so they don't multiply infinite log
+/// probabilities
+fn multinomial_terms<T: Float>(counts: &[u64], log_probs: &[T], shift: T) -> T {
+    assert_eq!(
+        counts.len(),
+        log_probs.len(),
+        "counts and log probabilities must have the same length"
+    );
+    counts
+        .iter()
+        .zip(log_probs)
+        .filter(|(&count, _)| count != 0)
+        .fold(ln_multinomial(counts), |acc, (&count, &log_prob)| {
+            acc + cast::<T>(count) * (log_prob - shift)
+        })
+}
+
+/// Compute the log probability mass of counts under a multinomial distribution
+///
+/// This is `ln(n! / Π c!) + Σ c ln(p)` with the coefficient from [`ln_multinomial`], so it's
+/// accurate for large counts. Categories with a count of zero contribute nothing, even if their
+/// probability is zero. The log probabilities must be normalized: if their
+/// [`ln_sum_exp_slice`] is further than `tol` from zero, this returns an error. Use
+/// [`multinomial_ln_pmf_normalized`] to normalize them instead.
+///
+/// # Errors
+///
+/// If `log_probs` doesn't normalize to within `tol`.
+///
+/// # Panics
+///
+/// If `counts` and `log_probs` have different lengths, or the total count overflows a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::multinomial_ln_pmf;
+///
+/// let log_probs = [0.5_f64.ln(), 0.25_f64.ln(), 0.25_f64.ln()];
+/// multinomial_ln_pmf(&[1, 1, 0], &log_probs, 1e-12)?; // 0.25_f64.ln()
+/// # Ok::<(), logaddexp::NotNormalized<f64>>(())
+/// ```
+pub fn multinomial_ln_pmf<T>(counts: &[u64], log_probs: &[T], tol: T) -> Result<T, NotNormalized<T>>
+where
+    T: Float,
+{
+    let ln_total = ln_sum_exp_slice(log_probs);
+    if ln_total.abs() <= tol {
+        Ok(multinomial_terms(counts, log_probs, T::zero()))
+    } else {
+        Err(NotNormalized { ln_total })
+    }
+}
+
+/// Compute the log probability mass of counts under a multinomial distribution with unnormalized
+/// log weights
+///
+/// This is [`multinomial_ln_pmf`] after normalizing `log_weights` with [`ln_sum_exp_slice`],
+/// without allocating the normalized weights.
+///
+/// # Panics
+///
+/// If `counts` and `log_weights` have different lengths, or the total count overflows a `u64`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::multinomial_ln_pmf_normalized;
+///
+/// multinomial_ln_pmf_normalized(&[1, 1, 0], &[2_f64.ln(), 0.0, 0.0]); // 0.25_f64.ln()
+/// ```
+#[must_use]
+pub fn multinomial_ln_pmf_normalized<T>(counts: &[u64], log_weights: &[T]) -> T
+where
+    T: Float,
+{
+    multinomial_terms(counts, log_weights, ln_sum_exp_slice(log_weights))
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        ln_binomial, ln_gamma, ln_multinomial, multinomial_ln_pmf, multinomial_ln_pmf_normalized,
+        NotNormalized,
+    };
+
+    /// `ln(n choose k)` as a sum of logs
+    fn ln_binomial_sum(n: u64, k: u64) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        (1..=k)
+            .map(|ind| ((n - k + ind) as f64 / ind as f64).ln())
+            .sum()
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        let mut fact = 1.0;
+        for n in 1..30_u8 {
+            assert_close!(
+                ln_gamma(f64::from(n)),
+                f64::ln(fact),
+                rtol = 1e-13,
+                atol = 1e-14
+            );
+            fact *= f64::from(n);
+        }
+        assert_close!(
+            ln_gamma(1.5),
+            0.5 * std::f64::consts::PI.ln() - 2_f64.ln(),
+            atol = 1e-14
+        );
+    }
+
+    #[test]
+    fn test_ln_binomial() {
+        assert_close!(ln_binomial::<f64>(10, 3), 120_f64.ln(), rtol = 1e-14);
+        assert_close!(
+            ln_binomial::<f64>(50, 25),
+            126_410_606_437_752_f64.ln(),
+            rtol = 1e-14
+        );
+        for (n, k) in [(2, 1), (20, 7), (100, 1), (1000, 500), (1_000_000, 17)] {
+            assert_close!(
+                ln_binomial::<f64>(n, k),
+                ln_binomial_sum(n, k),
+                rtol = 1e-12
+            );
+            assert_eq!(ln_binomial::<f64>(n, k), ln_binomial::<f64>(n, n - k));
+        }
+        let big = 1_000_000_000_000_000;
+        assert_close!(ln_binomial::<f64>(big, 1), 1e15_f64.ln(), rtol = 1e-14);
+        assert_close!(
+            ln_binomial::<f64>(big, 3),
+            ln_binomial_sum(big, 3),
+            rtol = 1e-12
+        );
+        assert!(ln_binomial::<f64>(u64::MAX, u64::MAX / 2).is_finite());
+        assert_close!(ln_binomial::<f32>(30, 15), 155_117_520_f32.ln());
+
+        assert_eq!(ln_binomial::<f64>(5, 0), 0.0);
+        assert_eq!(ln_binomial::<f64>(5, 5), 0.0);
+        assert_eq!(ln_binomial::<f64>(0, 0), 0.0);
+        assert_eq!(ln_binomial::<f64>(3, 4), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_ln_multinomial() {
+        assert_close!(
+            ln_multinomial::<f64>(&[2, 3, 5]),
+            2520_f64.ln(),
+            rtol = 1e-14
+        );
+        assert_close!(
+            ln_multinomial::<f64>(&[7, 0, 4]),
+            ln_binomial::<f64>(11, 4),
+            rtol = 1e-14
+        );
+        assert_eq!(ln_multinomial::<f64>(&[]), 0.0);
+        assert_eq!(ln_multinomial::<f64>(&[12]), 0.0);
+    }
+
+    #[test]
+    fn test_multinomial_ln_pmf() {
+        let probs: [f64; 3] = [0.2, 0.3, 0.5];
+        let log_probs: Vec<f64> = probs.iter().map(|prob| prob.ln()).collect();
+        let expected = (2520.0 * 0.2_f64.powi(2) * 0.3_f64.powi(3) * 0.5_f64.powi(5)).ln();
+        let res = multinomial_ln_pmf(&[2, 3, 5], &log_probs, 1e-12).unwrap();
+        assert_close!(res, expected, rtol = 1e-13);
+
+        let weights: Vec<f64> = log_probs.iter().map(|val| val + 40.0).collect();
+        assert_eq!(
+            multinomial_ln_pmf(&[2, 3, 5], &weights, 1e-12),
+            Err(NotNormalized { ln_total: 40.0 })
+        );
+        assert_close!(
+            multinomial_ln_pmf_normalized(&[2, 3, 5], &weights),
+            expected,
+            rtol = 1e-13
+        );
+
+        // a million draws overflow the coefficient and the probabilities in linear space
+        let res = multinomial_ln_pmf(&[500_000, 500_000], &[0.5_f64.ln(); 2], 1e-12).unwrap();
+        assert_close!(
+            res,
+            ln_binomial::<f64>(1_000_000, 500_000) - 1e6 * 2_f64.ln()
+        );
+
+        let zero = [0.0, f64::NEG_INFINITY];
+        assert_eq!(multinomial_ln_pmf(&[4, 0], &zero, 0.0), Ok(0.0));
+        assert_eq!(
+            multinomial_ln_pmf(&[4, 1], &zero, 0.0),
+            Ok(f64::NEG_INFINITY)
+        );
+    }
+}
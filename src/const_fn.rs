@@ -0,0 +1,158 @@
+//! `const` versions of the scalar kernels
+//!
+//! The standard library's `exp` and `ln_1p` aren't `const`, so these use software versions that
+//! are only accurate on the ranges `ln_add_exp` needs.
+use std::f64::consts::LN_2;
+
+/// `exp(x)` for `x <= 0`
+pub(crate) const fn exp_nonpositive(x: f64) -> f64 {
+    if x < -746.0 {
+        return 0.0;
+    }
+    // x = k ln(2) + r with |r| <= ln(2) / 2, casting truncates towards zero so this rounds
+    #[allow(clippy::cast_possible_truncation)]
+    let k = (x / LN_2 - 0.5) as i64;
+    #[allow(clippy::cast_precision_loss)]
+    let r = x - k as f64 * LN_2;
+
+    // taylor series of exp(r), 14 terms are enough for |r| <= 0.35
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    let mut n = 1.0;
+    while n < 15.0 {
+        term = term * r / n;
+        sum += term;
+        n += 1.0;
+    }
+
+    // scale by 2^k in two steps so subnormal results don't underflow early
+    let half = k / 2;
+    sum * pow2(half) * pow2(k - half)
+}
+
+/// `2^k` for `-1022 <= k <= 0`
+const fn pow2(k: i64) -> f64 {
+    #[allow(clippy::cast_sign_loss)]
+    let bits = ((k + 1023) as u64) << 52;
+    f64::from_bits(bits)
+}
+
+/// `ln_1p(y)` for `0 <= y <= 1`
+pub(crate) const fn ln_1p_unit(y: f64) -> f64 {
+    // ln(1 + y) = 2 atanh(s) with s = y / (2 + y) <= 1/3
+    let s = y / (2.0 + y);
+    let s2 = s * s;
+    let mut power = s;
+    let mut sum = 0.0;
+    let mut n = 1.0;
+    while n < 40.0 {
+        sum += power / n;
+        power *= s2;
+        n += 2.0;
+    }
+    2.0 * sum
+}
+
+/// A `const` version of [`ln_add_exp`][crate::LogAddExp::ln_add_exp] for `f64`
+///
+/// This handles infinities and NaN the same way, and is accurate to within a few ulps, so it can
+/// be used to build tables of combined log probabilities at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::const_ln_add_exp_f64;
+///
+/// const LN_TWO: f64 = const_ln_add_exp_f64(0.0, 0.0);
+/// ```
+#[must_use]
+#[allow(clippy::float_cmp)]
+pub const fn const_ln_add_exp_f64(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        a + LN_2
+    } else if a > b {
+        a + ln_1p_unit(exp_nonpositive(b - a))
+    } else {
+        b + ln_1p_unit(exp_nonpositive(a - b))
+    }
+}
+
+/// A `const` version of [`ln_add_exp`][crate::LogAddExp::ln_add_exp] for `f32`
+///
+/// This computes in `f64` and rounds the result, see [`const_ln_add_exp_f64`].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::const_ln_add_exp_f32;
+///
+/// const TABLE: [f32; 3] = [
+///     const_ln_add_exp_f32(0.0, 0.0),
+///     const_ln_add_exp_f32(0.0, -1.0),
+///     const_ln_add_exp_f32(0.0, -2.0),
+/// ];
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub const fn const_ln_add_exp_f32(a: f32, b: f32) -> f32 {
+    const_ln_add_exp_f64(a as f64, b as f64) as f32
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{const_ln_add_exp_f32, const_ln_add_exp_f64};
+    use crate::LogAddExp;
+
+    #[test]
+    fn test_const_ln_add_exp_f64() {
+        const LN_TWO: f64 = const_ln_add_exp_f64(0.0, 0.0);
+        assert_eq!(LN_TWO, 2_f64.ln());
+
+        for i in -800..800 {
+            let diff = f64::from(i) / 8.0;
+            for base in [-1e3, -1.0, 0.0, 3.5, 1e5] {
+                let expected = base.ln_add_exp(base + diff);
+                assert_close!(
+                    const_ln_add_exp_f64(base, base + diff),
+                    expected,
+                    rtol = 1e-14,
+                    atol = 1e-14
+                );
+            }
+        }
+        assert_close!(
+            const_ln_add_exp_f64(0.0, -740.0),
+            (-740_f64).exp(),
+            rtol = 1e-12
+        );
+
+        assert_eq!(const_ln_add_exp_f64(f64::INFINITY, 0.0), f64::INFINITY);
+        assert_eq!(const_ln_add_exp_f64(0.0, f64::INFINITY), f64::INFINITY);
+        assert_eq!(const_ln_add_exp_f64(f64::NEG_INFINITY, 1.0), 1.0);
+        assert_eq!(
+            const_ln_add_exp_f64(f64::NEG_INFINITY, f64::NEG_INFINITY),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            const_ln_add_exp_f64(f64::NEG_INFINITY, f64::INFINITY),
+            f64::INFINITY
+        );
+        assert!(const_ln_add_exp_f64(f64::NAN, 1.0).is_nan());
+        assert!(const_ln_add_exp_f64(f64::INFINITY, f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn test_const_ln_add_exp_f32() {
+        for i in -100..100 {
+            let diff = f32::from(i16::try_from(i).unwrap()) / 4.0;
+            assert_close!(
+                const_ln_add_exp_f32(1.0, 1.0 + diff),
+                1_f32.ln_add_exp(1.0 + diff),
+                rtol = 1e-6
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! `ln_sum_exp` of values with multiplicities
+use super::math::{cast, ln};
+use super::StreamingLnSumExp;
+use num_traits::Float;
+
+/// Compute `ln Σ cᵢ exp(xᵢ)` from `(value, count)` pairs
+///
+/// This is the same as the `ln_sum_exp` of every value repeated by its count, but each count is
+/// folded in as `ln(count)` in a single pass, so aggregated data doesn't need to be expanded.
+/// Values with a count of zero are skipped entirely, so even an infinite or NaN value contributes
+/// nothing. No pairs, or only zero counts, return negative infinity.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_counts;
+///
+/// ln_sum_exp_counts([(1_f64.ln(), 3), (5_f64.ln(), 2)]); // 13_f64.ln()
+/// ```
+pub fn ln_sum_exp_counts<I, T>(pairs: I) -> T
+where
+    I: IntoIterator<Item = (T, usize)>,
+    T: Float,
+{
+    let acc: StreamingLnSumExp<T> = pairs
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(value, count)| value + ln(cast::<T>(count)))
+        .collect();
+    acc.ln_sum_exp()
+}
+
+/// Compute `ln Σ counts[i] exp(values[i])` from parallel slices
+///
+/// See [`ln_sum_exp_counts`] for details.
+///
+/// # Panics
+///
+/// If `values` and `counts` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_with_counts;
+///
+/// ln_sum_exp_with_counts(&[1_f64.ln(), 5_f64.ln()], &[3, 2]); // 13_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_sum_exp_with_counts<T>(values: &[T], counts: &[usize]) -> T
+where
+    T: Float,
+{
+    assert_eq!(
+        values.len(),
+        counts.len(),
+        "values and counts must have the same length"
+    );
+    ln_sum_exp_counts(values.iter().copied().zip(counts.iter().copied()))
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_counts, ln_sum_exp_with_counts};
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_counts() {
+        let vals: Vec<f64> = (0..20).map(|n| f64::from(n).sin() * 100.0).collect();
+        let counts: Vec<usize> = (0..20).map(|n| n % 4).collect();
+        let expanded = vals
+            .iter()
+            .zip(&counts)
+            .flat_map(|(&val, &count)| std::iter::repeat_n(val, count));
+        let expected = expanded.ln_sum_exp();
+        assert_close!(ln_sum_exp_with_counts(&vals, &counts), expected);
+        assert_close!(
+            ln_sum_exp_counts(vals.iter().copied().zip(counts.iter().copied())),
+            expected
+        );
+
+        // large counts don't overflow
+        assert_close!(
+            ln_sum_exp_counts([(-1000_f32, 1_000_000)]),
+            -1000.0 + 1e6_f32.ln()
+        );
+    }
+
+    #[test]
+    fn test_ln_sum_exp_counts_special() {
+        assert_eq!(ln_sum_exp_with_counts::<f64>(&[], &[]), f64::NEG_INFINITY);
+        assert_eq!(
+            ln_sum_exp_with_counts(&[f64::INFINITY, f64::NAN, 1.0], &[0, 0, 0]),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            ln_sum_exp_with_counts(&[f64::INFINITY, 1.0], &[2, 1]),
+            f64::INFINITY
+        );
+        assert!(ln_sum_exp_with_counts(&[f64::NAN, 1.0], &[1, 1]).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "values and counts must have the same length")]
+    fn test_ln_sum_exp_with_counts_mismatch() {
+        let _ = ln_sum_exp_with_counts(&[1.0, 2.0], &[1]);
+    }
+}
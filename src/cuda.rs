@@ -0,0 +1,402 @@
+//! Row-wise `ln_sum_exp` and log softmax on NVIDIA GPUs with CUDA
+//!
+//! The kernels are compiled at runtime with NVRTC and work on row-major matrices in device
+//! memory. Each row is reduced by one block, where every thread keeps a running (max, scaled
+//! sum) state like [`StreamingLnSumExp`][crate::StreamingLnSumExp] so the input is only read
+//! once, and the states are merged with warp shuffles. Work is queued on whatever
+//! [`CudaStream`] is passed in, so independent batches can overlap on separate streams.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use cudarc::driver::CudaContext;
+//! use logaddexp::cuda::{self, CudaKernels};
+//!
+//! let ctx = CudaContext::new(0).unwrap();
+//! let stream = ctx.default_stream();
+//! let kernels = CudaKernels::new(&ctx).unwrap();
+//!
+//! let host = cuda::pinned_from_slice(&ctx, &[1.0_f32, 2.0, 3.0, 4.0]).unwrap();
+//! let input = stream.clone_htod(&host).unwrap();
+//! let output = kernels.ln_sum_exp_rows(&stream, &input, (2, 2)).unwrap();
+//! let res = stream.clone_dtoh(&output).unwrap(); // one value per row
+//! ```
+use cudarc::driver::{
+    CudaContext, CudaFunction, CudaSlice, CudaStream, DeviceRepr, DriverError, LaunchConfig,
+    PinnedHostSlice, PushKernelArg, ValidAsZeroBits,
+};
+use cudarc::nvrtc::{self, CompileError};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Threads per block, a multiple of the warp size
+const BLOCK: u32 = 256;
+
+const KERNELS: &str = r#"
+__device__ inline float neg_inf(float) { return __int_as_float(0xff800000); }
+__device__ inline double neg_inf(double) { return __longlong_as_double(0xfff0000000000000ULL); }
+__device__ inline float fexp(float x) { return expf(x); }
+__device__ inline double fexp(double x) { return exp(x); }
+__device__ inline float flog(float x) { return logf(x); }
+__device__ inline double flog(double x) { return log(x); }
+
+// add the state (omax, osum) into (max, sum), keeping nan sticky
+template <typename T>
+__device__ inline void combine(T &max, T &sum, T omax, T osum) {
+    if (omax > max) {
+        sum = sum * fexp(max - omax) + osum;
+        max = omax;
+    } else if (omax == max) {
+        sum += osum;
+    } else {
+        sum += osum * fexp(omax - max);
+    }
+}
+
+// ln_sum_exp of a row, the result is only valid in thread zero
+template <typename T>
+__device__ T row_ln_sum_exp(const T *row, unsigned int cols) {
+    __shared__ T maxes[32];
+    __shared__ T sums[32];
+    T max = neg_inf(T());
+    T sum = 0;
+    for (unsigned int i = threadIdx.x; i < cols; i += blockDim.x) {
+        combine(max, sum, row[i], T(1));
+    }
+    for (int offset = 16; offset > 0; offset /= 2) {
+        T omax = __shfl_down_sync(0xffffffff, max, offset);
+        T osum = __shfl_down_sync(0xffffffff, sum, offset);
+        combine(max, sum, omax, osum);
+    }
+    unsigned int warp = threadIdx.x / 32;
+    unsigned int lane = threadIdx.x % 32;
+    if (lane == 0) {
+        maxes[warp] = max;
+        sums[warp] = sum;
+    }
+    __syncthreads();
+    if (warp == 0) {
+        unsigned int warps = blockDim.x / 32;
+        max = lane < warps ? maxes[lane] : neg_inf(T());
+        sum = lane < warps ? sums[lane] : T(0);
+        for (int offset = 16; offset > 0; offset /= 2) {
+            T omax = __shfl_down_sync(0xffffffff, max, offset);
+            T osum = __shfl_down_sync(0xffffffff, sum, offset);
+            combine(max, sum, omax, osum);
+        }
+    }
+    if (sum == T(0)) {
+        return neg_inf(T());
+    } else if (isinf(max) && (max > T(0) || !isnan(sum))) {
+        // positive infinity wins over nan, negative infinity doesn't
+        return max;
+    } else {
+        return max + flog(sum);
+    }
+}
+
+template <typename T>
+__device__ void ln_sum_exp(const T *input, T *output, unsigned int cols) {
+    T res = row_ln_sum_exp(input + (unsigned long long)blockIdx.x * cols, cols);
+    if (threadIdx.x == 0) {
+        output[blockIdx.x] = res;
+    }
+}
+
+template <typename T>
+__device__ void log_softmax(const T *input, T *output, unsigned int cols) {
+    __shared__ T total;
+    unsigned long long base = (unsigned long long)blockIdx.x * cols;
+    T res = row_ln_sum_exp(input + base, cols);
+    if (threadIdx.x == 0) {
+        total = res;
+    }
+    __syncthreads();
+    for (unsigned int i = threadIdx.x; i < cols; i += blockDim.x) {
+        output[base + i] = input[base + i] - total;
+    }
+}
+
+extern "C" __global__ void ln_sum_exp_f32(const float *input, float *output, unsigned int cols) {
+    ln_sum_exp(input, output, cols);
+}
+
+extern "C" __global__ void ln_sum_exp_f64(const double *input, double *output, unsigned int cols) {
+    ln_sum_exp(input, output, cols);
+}
+
+extern "C" __global__ void log_softmax_f32(const float *input, float *output, unsigned int cols) {
+    log_softmax(input, output, cols);
+}
+
+extern "C" __global__ void log_softmax_f64(const double *input, double *output, unsigned int cols) {
+    log_softmax(input, output, cols);
+}
+"#;
+
+/// An error from compiling or running the kernels
+#[derive(Debug)]
+pub enum CudaError {
+    /// NVRTC failed to compile the kernels
+    Compile(CompileError),
+    /// A CUDA driver call failed
+    Driver(DriverError),
+    /// The matrix has more rows than can be launched, or longer rows than can be indexed
+    Shape,
+}
+
+impl fmt::Display for CudaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CudaError::Compile(err) => write!(f, "failed to compile kernels: {err}"),
+            CudaError::Driver(err) => write!(f, "cuda driver error: {err}"),
+            CudaError::Shape => write!(f, "matrix shape is too large"),
+        }
+    }
+}
+
+impl Error for CudaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CudaError::Compile(err) => Some(err),
+            CudaError::Driver(err) => Some(err),
+            CudaError::Shape => None,
+        }
+    }
+}
+
+impl From<CompileError> for CudaError {
+    fn from(err: CompileError) -> Self {
+        CudaError::Compile(err)
+    }
+}
+
+impl From<DriverError> for CudaError {
+    fn from(err: DriverError) -> Self {
+        CudaError::Driver(err)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+/// Float types that the kernels are instantiated for
+pub trait CudaFloat: Copy + DeviceRepr + ValidAsZeroBits + private::Sealed {
+    #[doc(hidden)]
+    fn functions(kernels: &CudaKernels) -> (&CudaFunction, &CudaFunction);
+}
+
+impl CudaFloat for f32 {
+    fn functions(kernels: &CudaKernels) -> (&CudaFunction, &CudaFunction) {
+        (&kernels.ln_sum_exp_f32, &kernels.log_softmax_f32)
+    }
+}
+
+impl CudaFloat for f64 {
+    fn functions(kernels: &CudaKernels) -> (&CudaFunction, &CudaFunction) {
+        (&kernels.ln_sum_exp_f64, &kernels.log_softmax_f64)
+    }
+}
+
+/// Compiled kernels for row-wise `ln_sum_exp` and log softmax
+///
+/// Compiling takes a while, so create this once per context and reuse it.
+#[derive(Debug)]
+pub struct CudaKernels {
+    ln_sum_exp_f32: CudaFunction,
+    ln_sum_exp_f64: CudaFunction,
+    log_softmax_f32: CudaFunction,
+    log_softmax_f64: CudaFunction,
+}
+
+impl CudaKernels {
+    /// Compile the kernels and load them into a context
+    ///
+    /// # Errors
+    ///
+    /// If NVRTC can't compile the kernels or the module can't be loaded.
+    pub fn new(ctx: &Arc<CudaContext>) -> Result<Self, CudaError> {
+        let module = ctx.load_module(nvrtc::compile_ptx(KERNELS)?)?;
+        Ok(CudaKernels {
+            ln_sum_exp_f32: module.load_function("ln_sum_exp_f32")?,
+            ln_sum_exp_f64: module.load_function("ln_sum_exp_f64")?,
+            log_softmax_f32: module.load_function("log_softmax_f32")?,
+            log_softmax_f64: module.load_function("log_softmax_f64")?,
+        })
+    }
+
+    /// Compute `ln_sum_exp` of every row of a row-major `(rows, cols)` matrix
+    ///
+    /// The work is queued on `stream`, and the result is a new device buffer with one value per
+    /// row. Empty rows are negative infinity, and infinities and NaN are handled like
+    /// [`StreamingLnSumExp`][crate::StreamingLnSumExp].
+    ///
+    /// # Errors
+    ///
+    /// If the shape doesn't fit a launch or a driver call fails.
+    ///
+    /// # Panics
+    ///
+    /// If `input` has fewer than `rows * cols` values.
+    pub fn ln_sum_exp_rows<T: CudaFloat>(
+        &self,
+        stream: &Arc<CudaStream>,
+        input: &CudaSlice<T>,
+        shape: (usize, usize),
+    ) -> Result<CudaSlice<T>, CudaError> {
+        let (func, _) = T::functions(self);
+        launch(func, stream, input, shape, shape.0)
+    }
+
+    /// Compute the log softmax of every row of a row-major `(rows, cols)` matrix
+    ///
+    /// This is the same as [`ln_sum_exp_rows`][Self::ln_sum_exp_rows], but the result has the
+    /// same shape as the input, with every value shifted by its row's `ln_sum_exp`.
+    ///
+    /// # Errors
+    ///
+    /// If the shape doesn't fit a launch or a driver call fails.
+    ///
+    /// # Panics
+    ///
+    /// If `input` has fewer than `rows * cols` values.
+    pub fn log_softmax_rows<T: CudaFloat>(
+        &self,
+        stream: &Arc<CudaStream>,
+        input: &CudaSlice<T>,
+        shape: (usize, usize),
+    ) -> Result<CudaSlice<T>, CudaError> {
+        let (_, func) = T::functions(self);
+        launch(func, stream, input, shape, shape.0 * shape.1)
+    }
+}
+
+/// Launch one of the kernels with one block per row
+fn launch<T: CudaFloat>(
+    func: &CudaFunction,
+    stream: &Arc<CudaStream>,
+    input: &CudaSlice<T>,
+    (rows, cols): (usize, usize),
+    output_len: usize,
+) -> Result<CudaSlice<T>, CudaError> {
+    assert!(
+        rows.checked_mul(cols).is_some_and(|len| input.len() >= len),
+        "input is smaller than its shape"
+    );
+    let grid = u32::try_from(rows).map_err(|_| CudaError::Shape)?;
+    let cols = u32::try_from(cols).map_err(|_| CudaError::Shape)?;
+    let mut output = stream.alloc_zeros::<T>(output_len)?;
+    if grid == 0 {
+        return Ok(output);
+    }
+    let cfg = LaunchConfig {
+        grid_dim: (grid, 1, 1),
+        block_dim: (BLOCK, 1, 1),
+        shared_mem_bytes: 0,
+    };
+    let mut builder = stream.launch_builder(func);
+    builder.arg(input).arg(&mut output).arg(&cols);
+    // SAFETY: the kernels take (input, output, cols), only read rows * cols values of input, and
+    // only write output_len values of output
+    unsafe { builder.launch(cfg) }?;
+    Ok(output)
+}
+
+/// Copy values into page-locked host memory for fast asynchronous transfers
+///
+/// Copies to and from pinned memory with [`CudaStream::clone_htod`] and
+/// [`CudaStream::memcpy_dtoh`] don't need to be staged by the driver, so they're faster and can
+/// overlap with kernels on other streams.
+///
+/// # Errors
+///
+/// If the allocation fails.
+pub fn pinned_from_slice<T: CudaFloat>(
+    ctx: &Arc<CudaContext>,
+    values: &[T],
+) -> Result<PinnedHostSlice<T>, DriverError> {
+    // SAFETY: every value is written before the memory is read
+    let mut pinned = unsafe { ctx.alloc_pinned::<T>(values.len()) }?;
+    pinned.as_mut_slice()?.copy_from_slice(values);
+    Ok(pinned)
+}
+
+/// Copy a device buffer into newly allocated page-locked host memory
+///
+/// The copy is queued on `stream`, and reading the result with
+/// [`as_slice`][PinnedHostSlice::as_slice] waits for it to finish.
+///
+/// # Errors
+///
+/// If the allocation or copy fails.
+pub fn pinned_from_device<T: CudaFloat>(
+    stream: &Arc<CudaStream>,
+    values: &CudaSlice<T>,
+) -> Result<PinnedHostSlice<T>, DriverError> {
+    // SAFETY: the memory is overwritten by the copy before it can be read
+    let mut pinned = unsafe { stream.context().alloc_pinned::<T>(values.len()) }?;
+    stream.memcpy_dtoh(values, &mut pinned)?;
+    Ok(pinned)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{pinned_from_device, pinned_from_slice, CudaKernels};
+    use crate::{ln_normalize_collect, ln_sum_exp_slice};
+    use cudarc::driver::CudaContext;
+
+    #[test]
+    fn test_cuda_rows() {
+        // only run when there's a driver and a device
+        // SAFETY: this only checks whether the library can be loaded
+        if !unsafe { cudarc::driver::sys::is_culib_present() } {
+            return;
+        }
+        let Ok(ctx) = CudaContext::new(0) else {
+            return;
+        };
+        let stream = ctx.new_stream().unwrap();
+        let kernels = CudaKernels::new(&ctx).unwrap();
+
+        for (rows, cols) in [(3, 1), (5, 7), (2, 1000), (4, 0)] {
+            let values: Vec<f64> = (0..rows * cols)
+                .map(|n| (f64::from(u16::try_from(n).unwrap()) * 0.37).sin() * 20.0)
+                .collect();
+            let host = pinned_from_slice(&ctx, &values).unwrap();
+            let input = stream.clone_htod(&host).unwrap();
+
+            let output = kernels
+                .ln_sum_exp_rows(&stream, &input, (rows, cols))
+                .unwrap();
+            let res = pinned_from_device(&stream, &output).unwrap();
+            for (row, &val) in values.chunks(cols.max(1)).zip(res.as_slice().unwrap()) {
+                assert_close!(val, ln_sum_exp_slice(row), rtol = 1e-12);
+            }
+
+            let output = kernels
+                .log_softmax_rows(&stream, &input, (rows, cols))
+                .unwrap();
+            let res = stream.clone_dtoh(&output).unwrap();
+            for (row, vals) in values.chunks(cols.max(1)).zip(res.chunks(cols.max(1))) {
+                let (_, expected) = ln_normalize_collect(row);
+                for (&val, &exp) in vals.iter().zip(&expected) {
+                    assert_close!(val, exp, rtol = 1e-12, atol = 1e-12);
+                }
+            }
+        }
+
+        let input = stream
+            .clone_htod(&[f32::INFINITY, 1.0, f32::NEG_INFINITY, f32::NEG_INFINITY])
+            .unwrap();
+        let output = kernels.ln_sum_exp_rows(&stream, &input, (2, 2)).unwrap();
+        assert_eq!(
+            stream.clone_dtoh(&output).unwrap(),
+            [f32::INFINITY, f32::NEG_INFINITY]
+        );
+    }
+}
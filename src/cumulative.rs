@@ -0,0 +1,197 @@
+//! Cumulative `ln_sum_exp` of slices
+use super::LogAddExp;
+use num_traits::Float;
+
+/// Compute the prefix `ln_sum_exp` of every position in a slice
+///
+/// The result has `ln Σ_{i ≤ k} exp(values[i])` at position `k`, so for a log probability mass
+/// function it's the log cumulative distribution function `ln P(X ≤ k)`, which can be searched
+/// with [`ln_quantile_cdf`].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_cum_sum_exp;
+///
+/// ln_cum_sum_exp(&[0.5_f64.ln(), 0.25_f64.ln(), 0.25_f64.ln()]);
+/// // [0.5_f64.ln(), 0.75_f64.ln(), 0.0]
+/// ```
+#[must_use]
+pub fn ln_cum_sum_exp<T>(values: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    let mut acc = T::neg_infinity();
+    values
+        .iter()
+        .map(|&val| {
+            acc = acc.ln_add_exp(val);
+            acc
+        })
+        .collect()
+}
+
+/// Find the quantile of a log probability mass function
+///
+/// This returns the smallest index `k` where `P(X ≤ k) ≥ q`, given `ln(q)`. Everything is done in
+/// log space, so quantiles of extremely skewed distributions, or with `q` too small for a float,
+/// are still exact. The mass function doesn't need to be normalized: `q` is relative to its total,
+/// so `ln_q = 0` always finds the last index with nonzero mass, even if rounding makes the total
+/// slightly different from one.
+///
+/// This builds the log cumulative distribution with [`ln_cum_sum_exp`] every call; to find many
+/// quantiles of the same distribution, build it once and use [`ln_quantile_cdf`].
+///
+/// Returns `None` if there is no mass, or `ln_q` is greater than zero or NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_quantile;
+///
+/// let log_pmf = [0.5_f64.ln(), 0.25_f64.ln(), 0.25_f64.ln()];
+/// assert_eq!(ln_quantile(&log_pmf, 0.6_f64.ln()), Some(1));
+/// ```
+#[must_use]
+pub fn ln_quantile<T>(log_pmf: &[T], ln_q: T) -> Option<usize>
+where
+    T: Float,
+{
+    ln_quantile_cdf(&ln_cum_sum_exp(log_pmf), ln_q)
+}
+
+/// Find a quantile from a log cumulative distribution function
+///
+/// This is [`ln_quantile`] given the output of [`ln_cum_sum_exp`], and finds the index with a
+/// binary search. `log_cdf` must be non-decreasing.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::{ln_cum_sum_exp, ln_quantile_cdf};
+///
+/// let log_cdf = ln_cum_sum_exp(&[0.5_f64.ln(), 0.25_f64.ln(), 0.25_f64.ln()]);
+/// assert_eq!(ln_quantile_cdf(&log_cdf, 0.4_f64.ln()), Some(0));
+/// assert_eq!(ln_quantile_cdf(&log_cdf, 0.9_f64.ln()), Some(2));
+/// ```
+#[must_use]
+pub fn ln_quantile_cdf<T>(log_cdf: &[T], ln_q: T) -> Option<usize>
+where
+    T: Float,
+{
+    let &total = log_cdf.last()?;
+    if total == T::neg_infinity() || total.is_nan() || ln_q.is_nan() || ln_q > T::zero() {
+        None
+    } else {
+        let target = ln_q + total;
+        Some(log_cdf.partition_point(|&val| val < target))
+    }
+}
+
+/// Compute the suffix `ln_sum_exp` of every position in a slice
+///
+/// The result has `ln Σ_{i ≥ k} exp(values[i])` at position `k`, so for a log probability mass
+/// function it's the log survival function `ln P(X ≥ k)`. It's accumulated from the end with
+/// [`ln_add_exp`][LogAddExp::ln_add_exp], so tail probabilities far too small for a float are
+/// still accurate, where `1 - cdf` would round to zero.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_cum_sum_exp_rev;
+///
+/// ln_cum_sum_exp_rev(&[0.5_f64.ln(), 0.25_f64.ln(), 0.25_f64.ln()]);
+/// // [0.0, 0.5_f64.ln(), 0.25_f64.ln()]
+/// ```
+#[must_use]
+pub fn ln_cum_sum_exp_rev<T>(values: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    let mut out = vec![T::neg_infinity(); values.len()];
+    let mut acc = T::neg_infinity();
+    for (res, &val) in out.iter_mut().zip(values).rev() {
+        acc = acc.ln_add_exp(val);
+        *res = acc;
+    }
+    out
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_cum_sum_exp, ln_cum_sum_exp_rev, ln_quantile, ln_quantile_cdf};
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_cum_sum_exp() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let res = ln_cum_sum_exp(&vals);
+        for (ind, &res) in res.iter().enumerate() {
+            assert_close!(res, vals[..=ind].iter().copied().ln_sum_exp());
+        }
+        assert!(ln_cum_sum_exp::<f64>(&[]).is_empty());
+        assert_eq!(
+            ln_cum_sum_exp(&[f64::NEG_INFINITY, 1.0]),
+            [f64::NEG_INFINITY, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_ln_quantile() {
+        let log_pmf = [
+            0.5_f64.ln(),
+            0.25_f64.ln(),
+            f64::NEG_INFINITY,
+            0.25_f64.ln(),
+        ];
+        assert_eq!(ln_quantile(&log_pmf, 0.1_f64.ln()), Some(0));
+        assert_eq!(ln_quantile(&log_pmf, 0.49_f64.ln()), Some(0));
+        assert_eq!(ln_quantile(&log_pmf, 0.6_f64.ln()), Some(1));
+        assert_eq!(ln_quantile(&log_pmf, 0.8_f64.ln()), Some(3));
+        assert_eq!(ln_quantile(&log_pmf, 0.0), Some(3));
+        assert_eq!(ln_quantile(&log_pmf, f64::NEG_INFINITY), Some(0));
+
+        // the mass only matters relative to its total
+        let scaled: Vec<f64> = log_pmf.iter().map(|val| val - 1000.0).collect();
+        assert_eq!(ln_quantile(&scaled, 0.6_f64.ln()), Some(1));
+
+        // almost all of the mass is on the last index
+        let skewed = [-800.0, -700.0, 0.0];
+        assert_eq!(ln_quantile(&skewed, -750.0), Some(1));
+        assert_eq!(ln_quantile(&skewed, -900.0), Some(0));
+
+        assert_eq!(ln_quantile::<f64>(&[], 0.5_f64.ln()), None);
+        assert_eq!(ln_quantile(&[f64::NEG_INFINITY], -1.0), None);
+        assert_eq!(ln_quantile(&log_pmf, 0.1), None);
+        assert_eq!(ln_quantile(&log_pmf, f64::NAN), None);
+
+        let log_cdf = ln_cum_sum_exp(&log_pmf);
+        assert_eq!(ln_quantile_cdf(&log_cdf, 0.74_f64.ln()), Some(1));
+    }
+
+    #[test]
+    fn test_ln_cum_sum_exp_rev() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let res = ln_cum_sum_exp_rev(&vals);
+        for (ind, &res) in res.iter().enumerate() {
+            assert_close!(res, vals[ind..].iter().copied().ln_sum_exp());
+        }
+
+        // a geometric pmf whose tail is far below the smallest float
+        let ln_success = (-(-1_f64).exp()).ln_1p();
+        let log_pmf: Vec<f64> = (0..2000).map(|k| ln_success - f64::from(k)).collect();
+        let log_survival = ln_cum_sum_exp_rev(&log_pmf);
+        assert_close!(log_survival[0], 0.0, atol = 1e-12);
+        assert_close!(log_survival[1500], -1500.0, rtol = 1e-12);
+
+        assert!(ln_cum_sum_exp_rev::<f64>(&[]).is_empty());
+        assert_eq!(
+            ln_cum_sum_exp_rev(&[1.0, f64::NEG_INFINITY]),
+            [1.0, f64::NEG_INFINITY]
+        );
+        let res = ln_cum_sum_exp_rev(&[1.0, f64::NAN, 2.0]);
+        assert!(res[0].is_nan() && res[1].is_nan());
+        assert_eq!(res[2], 2.0);
+    }
+}
@@ -0,0 +1,108 @@
+//! An exponentially decayed `ln_sum_exp`
+use super::math::ln;
+use super::LogAddExp;
+use num_traits::Float;
+
+/// An accumulator where every value is discounted by a constant factor per unit of time
+///
+/// This tracks `ln(sum_i(decay^(now - t_i) * exp(v_i)))`, the log-domain equivalent of an
+/// exponentially weighted sum. Time is advanced explicitly with [`tick`][Self::tick] or
+/// [`advance`][Self::advance], so the unit can be ticks, seconds, or anything else.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::DecayedLnSumExp;
+///
+/// let mut acc = DecayedLnSumExp::new(0.5);
+/// acc.push(0.0);
+/// acc.tick();
+/// acc.push(0.0);
+/// acc.ln_sum_exp(); // 1.5_f64.ln()
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DecayedLnSumExp<T> {
+    ln_decay: T,
+    total: T,
+}
+
+impl<T> DecayedLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create a new empty accumulator with a linear decay factor per unit time
+    ///
+    /// `decay` should be in `(0, 1]`, where one never forgets and values closer to zero forget
+    /// faster.
+    #[must_use]
+    pub fn new(decay: T) -> Self {
+        Self::with_ln_decay(ln(decay))
+    }
+
+    /// Create a new empty accumulator with the log of the decay factor per unit time
+    ///
+    /// This avoids underflow when the factor itself is too small to represent.
+    #[must_use]
+    pub fn with_ln_decay(ln_decay: T) -> Self {
+        DecayedLnSumExp {
+            ln_decay,
+            total: T::neg_infinity(),
+        }
+    }
+
+    /// Add a value at the current time
+    pub fn push(&mut self, value: T) {
+        self.total = self.total.ln_add_exp(value);
+    }
+
+    /// Advance time by one unit
+    pub fn tick(&mut self) {
+        self.total = self.total + self.ln_decay;
+    }
+
+    /// Advance time by `elapsed` units, which may be fractional
+    pub fn advance(&mut self, elapsed: T) {
+        self.total = self.total + self.ln_decay * elapsed;
+    }
+
+    /// The decayed `ln_sum_exp` at the current time
+    ///
+    /// An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        self.total
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::DecayedLnSumExp;
+
+    #[test]
+    fn test_decayed_ln_sum_exp() {
+        let mut acc = DecayedLnSumExp::new(0.5);
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        acc.tick();
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+
+        acc.push(2_f64.ln());
+        acc.tick();
+        assert_close!(acc.ln_sum_exp(), 0.0);
+        acc.push(0.0);
+        assert_close!(acc.ln_sum_exp(), 2_f64.ln());
+        acc.advance(2.0);
+        assert_close!(acc.ln_sum_exp(), 0.5_f64.ln());
+        acc.advance(0.5);
+        assert_close!(acc.ln_sum_exp(), 0.125_f64.sqrt().ln());
+    }
+
+    #[test]
+    fn test_ln_decay() {
+        let mut acc = DecayedLnSumExp::with_ln_decay(-1000.0);
+        acc.push(0.0);
+        acc.tick();
+        acc.push(-1000.0);
+        assert_close!(acc.ln_sum_exp(), -1000.0 + 2_f64.ln());
+    }
+}
@@ -0,0 +1,112 @@
+//! Log-domain operations on `rust_decimal` decimals
+//!
+//! [`Decimal`] doesn't implement [`Float`][num_traits::Float], so like
+//! [`FixedLogExp`][crate::FixedLogExp] it gets its own trait. Everything, including `exp` and
+//! `ln`, is computed in decimal arithmetic with `rust_decimal`'s software implementations, so
+//! results have far more significant digits than `f64` for values of moderate size.
+//!
+//! Decimals have no infinities, so [`Decimal::MIN`] stands in for negative infinity, i.e. a
+//! probability of zero. Terms more than 65 below the maximum have an exponential smaller than the
+//! smallest positive decimal, so they're dropped. Results that would overflow saturate at
+//! [`Decimal::MAX`].
+use rust_decimal::{Decimal, MathematicalOps};
+
+/// Differences below this have an exponential smaller than `1e-28`
+const MIN_DIFF: Decimal = Decimal::from_parts(65, 0, 0, true, 0);
+
+/// Log-domain operations on decimals
+pub trait DecimalLogExp: Sized {
+    /// Compute [`ln_add_exp`][crate::LogAddExp::ln_add_exp] in decimal arithmetic
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::DecimalLogExp;
+    /// use rust_decimal::Decimal;
+    ///
+    /// Decimal::ZERO.ln_add_exp(Decimal::ZERO); // 2_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_add_exp(self, other: Self) -> Self;
+
+    /// Compute [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] of a slice in decimal arithmetic
+    ///
+    /// An empty slice returns the minimum value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::DecimalLogExp;
+    /// use rust_decimal::Decimal;
+    ///
+    /// Decimal::ln_sum_exp(&[Decimal::ONE; 4]); // 1.0 + 4_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_sum_exp(values: &[Self]) -> Self;
+}
+
+impl DecimalLogExp for Decimal {
+    fn ln_add_exp(self, other: Self) -> Self {
+        Self::ln_sum_exp(&[self, other])
+    }
+
+    fn ln_sum_exp(values: &[Self]) -> Self {
+        let max = values.iter().copied().max().unwrap_or(Decimal::MIN);
+        if max == Decimal::MIN {
+            max
+        } else {
+            // a difference that overflows is far below the cutoff
+            let sum: Decimal = values
+                .iter()
+                .filter_map(|val| val.checked_sub(max))
+                .filter(|&diff| diff >= MIN_DIFF)
+                .map(|diff| diff.exp())
+                .sum();
+            max.saturating_add(sum.ln())
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::DecimalLogExp;
+    use crate::{LogAddExp, LogSumExp};
+    use rust_decimal::Decimal;
+
+    fn dec(val: f64) -> Decimal {
+        Decimal::try_from(val).unwrap()
+    }
+
+    fn float(val: Decimal) -> f64 {
+        f64::try_from(val).unwrap()
+    }
+
+    #[test]
+    fn test_ln_add_exp() {
+        for a in [-50.0, -3.0, 0.0, 0.5, 20.0] {
+            for b in [-49.0, -3.1, 0.0, 7.0, 36.5, 200.0] {
+                let res = dec(a).ln_add_exp(dec(b));
+                assert_close!(float(res), a.ln_add_exp(b), rtol = 1e-12, atol = 1e-12);
+            }
+        }
+        assert_eq!(Decimal::MIN.ln_add_exp(Decimal::ONE), Decimal::ONE);
+        assert_eq!(Decimal::ONE.ln_add_exp(Decimal::MIN), Decimal::ONE);
+        assert_eq!(Decimal::MIN.ln_add_exp(Decimal::MIN), Decimal::MIN);
+        assert_eq!(Decimal::MAX.ln_add_exp(Decimal::MAX), Decimal::MAX);
+    }
+
+    #[test]
+    fn test_ln_sum_exp() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let decs: Vec<Decimal> = vals.iter().map(|&val| dec(val)).collect();
+        let res = Decimal::ln_sum_exp(&decs);
+        assert_close!(float(res), vals.iter().copied().ln_sum_exp(), rtol = 1e-12);
+
+        assert_eq!(Decimal::ln_sum_exp(&[]), Decimal::MIN);
+        assert_eq!(
+            Decimal::ln_sum_exp(&[dec(-100.0), Decimal::ZERO]),
+            Decimal::ZERO
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Sampling the logs of random variables with `rand`
+//!
+//! Drawing a tiny value and then taking its log loses everything once the value underflows, and
+//! loses precision well before that. These distributions sample the log of the variable directly,
+//! so simulations can stay in log space end to end.
+use super::math::{cast, exp, ln, ln_1p};
+use num_traits::Float;
+use rand::distr::Distribution;
+use rand::Rng;
+use std::f64::consts::TAU;
+
+/// A uniform draw from `[0, 1)` as `T`
+///
+/// An `f64` draw just below one rounds up to one in a narrower `T` like `f32`, so those are drawn
+/// again instead of returning one.
+fn uniform<T: Float, R: Rng + ?Sized>(rng: &mut R) -> T {
+    loop {
+        let val = cast::<T>(rng.random::<f64>());
+        if val < T::one() {
+            return val;
+        }
+    }
+}
+
+/// The log of a uniform draw from `(0, 1]`
+///
+/// This is `ln_1p(-u)` for `u` in `[0, 1)`, so draws close to one, whose logs are close to zero,
+/// keep their precision.
+fn ln_uniform<T: Float, R: Rng + ?Sized>(rng: &mut R) -> T {
+    ln_1p(-uniform::<T, R>(rng))
+}
+
+/// A standard normal draw with the Box-Muller transform
+fn normal<T: Float, R: Rng + ?Sized>(rng: &mut R) -> T {
+    let two = T::one() + T::one();
+    let radius = (-two * ln_uniform::<T, R>(rng)).sqrt();
+    let angle = cast::<T>(TAU) * uniform::<T, R>(rng);
+    radius * angle.cos()
+}
+
+/// The log of a draw from a log-uniform distribution
+///
+/// A log-uniform variable between `exp(ln_low)` and `exp(ln_high)` has a log that's uniform
+/// between `ln_low` and `ln_high`, so this samples that uniform directly. The bounds can be far
+/// outside the range of `exp`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnLogUniform;
+/// use rand::distr::Distribution;
+///
+/// let dist = LnLogUniform::new(-5000.0, -4000.0);
+/// let ln_val: f64 = dist.sample(&mut rand::rng());
+/// assert!((-5000.0..-4000.0).contains(&ln_val));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LnLogUniform<T> {
+    ln_low: T,
+    ln_high: T,
+}
+
+impl<T> LnLogUniform<T>
+where
+    T: Float,
+{
+    /// Create a log-uniform distribution between `exp(ln_low)` and `exp(ln_high)`
+    ///
+    /// # Panics
+    ///
+    /// If either bound isn't finite, or `ln_low` is greater than `ln_high`.
+    #[must_use]
+    pub fn new(ln_low: T, ln_high: T) -> Self {
+        assert!(
+            ln_low.is_finite() && ln_high.is_finite() && ln_low <= ln_high,
+            "bounds must be finite and ordered"
+        );
+        LnLogUniform { ln_low, ln_high }
+    }
+}
+
+impl<T> Distribution<T> for LnLogUniform<T>
+where
+    T: Float,
+{
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        self.ln_low + (self.ln_high - self.ln_low) * uniform::<T, R>(rng)
+    }
+}
+
+/// The log of a draw from an exponential distribution
+///
+/// An exponential draw is `-ln(u)` for a uniform `u`, and small draws come from `u` close to one,
+/// so this computes it as `-ln_1p(-v)` for a uniform `v` in `[0, 1)`, which keeps full precision
+/// for draws far smaller than the mean.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnExponential;
+/// use rand::distr::Distribution;
+///
+/// let ln_val: f64 = LnExponential::new(2.0).sample(&mut rand::rng());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LnExponential<T> {
+    ln_rate: T,
+}
+
+impl<T> LnExponential<T>
+where
+    T: Float,
+{
+    /// Create an exponential distribution with rate `rate`, i.e. a mean of `1 / rate`
+    ///
+    /// # Panics
+    ///
+    /// If `rate` isn't positive and finite.
+    #[must_use]
+    pub fn new(rate: T) -> Self {
+        assert!(
+            rate > T::zero() && rate.is_finite(),
+            "rate must be positive and finite"
+        );
+        LnExponential { ln_rate: ln(rate) }
+    }
+}
+
+impl<T> Distribution<T> for LnExponential<T>
+where
+    T: Float,
+{
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        ln(-ln_uniform::<T, R>(rng)) - self.ln_rate
+    }
+}
+
+/// The log of a draw from a gamma distribution
+///
+/// This isn't the log of the gamma function: it samples `ln(x)` where `x` has a gamma
+/// distribution with the given shape and scale. Shapes of at least one use Marsaglia and Tsang's
+/// method, which produces the log of the draw without rounding it first. Smaller shapes use
+/// `ln(x) = ln(y) + ln(u) / shape` with `y` drawn with a shape one larger, which is where sampling
+/// in log space matters: with a shape of `0.01`, draws below `1e-300` are common and underflow to
+/// zero in linear space.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnGamma;
+/// use rand::distr::Distribution;
+///
+/// let ln_val: f64 = LnGamma::new(0.01, 1.0).sample(&mut rand::rng());
+/// assert!(ln_val.is_finite());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LnGamma<T> {
+    shape: T,
+    ln_scale: T,
+}
+
+impl<T> LnGamma<T>
+where
+    T: Float,
+{
+    /// Create a gamma distribution with shape `shape` and scale `scale`
+    ///
+    /// # Panics
+    ///
+    /// If `shape` or `scale` isn't positive and finite.
+    #[must_use]
+    pub fn new(shape: T, scale: T) -> Self {
+        assert!(
+            shape > T::zero() && shape.is_finite() && scale > T::zero() && scale.is_finite(),
+            "shape and scale must be positive and finite"
+        );
+        LnGamma {
+            shape,
+            ln_scale: ln(scale),
+        }
+    }
+
+    /// The log of a draw with unit scale and a shape of at least one
+    fn sample_large<R: Rng + ?Sized>(shape: T, rng: &mut R) -> T {
+        let third = cast::<T>(1.0 / 3.0);
+        let half = cast::<T>(0.5);
+        let d = shape - third;
+        let c = third / d.sqrt();
+        loop {
+            let x = normal::<T, R>(rng);
+            let cube_root = T::one() + c * x;
+            if cube_root <= T::zero() {
+                continue;
+            }
+            let ln_v = ln(cube_root * cube_root * cube_root);
+            let v = exp(ln_v);
+            if ln_uniform::<T, R>(rng) < half * x * x + d - d * v + d * ln_v {
+                return ln(d) + ln_v;
+            }
+        }
+    }
+}
+
+impl<T> Distribution<T> for LnGamma<T>
+where
+    T: Float,
+{
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        let res = if self.shape < T::one() {
+            let boosted = Self::sample_large(self.shape + T::one(), rng);
+            boosted + ln_uniform::<T, R>(rng) / self.shape
+        } else {
+            Self::sample_large(self.shape, rng)
+        };
+        res + self.ln_scale
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{LnExponential, LnGamma, LnLogUniform};
+    use crate::LogSumExp;
+    use rand::distr::Distribution;
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    const SAMPLES: u32 = 20_000;
+
+    fn samples<D: Distribution<f64>>(dist: &D) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..SAMPLES).map(|_| dist.sample(&mut rng)).collect()
+    }
+
+    /// The log of the mean of the exponentials
+    fn ln_mean(vals: &[f64]) -> f64 {
+        vals.iter().copied().ln_sum_exp() - f64::from(SAMPLES).ln()
+    }
+
+    #[test]
+    fn test_ln_log_uniform() {
+        let vals = samples(&LnLogUniform::new(-5000.0, -4000.0));
+        assert!(vals.iter().all(|val| (-5000.0..-4000.0).contains(val)));
+        let mean = vals.iter().sum::<f64>() / f64::from(SAMPLES);
+        assert_close!(mean, -4500.0, rtol = 1e-2);
+
+        let vals = samples(&LnLogUniform::new(1.0, 1.0));
+        assert!(vals.iter().all(|&val| val == 1.0));
+    }
+
+    #[test]
+    fn test_ln_exponential() {
+        let vals = samples(&LnExponential::new(4.0));
+        assert_close!(ln_mean(&vals), 0.25_f64.ln(), atol = 0.05);
+        // the log of an exponential draw has mean -euler_gamma - ln(rate)
+        let mean = vals.iter().sum::<f64>() / f64::from(SAMPLES);
+        assert_close!(mean, -0.577_215_664_9 - 4_f64.ln(), atol = 0.05);
+        assert!(vals.iter().all(|val| val.is_finite()));
+    }
+
+    /// Returns the largest value first and zeros after that
+    struct MaxThenZero(bool);
+
+    impl RngCore for MaxThenZero {
+        fn next_u32(&mut self) -> u32 {
+            // only the top 32 bits matter
+            #[allow(clippy::cast_possible_truncation)]
+            let res = (self.next_u64() >> 32) as u32;
+            res
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            if self.0 {
+                0
+            } else {
+                self.0 = true;
+                u64::MAX
+            }
+        }
+
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            dst.fill(0);
+        }
+    }
+
+    #[test]
+    fn test_uniform_below_one() {
+        // the first f64 draw rounds to one as an f32, so the zero after it is used instead
+        let val: f32 = LnExponential::new(1.0).sample(&mut MaxThenZero(false));
+        assert_eq!(val, f32::NEG_INFINITY);
+        let val: f32 = LnLogUniform::new(0.0, 1.0).sample(&mut MaxThenZero(false));
+        assert_eq!(val, 0.0);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        for (shape, scale) in [(1.0, 1.0), (3.5, 2.0), (0.3, 0.5)] {
+            let vals = samples(&LnGamma::new(shape, scale));
+            let mean: f64 = shape * scale;
+            assert_close!(ln_mean(&vals), mean.ln(), atol = 0.05);
+        }
+
+        // draws that underflow in linear space
+        let vals = samples(&LnGamma::new(0.01, 1.0));
+        assert!(vals.iter().all(|val| val.is_finite()));
+        assert!(vals.iter().any(|&val| val < -745.0));
+        assert_close!(ln_mean(&vals), 0.01_f64.ln(), atol = 0.3);
+    }
+
+    #[test]
+    #[should_panic(expected = "shape and scale must be positive and finite")]
+    fn test_ln_gamma_invalid() {
+        let _ = LnGamma::new(0.0, 1.0);
+    }
+}
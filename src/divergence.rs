@@ -0,0 +1,310 @@
+//! Information-theoretic quantities of log probability vectors
+//!
+//! Every function here treats a log probability of negative infinity as an exact zero, and uses
+//! the convention that `0 ln 0 = 0`, so zero probability entries contribute nothing instead of
+//! producing NaN.
+use super::math::{cast, exp};
+use super::slice::LANES;
+use super::{ln_2, ln_sum_exp_slice, LogAddExp};
+use num_traits::Float;
+
+/// Sum `term` over the values of a slice in fixed lanes
+fn sum_terms<T: Float>(values: &[T], term: impl Fn(T) -> T) -> T {
+    let mut lanes = [T::zero(); LANES];
+    for chunk in values.chunks(LANES) {
+        for (lane, &val) in lanes.iter_mut().zip(chunk) {
+            *lane = *lane + term(val);
+        }
+    }
+    lanes.into_iter().fold(T::zero(), |a, b| a + b)
+}
+
+/// Sum `term` over pairs of values from two slices in fixed lanes
+fn sum_pairs<T: Float>(first: &[T], second: &[T], term: impl Fn(T, T) -> T) -> T {
+    assert_eq!(
+        first.len(),
+        second.len(),
+        "distributions must have the same length"
+    );
+    let mut lanes = [T::zero(); LANES];
+    for (firsts, seconds) in first.chunks(LANES).zip(second.chunks(LANES)) {
+        for ((lane, &fst), &snd) in lanes.iter_mut().zip(firsts).zip(seconds) {
+            *lane = *lane + term(fst, snd);
+        }
+    }
+    lanes.into_iter().fold(T::zero(), |a, b| a + b)
+}
+
+/// `p ln(p / q)` in log space, which is zero if `p` is
+fn kl_term<T: Float>(log_p: T, log_q: T) -> T {
+    if log_p == T::neg_infinity() {
+        T::zero()
+    } else {
+        exp(log_p) * (log_p - log_q)
+    }
+}
+
+/// Compute the Kullback-Leibler divergence between two log probability vectors
+///
+/// This computes `Σ p ln(p / q)` from `ln(p)` and `ln(q)`. Entries where `p` is zero contribute
+/// nothing, and entries where only `q` is zero make the divergence infinite. The inputs are
+/// assumed to be normalized; see [`kl_div_normalized`] if they aren't.
+///
+/// # Panics
+///
+/// If `log_p` and `log_q` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::kl_div;
+///
+/// let log_p = [0.5_f64.ln(), 0.5_f64.ln()];
+/// let log_q = [0.25_f64.ln(), 0.75_f64.ln()];
+/// kl_div(&log_p, &log_q); // 0.5 * 2_f64.ln() + 0.5 * (2_f64 / 3.0).ln()
+/// ```
+#[must_use]
+pub fn kl_div<T>(log_p: &[T], log_q: &[T]) -> T
+where
+    T: Float,
+{
+    sum_pairs(log_p, log_q, kl_term)
+}
+
+/// Compute the Kullback-Leibler divergence between two unnormalized log mass vectors
+///
+/// This is [`kl_div`] of the distributions after normalizing each with [`ln_sum_exp_slice`],
+/// without allocating the normalized vectors. If either has no mass, or infinite mass, the result
+/// is NaN.
+///
+/// # Panics
+///
+/// If `log_p` and `log_q` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::kl_div_normalized;
+///
+/// kl_div_normalized(&[0.0_f64, 0.0], &[1.0, 3_f64.ln() + 1.0]);
+/// // 0.5 * 2_f64.ln() + 0.5 * (2_f64 / 3.0).ln()
+/// ```
+#[must_use]
+pub fn kl_div_normalized<T>(log_p: &[T], log_q: &[T]) -> T
+where
+    T: Float,
+{
+    let total_p = ln_sum_exp_slice(log_p);
+    let total_q = ln_sum_exp_slice(log_q);
+    if total_p.is_finite() && total_q.is_finite() {
+        sum_pairs(log_p, log_q, |lp, lq| kl_term(lp - total_p, lq - total_q))
+    } else {
+        T::nan()
+    }
+}
+
+/// Compute the Jensen-Shannon divergence between two log probability vectors
+///
+/// This is `(KL(p || m) + KL(q || m)) / 2` where `m = (p + q) / 2` is computed in log space with
+/// [`ln_add_exp`][LogAddExp::ln_add_exp], in a single pass over both inputs. Unlike [`kl_div`] it's
+/// symmetric and always finite, between zero and `ln(2)`, for normalized inputs.
+///
+/// # Panics
+///
+/// If `log_p` and `log_q` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::js_div;
+///
+/// js_div(&[0.0, f64::NEG_INFINITY], &[f64::NEG_INFINITY, 0.0]); // 2_f64.ln()
+/// ```
+#[must_use]
+pub fn js_div<T>(log_p: &[T], log_q: &[T]) -> T
+where
+    T: Float,
+{
+    let half = cast::<T>(0.5);
+    sum_pairs(log_p, log_q, |lp, lq| {
+        let log_m = lp.ln_add_exp(lq) - ln_2();
+        (kl_term(lp, log_m) + kl_term(lq, log_m)) * half
+    })
+}
+
+/// Compute the entropy of a log probability vector
+///
+/// This computes `-Σ p ln(p)` from `ln(p)`, in nats. Entries where `p` is zero contribute
+/// nothing. The input is assumed to be normalized; see [`entropy_normalized`] if it isn't.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::entropy;
+///
+/// entropy(&[0.25_f64.ln(); 4]); // 4_f64.ln()
+/// ```
+#[must_use]
+pub fn entropy<T>(log_p: &[T]) -> T
+where
+    T: Float,
+{
+    -sum_terms(log_p, |lp| kl_term(lp, T::zero()))
+}
+
+/// Compute the entropy of an unnormalized log mass vector
+///
+/// This is [`entropy`] of the distribution after normalizing it with [`ln_sum_exp_slice`], without
+/// allocating the normalized vector. If it has no mass, or infinite mass, the result is NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::entropy_normalized;
+///
+/// entropy_normalized(&[3.0_f64; 4]); // 4_f64.ln()
+/// ```
+#[must_use]
+pub fn entropy_normalized<T>(log_p: &[T]) -> T
+where
+    T: Float,
+{
+    let total = ln_sum_exp_slice(log_p);
+    if total.is_finite() {
+        -sum_terms(log_p, |lp| kl_term(lp - total, T::zero()))
+    } else {
+        T::nan()
+    }
+}
+
+/// Compute the cross-entropy between two log probability vectors
+///
+/// This computes `-Σ p ln(q)` from `ln(p)` and `ln(q)` in a single pass over both. Entries where
+/// `p` is zero contribute nothing, even if `q` is also zero, and entries where only `q` is zero
+/// make the cross-entropy infinite. It equals [`entropy`] of `p` plus [`kl_div`] from `p` to `q`.
+///
+/// # Panics
+///
+/// If `log_p` and `log_q` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::cross_entropy;
+///
+/// let log_p = [0.0, f64::NEG_INFINITY];
+/// let log_q = [0.25_f64.ln(), 0.75_f64.ln()];
+/// cross_entropy(&log_p, &log_q); // 4_f64.ln()
+/// ```
+#[must_use]
+pub fn cross_entropy<T>(log_p: &[T], log_q: &[T]) -> T
+where
+    T: Float,
+{
+    sum_pairs(log_p, log_q, |lp, lq| {
+        if lp == T::neg_infinity() {
+            T::zero()
+        } else {
+            -exp(lp) * lq
+        }
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{cross_entropy, entropy, entropy_normalized, js_div, kl_div, kl_div_normalized};
+
+    fn ln_all(probs: &[f64]) -> Vec<f64> {
+        probs.iter().map(|prob| prob.ln()).collect()
+    }
+
+    #[test]
+    fn test_kl_div() {
+        let probs: [f64; 10] = [0.1, 0.2, 0.3, 0.15, 0.05, 0.1, 0.04, 0.06, 0.0, 0.0];
+        let others: [f64; 10] = [0.05, 0.05, 0.2, 0.2, 0.2, 0.1, 0.05, 0.05, 0.1, 0.0];
+        let expected: f64 = probs
+            .iter()
+            .zip(&others)
+            .filter(|(&prob, _)| prob > 0.0)
+            .map(|(prob, other)| prob * (prob / other).ln())
+            .sum();
+        assert_close!(kl_div(&ln_all(&probs), &ln_all(&others)), expected);
+        assert_eq!(kl_div(&ln_all(&probs), &ln_all(&probs)), 0.0);
+        assert_eq!(kl_div(&ln_all(&others), &ln_all(&probs)), f64::INFINITY);
+        assert_eq!(kl_div::<f64>(&[], &[]), 0.0);
+        assert!(kl_div(&[f64::NAN], &[0.0]).is_nan());
+
+        let scaled: Vec<f64> = ln_all(&probs).iter().map(|val| val + 3.0).collect();
+        let scaled_others: Vec<f64> = ln_all(&others).iter().map(|val| val - 7.0).collect();
+        assert_close!(kl_div_normalized(&scaled, &scaled_others), expected);
+        assert!(kl_div_normalized(&[f64::NEG_INFINITY], &[0.0]).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_kl_div_mismatch() {
+        let _ = kl_div(&[0.0], &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_js_div() {
+        let probs: [f64; 4] = [0.1, 0.4, 0.5, 0.0];
+        let others: [f64; 4] = [0.3, 0.3, 0.0, 0.4];
+        let mid: Vec<f64> = probs
+            .iter()
+            .zip(&others)
+            .map(|(prob, other)| ((prob + other) / 2.0).ln())
+            .collect();
+        let expected = 0.5 * (kl_div(&ln_all(&probs), &mid) + kl_div(&ln_all(&others), &mid));
+        assert_close!(js_div(&ln_all(&probs), &ln_all(&others)), expected);
+        assert_close!(
+            js_div(&ln_all(&others), &ln_all(&probs)),
+            js_div(&ln_all(&probs), &ln_all(&others))
+        );
+        assert_eq!(js_div(&ln_all(&probs), &ln_all(&probs)), 0.0);
+        assert_close!(
+            js_div(&[0.0, f64::NEG_INFINITY], &[f64::NEG_INFINITY, 0.0]),
+            2_f64.ln()
+        );
+        assert_eq!(js_div(&[f64::NEG_INFINITY], &[f64::NEG_INFINITY]), 0.0);
+    }
+
+    #[test]
+    fn test_entropy() {
+        let probs: [f64; 5] = [0.1, 0.2, 0.3, 0.4, 0.0];
+        let expected: f64 = -probs[..4].iter().map(|prob| prob * prob.ln()).sum::<f64>();
+        assert_close!(entropy(&ln_all(&probs)), expected);
+        assert_eq!(entropy(&[0.0_f64]), 0.0);
+        assert_eq!(entropy::<f64>(&[]), 0.0);
+        assert!(entropy(&[f64::NAN]).is_nan());
+
+        let scaled: Vec<f64> = ln_all(&probs).iter().map(|val| val - 500.0).collect();
+        assert_close!(entropy_normalized(&scaled), expected);
+        assert!(entropy_normalized(&[f64::NEG_INFINITY; 3]).is_nan());
+        assert!(entropy_normalized(&[f64::INFINITY, 0.0]).is_nan());
+    }
+
+    #[test]
+    fn test_cross_entropy() {
+        let probs: [f64; 5] = [0.1, 0.2, 0.3, 0.4, 0.0];
+        let others: [f64; 5] = [0.3, 0.3, 0.1, 0.2, 0.1];
+        let expected: f64 = -probs
+            .iter()
+            .zip(&others)
+            .map(|(prob, other)| prob * other.ln())
+            .sum::<f64>();
+        let (log_p, log_q) = (ln_all(&probs), ln_all(&others));
+        assert_close!(cross_entropy(&log_p, &log_q), expected);
+        assert_close!(
+            cross_entropy(&log_p, &log_q),
+            entropy(&log_p) + kl_div(&log_p, &log_q)
+        );
+        assert_close!(cross_entropy(&log_p, &log_p), entropy(&log_p));
+        assert_eq!(cross_entropy(&log_q, &log_p), f64::INFINITY);
+        assert_eq!(
+            cross_entropy(&[f64::NEG_INFINITY], &[f64::NEG_INFINITY]),
+            0.0
+        );
+    }
+}
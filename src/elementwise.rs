@@ -0,0 +1,256 @@
+//! Element-wise operations on slices of log values
+use super::math::{exp, ln_1p};
+use super::LogSubExp;
+use num_traits::Float;
+use std::error::Error;
+use std::fmt;
+
+/// Compute [`ln_sub_exp`][LogSubExp::ln_sub_exp] of every pair of values into `out`
+///
+/// This sets `out[i]` to `ln(exp(a[i]) - exp(b[i]))`, e.g. to remove one batch's contribution
+/// from a vector of log totals. Pairs where `b[i]` is greater than `a[i]` produce NaN; use
+/// [`checked_ln_sub_exp_slices`] to find them instead.
+///
+/// # Panics
+///
+/// If `a`, `b`, and `out` don't all have the same length.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sub_exp_slices;
+///
+/// let mut out = [0.0; 2];
+/// ln_sub_exp_slices(&[3_f64.ln(), 0.0], &[0.0, f64::NEG_INFINITY], &mut out);
+/// // out == [2_f64.ln(), 0.0]
+/// ```
+pub fn ln_sub_exp_slices<T>(a: &[T], b: &[T], out: &mut [T])
+where
+    T: Float,
+{
+    assert!(
+        a.len() == b.len() && a.len() == out.len(),
+        "slices must have the same length"
+    );
+    for ((res, &left), &right) in out.iter_mut().zip(a).zip(b) {
+        *res = left.ln_sub_exp(right);
+    }
+}
+
+/// Replace every value in `a` with its [`ln_sub_exp`][LogSubExp::ln_sub_exp] with the value in `b`
+///
+/// This is [`ln_sub_exp_slices`] writing the result over `a`.
+///
+/// # Panics
+///
+/// If `a` and `b` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sub_exp_in_place;
+///
+/// let mut totals = [3_f64.ln(), 5_f64.ln()];
+/// ln_sub_exp_in_place(&mut totals, &[0.0, 0.0]);
+/// // totals == [2_f64.ln(), 4_f64.ln()]
+/// ```
+pub fn ln_sub_exp_in_place<T>(a: &mut [T], b: &[T])
+where
+    T: Float,
+{
+    assert_eq!(a.len(), b.len(), "slices must have the same length");
+    for (left, &right) in a.iter_mut().zip(b) {
+        *left = left.ln_sub_exp(right);
+    }
+}
+
+/// Replace every value in a slice with its [`ln_add_exp`][crate::LogAddExp::ln_add_exp] with `rhs`
+///
+/// This adds the same mass to every entry, e.g. a pseudocount to a table of log counts. It gives
+/// the same result as calling `ln_add_exp` on each value, but once `rhs` is known to be finite
+/// the loop body is `max(x, rhs) + ln_1p(exp(-|x - rhs|))` with no branches, which handles
+/// infinite and NaN values without special cases, so the loop can be unrolled and vectorized.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp_scalar;
+///
+/// let mut log_counts = [f64::NEG_INFINITY, 0.0, 2_f64.ln()];
+/// ln_add_exp_scalar(&mut log_counts, 0.5_f64.ln());
+/// // log_counts == [0.5_f64.ln(), 1.5_f64.ln(), 2.5_f64.ln()]
+/// ```
+pub fn ln_add_exp_scalar<T>(values: &mut [T], rhs: T)
+where
+    T: Float,
+{
+    if rhs == T::neg_infinity() {
+        return;
+    } else if !rhs.is_finite() {
+        // infinity absorbs everything, including NaN, and NaN absorbs everything else
+        for val in values {
+            if rhs == T::infinity() || *val != T::infinity() {
+                *val = rhs;
+            }
+        }
+        return;
+    }
+    for val in values {
+        *val = val.max(rhs) + ln_1p(exp(-(*val - rhs).abs()));
+    }
+}
+
+/// An error for a difference of exponentials that would be negative
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegativeDifference {
+    /// The first index where the value being subtracted is larger
+    pub index: usize,
+}
+
+impl fmt::Display for NegativeDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "subtracted value is larger than the original at index {}",
+            self.index
+        )
+    }
+}
+
+impl Error for NegativeDifference {}
+
+/// Compute [`ln_sub_exp_slices`], or return an error if any difference would be negative
+///
+/// Every pair is checked before anything is written, so `out` is unchanged on error. NaN inputs
+/// aren't an error, and still produce NaN.
+///
+/// # Errors
+///
+/// If `b[i]` is greater than `a[i]` for any `i`, reporting the first such index.
+///
+/// # Panics
+///
+/// If `a`, `b`, and `out` don't all have the same length.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::checked_ln_sub_exp_slices;
+///
+/// let mut out = [0.0; 3];
+/// let res = checked_ln_sub_exp_slices(&[1.0, 1.0, 1.0], &[0.0, 2.0, 3.0], &mut out);
+/// assert_eq!(res.unwrap_err().index, 1);
+/// ```
+pub fn checked_ln_sub_exp_slices<T>(
+    a: &[T],
+    b: &[T],
+    out: &mut [T],
+) -> Result<(), NegativeDifference>
+where
+    T: Float,
+{
+    assert!(
+        a.len() == b.len() && a.len() == out.len(),
+        "slices must have the same length"
+    );
+    if let Some(index) = a.iter().zip(b).position(|(left, right)| right > left) {
+        Err(NegativeDifference { index })
+    } else {
+        ln_sub_exp_slices(a, b, out);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        checked_ln_sub_exp_slices, ln_add_exp_scalar, ln_sub_exp_in_place, ln_sub_exp_slices,
+        NegativeDifference,
+    };
+    use crate::{ln_is_close, LogAddExp, LogSubExp};
+
+    #[test]
+    fn test_ln_sub_exp_slices() {
+        let a: Vec<f64> = (0..20).map(|n| f64::from(n).sin() * 50.0 + 10.0).collect();
+        let b: Vec<f64> = a.iter().map(|val| val - 0.3).collect();
+        let mut out = vec![0.0; 20];
+        ln_sub_exp_slices(&a, &b, &mut out);
+        for ((&res, &left), &right) in out.iter().zip(&a).zip(&b) {
+            assert_eq!(res, left.ln_sub_exp(right));
+        }
+
+        let mut out = [0.0; 4];
+        ln_sub_exp_slices(
+            &[1_f32, 1.0, f32::NEG_INFINITY, 0.0],
+            &[1.0, f32::NEG_INFINITY, f32::NEG_INFINITY, 1.0],
+            &mut out,
+        );
+        assert_eq!(out[..3], [f32::NEG_INFINITY, 1.0, f32::NEG_INFINITY]);
+        assert!(out[3].is_nan());
+    }
+
+    #[test]
+    fn test_ln_sub_exp_in_place() {
+        let mut totals = [3_f64.ln(), 5_f64.ln()];
+        ln_sub_exp_in_place(&mut totals, &[0.0, 0.0]);
+        assert_close!(totals[0], 2_f64.ln());
+        assert_close!(totals[1], 4_f64.ln());
+    }
+
+    #[test]
+    fn test_checked_ln_sub_exp_slices() {
+        let mut out = [0.0; 3];
+        let res = checked_ln_sub_exp_slices(&[1.0, 1.0, 1.0], &[0.0, 2.0, 3.0], &mut out);
+        assert_eq!(res, Err(NegativeDifference { index: 1 }));
+        assert_eq!(out, [0.0; 3]);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "subtracted value is larger than the original at index 1"
+        );
+
+        let res = checked_ln_sub_exp_slices(&[3_f64.ln(), f64::NAN], &[0.0, 1.0], &mut out[..2]);
+        assert_eq!(res, Ok(()));
+        assert_close!(out[0], 2_f64.ln());
+        assert!(out[1].is_nan());
+    }
+
+    #[test]
+    fn test_ln_add_exp_scalar() {
+        let special = [
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NAN,
+            0.0,
+            -1000.0,
+            1000.0,
+        ];
+        let vals: Vec<f64> = (0..20)
+            .map(|n| f64::from(n).sin() * 50.0)
+            .chain(special)
+            .collect();
+        for rhs in [-3.0, 0.0, 0.7, 40.0].into_iter().chain(special) {
+            let mut res = vals.clone();
+            ln_add_exp_scalar(&mut res, rhs);
+            for (&res, &val) in res.iter().zip(&vals) {
+                let expected = val.ln_add_exp(rhs);
+                if expected.is_nan() {
+                    assert!(res.is_nan());
+                } else {
+                    assert!(ln_is_close(res, expected, 0.0, 1e-12));
+                }
+            }
+        }
+
+        let mut vals = [0_f32, 0.0];
+        ln_add_exp_scalar(&mut vals, 0.0);
+        assert_eq!(vals, [2_f32.ln(); 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "slices must have the same length")]
+    fn test_ln_sub_exp_slices_mismatch() {
+        let mut out = [0.0; 2];
+        ln_sub_exp_slices(&[1.0, 2.0], &[0.0, 0.0, 0.0], &mut out);
+    }
+}
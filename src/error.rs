@@ -0,0 +1,105 @@
+//! Estimating the rounding error of `ln_sum_exp`
+use super::ln_sum_exp_slice;
+use super::math::{cast, exp, ln};
+use super::slice::{slice_max, LANES};
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of a slice along with a bound on its absolute error
+///
+/// This returns `(ln_sum_exp, error)` where `ln_sum_exp` is the same as [`ln_sum_exp_slice`], and
+/// `error` is a first order bound on the absolute rounding error of that result, ignoring terms
+/// of order `epsilon²`. The bound accounts for
+///
+/// - rounding each shifted value, which grows with how far it is below the maximum,
+/// - evaluating each exponential to within one ulp,
+/// - summing in [`ln_sum_exp_slice`]'s fixed lanes, which grows with the number of values,
+/// - and the final log and addition of the maximum.
+///
+/// Since it assumes the platform's `exp` and `ln` are accurate to an ulp, it's a tight heuristic
+/// rather than a proof. A bound that's too large for the caller is a sign to recompute in a wider
+/// type, e.g. with [`ln_sum_exp_accumulate`][crate::LogSumExp::ln_sum_exp_accumulate].
+///
+/// Infinite results are exact so their error is zero, and NaN results have a NaN error.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_with_error;
+///
+/// let (res, err) = ln_sum_exp_with_error(&[1.0_f32, 2.0, 4.0]);
+/// let exact = ln_sum_exp_with_error(&[1.0_f64, 2.0, 4.0]).0;
+/// assert!((f64::from(res) - exact).abs() <= f64::from(err));
+/// ```
+#[must_use]
+pub fn ln_sum_exp_with_error<T>(values: &[T]) -> (T, T)
+where
+    T: Float,
+{
+    let res = ln_sum_exp_slice(values);
+    if res.is_nan() {
+        return (res, T::nan());
+    } else if res.is_infinite() {
+        return (res, T::zero());
+    }
+    let max = slice_max(values);
+    let mut sum = T::zero();
+    // sum of the terms weighted by the relative error of computing them
+    let mut weighted = T::zero();
+    for &val in values {
+        let diff = val - max;
+        let term = exp(diff);
+        if term > T::zero() {
+            sum = sum + term;
+            weighted = weighted + term * (T::one() - diff);
+        }
+    }
+    let depth = cast::<T>(values.len().div_ceil(LANES) + LANES);
+    let rel_sum = weighted / sum + depth;
+    let eps = T::epsilon();
+    (res, (rel_sum + ln(sum) + res.abs()) * eps)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ln_sum_exp_with_error;
+    use crate::ln_sum_exp_slice;
+
+    #[test]
+    fn test_ln_sum_exp_with_error() {
+        for len in [1_u16, 2, 3, 10, 1000, 10_000] {
+            for scale in [1.0, 10.0, 1000.0] {
+                let vals: Vec<f32> = (0..len).map(|n| f32::from(n).sin() * scale).collect();
+                let (res, err) = ln_sum_exp_with_error(&vals);
+                assert_eq!(res, ln_sum_exp_slice(&vals));
+                let wide: Vec<f64> = vals.iter().copied().map(f64::from).collect();
+                let exact = ln_sum_exp_slice(&wide);
+                assert!((f64::from(res) - exact).abs() <= f64::from(err));
+                // the bound shouldn't be vacuous
+                assert!(err < 1e-3 * res.abs().max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ln_sum_exp_with_error_growth() {
+        let short: Vec<f64> = vec![0.0; 10];
+        let long: Vec<f64> = vec![0.0; 10_000];
+        assert!(ln_sum_exp_with_error(&short).1 < ln_sum_exp_with_error(&long).1);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_with_error_special() {
+        assert_eq!(ln_sum_exp_with_error::<f64>(&[]), (f64::NEG_INFINITY, 0.0));
+        assert_eq!(
+            ln_sum_exp_with_error(&[1.0, f64::INFINITY]),
+            (f64::INFINITY, 0.0)
+        );
+        let (res, err) = ln_sum_exp_with_error(&[f64::NAN, 1.0]);
+        assert!(res.is_nan() && err.is_nan());
+
+        let (res, err) = ln_sum_exp_with_error(&[f64::NEG_INFINITY, 1.0, 1.0]);
+        assert_close!(res, 1.0 + 2_f64.ln());
+        assert!(err.is_finite());
+    }
+}
@@ -0,0 +1,187 @@
+//! A Fenwick tree for prefix `ln_sum_exp` queries
+use super::LogAddExp;
+use num_traits::Float;
+
+/// The lowest set bit of `index`
+fn low_bit(index: usize) -> usize {
+    index & index.wrapping_neg()
+}
+
+/// A fixed size array of log values supporting prefix `ln_sum_exp` queries
+///
+/// Prefix queries and prefix searches take O(log n) time. Updates take O(log² n) because changed
+/// nodes are recombined from their children instead of subtracting the old value, which would
+/// lose precision when large values are replaced by small ones.
+///
+/// Since the prefixes are monotone, [`search`][Self::search] can invert them, which makes this
+/// suitable for sampling from a categorical distribution whose log weights change over time.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LogSumExpFenwickTree;
+///
+/// let mut tree = LogSumExpFenwickTree::new(&[0.0, 0.0, 0.0, 0.0]);
+/// tree.prefix_ln_sum_exp(2); // 2_f64.ln()
+/// tree.update(1, 2_f64.ln());
+/// tree.search(2.5_f64.ln()); // 1
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogSumExpFenwickTree<T> {
+    values: Vec<T>,
+    /// one-indexed, node `i` combines the values in `(i - low_bit(i), i]`
+    nodes: Vec<T>,
+}
+
+impl<T> LogSumExpFenwickTree<T>
+where
+    T: Float,
+{
+    /// Build a tree over a slice of log values in O(n)
+    #[must_use]
+    pub fn new(values: &[T]) -> Self {
+        let mut nodes = Vec::with_capacity(values.len() + 1);
+        nodes.push(T::neg_infinity());
+        nodes.extend_from_slice(values);
+        for index in 1..nodes.len() {
+            let parent = index + low_bit(index);
+            if parent < nodes.len() {
+                nodes[parent] = nodes[parent].ln_add_exp(nodes[index]);
+            }
+        }
+        LogSumExpFenwickTree {
+            values: values.to_vec(),
+            nodes,
+        }
+    }
+
+    /// The number of values in the tree
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// True if the tree contains no values
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The value at `index`
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> T {
+        self.values[index]
+    }
+
+    /// Replace the value at `index`
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn update(&mut self, index: usize, value: T) {
+        self.values[index] = value;
+        let mut node = index + 1;
+        while node < self.nodes.len() {
+            let mut total = self.values[node - 1];
+            let mut child = node - 1;
+            while child > node - low_bit(node) {
+                total = total.ln_add_exp(self.nodes[child]);
+                child -= low_bit(child);
+            }
+            self.nodes[node] = total;
+            node += low_bit(node);
+        }
+    }
+
+    /// The `ln_sum_exp` of the first `count` values
+    ///
+    /// A count of zero returns negative infinity.
+    ///
+    /// # Panics
+    ///
+    /// If `count` is greater than the length.
+    #[must_use]
+    pub fn prefix_ln_sum_exp(&self, count: usize) -> T {
+        assert!(count <= self.len(), "count out of bounds");
+        let mut total = T::neg_infinity();
+        let mut node = count;
+        while node > 0 {
+            total = total.ln_add_exp(self.nodes[node]);
+            node -= low_bit(node);
+        }
+        total
+    }
+
+    /// The first index whose inclusive prefix `ln_sum_exp` is at least `target`
+    ///
+    /// This returns the length if no prefix reaches `target`. Drawing `target` as the log of a
+    /// uniform sample scaled by the total mass samples an index proportional to its weight.
+    #[must_use]
+    pub fn search(&self, target: T) -> usize {
+        let mut total = T::neg_infinity();
+        let mut index = 0;
+        let mut step = self.len().checked_next_power_of_two().unwrap_or(0);
+        while step > 0 {
+            if let Some(&node) = self.nodes.get(index + step) {
+                let next = total.ln_add_exp(node);
+                if next < target {
+                    total = next;
+                    index += step;
+                }
+            }
+            step /= 2;
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LogSumExpFenwickTree;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_prefix_ln_sum_exp() {
+        let mut vals: Vec<f64> = (1..14).map(|n| f64::from(n).ln()).collect();
+        let mut tree = LogSumExpFenwickTree::new(&vals);
+        assert_eq!(tree.len(), 13);
+        assert_eq!(tree.prefix_ln_sum_exp(0), f64::NEG_INFINITY);
+
+        vals[5] = 10.0;
+        tree.update(5, 10.0);
+        vals[5] = -10.0;
+        tree.update(5, -10.0);
+        vals[12] = 2.0;
+        tree.update(12, 2.0);
+        assert_eq!(tree.get(5), -10.0);
+
+        for count in 1..=vals.len() {
+            assert_close!(
+                tree.prefix_ln_sum_exp(count),
+                vals[..count].iter().copied().ln_sum_exp()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let tree = LogSumExpFenwickTree::new(&[0.0, f64::NEG_INFINITY, 0.0, 3_f64.ln(), 0.0]);
+        assert_eq!(tree.search(f64::NEG_INFINITY), 0);
+        assert_eq!(tree.search(0.5_f64.ln()), 0);
+        assert_eq!(tree.search(0.0), 0);
+        assert_eq!(tree.search(1.5_f64.ln()), 2);
+        assert_eq!(tree.search(2.5_f64.ln()), 3);
+        assert_eq!(tree.search(5_f64.ln()), 3);
+        assert_eq!(tree.search(5.5_f64.ln()), 4);
+        assert_eq!(tree.search(7_f64.ln()), 5);
+
+        let empty = LogSumExpFenwickTree::<f64>::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.search(0.0), 0);
+    }
+}
@@ -0,0 +1,124 @@
+//! A C ABI for calling from C, C++, Fortran, or anything else with a C FFI
+//!
+//! These functions use the standard C calling convention and only pass `double`s, pointers to
+//! `double`, and `size_t`s, so they can be called through `iso_c_binding` or any other FFI. The
+//! declarations are in `include/logaddexp.h`, which is generated with
+//! [cbindgen](https://github.com/mozilla/cbindgen):
+//!
+//! ```text
+//! cbindgen --config cbindgen.toml --output include/logaddexp.h
+//! ```
+//!
+//! To build a library to link against, compile the crate as a `cdylib` or `staticlib`:
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! cargo rustc --release --features ffi --crate-type staticlib
+//! ```
+//!
+//! Invalid arguments are reported by returning NaN rather than by panicking, and every function
+//! catches panics before they reach the caller, since unwinding into C is undefined behavior.
+use super::{ln_sum_exp_slice, LogAddExp};
+use std::panic;
+use std::slice;
+
+/// Run `func`, returning NaN if it panics
+fn nan_on_panic(func: impl FnOnce() -> f64 + panic::UnwindSafe) -> f64 {
+    panic::catch_unwind(func).unwrap_or(f64::NAN)
+}
+
+/// Compute `ln(exp(a) + exp(b))`
+#[no_mangle]
+pub extern "C" fn logaddexp_f64(a: f64, b: f64) -> f64 {
+    nan_on_panic(|| a.ln_add_exp(b))
+}
+
+/// Compute `ln(sum(exp(values)))` of `len` values
+///
+/// An empty array returns negative infinity. `values` may be null if `len` is zero, and a null
+/// `values` with a nonzero `len` returns NaN.
+///
+/// # Safety
+///
+/// If `values` isn't null, it must point to `len` initialized `double`s that aren't modified
+/// during the call.
+#[no_mangle]
+pub unsafe extern "C" fn logsumexp_f64(values: *const f64, len: usize) -> f64 {
+    if values.is_null() {
+        return if len == 0 {
+            f64::NEG_INFINITY
+        } else {
+            f64::NAN
+        };
+    }
+    // SAFETY: the caller guarantees values points to len doubles
+    let values = unsafe { slice::from_raw_parts(values, len) };
+    nan_on_panic(|| ln_sum_exp_slice(values))
+}
+
+/// Replace `len` values with their log softmax and return their `ln_sum_exp`
+///
+/// After this, `values[i]` is the old `values[i]` minus the returned value. `values` may be null
+/// if `len` is zero, and a null `values` with a nonzero `len` returns NaN and writes nothing.
+///
+/// # Safety
+///
+/// If `values` isn't null, it must point to `len` initialized `double`s that nothing else
+/// accesses during the call.
+#[no_mangle]
+pub unsafe extern "C" fn log_softmax_f64(values: *mut f64, len: usize) -> f64 {
+    if values.is_null() {
+        return if len == 0 {
+            f64::NEG_INFINITY
+        } else {
+            f64::NAN
+        };
+    }
+    // SAFETY: the caller guarantees values points to len doubles it doesn't otherwise access
+    let values = unsafe { slice::from_raw_parts_mut(values, len) };
+    nan_on_panic(panic::AssertUnwindSafe(|| {
+        let total = ln_sum_exp_slice(values);
+        for val in values {
+            *val -= total;
+        }
+        total
+    }))
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{log_softmax_f64, logaddexp_f64, logsumexp_f64, nan_on_panic};
+    use crate::{ln_sum_exp_slice, LogAddExp};
+    use std::ptr;
+
+    #[test]
+    fn test_ffi() {
+        assert_eq!(logaddexp_f64(1.0, 2.0), 1_f64.ln_add_exp(2.0));
+
+        let mut vals = [1.0, 2.0, 4.0, -3.0];
+        let total = ln_sum_exp_slice(&vals);
+        assert_eq!(unsafe { logsumexp_f64(vals.as_ptr(), vals.len()) }, total);
+        assert_eq!(
+            unsafe { log_softmax_f64(vals.as_mut_ptr(), vals.len()) },
+            total
+        );
+        assert_close!(ln_sum_exp_slice(&vals), 0.0);
+    }
+
+    #[test]
+    fn test_ffi_null() {
+        assert_eq!(unsafe { logsumexp_f64(ptr::null(), 0) }, f64::NEG_INFINITY);
+        assert!(unsafe { logsumexp_f64(ptr::null(), 3) }.is_nan());
+        assert_eq!(
+            unsafe { log_softmax_f64(ptr::null_mut(), 0) },
+            f64::NEG_INFINITY
+        );
+        assert!(unsafe { log_softmax_f64(ptr::null_mut(), 3) }.is_nan());
+    }
+
+    #[test]
+    fn test_nan_on_panic() {
+        assert!(nan_on_panic(|| panic!("boom")).is_nan());
+    }
+}
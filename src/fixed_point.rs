@@ -0,0 +1,222 @@
+//! Log-domain operations on fixed-point values for targets without an FPU
+//!
+//! These only use integer arithmetic. Log values are [`I16F16`] (Q16.16) or [`I8F24`] (Q8.24)
+//! from the `fixed` crate, and the minimum value of each type stands in for negative infinity,
+//! i.e. a probability of zero.
+//!
+//! Internally every term is converted to `e^-d` as an unsigned Q0.32 value, where `d` is its
+//! distance below the maximum, with a shift-and-multiply `exp2` over a table of `2^-2^-i`. The
+//! log of the sum is computed one bit at a time by repeated squaring. Both are accurate to a few
+//! units of `2^-28`, so results are within one unit in the last place of the exact value,
+//! rounded, for both formats. Results that would overflow saturate at the maximum value.
+use fixed::types::{I16F16, I8F24};
+
+/// `2^(-2^-i)` for `i` in `1..=32` as Q0.32
+const EXP2_NEG_POW: [u64; 32] = [
+    0xB504_F334,
+    0xD744_FCCB,
+    0xEAC0_C6E8,
+    0xF525_7D15,
+    0xFA83_B2DB,
+    0xFD3E_0C0D,
+    0xFE9E_115C,
+    0xFF4E_CB59,
+    0xFFA7_5652,
+    0xFFD3_A752,
+    0xFFE9_D2B3,
+    0xFFF4_E91C,
+    0xFFFA_747F,
+    0xFFFD_3A3B,
+    0xFFFE_9D1D,
+    0xFFFF_4E8E,
+    0xFFFF_A747,
+    0xFFFF_D3A3,
+    0xFFFF_E9D2,
+    0xFFFF_F4E9,
+    0xFFFF_FA74,
+    0xFFFF_FD3A,
+    0xFFFF_FE9D,
+    0xFFFF_FF4F,
+    0xFFFF_FFA7,
+    0xFFFF_FFD4,
+    0xFFFF_FFEA,
+    0xFFFF_FFF5,
+    0xFFFF_FFFA,
+    0xFFFF_FFFD,
+    0xFFFF_FFFF,
+    0xFFFF_FFFF,
+];
+
+/// `1` as Q32.32
+const ONE: u64 = 1 << 32;
+
+/// `ln(2)` as Q0.32
+const LN_2: u64 = 2_977_044_472;
+
+/// `log2(e) - 1` as Q0.32
+const LOG2_E_M1: u64 = 1_901_360_723;
+
+/// `e^-diff` for a Q32.32 `diff`, as Q32.32
+fn exp_neg(diff: u64) -> u64 {
+    if diff >= 64 << 32 {
+        return 0;
+    }
+    // diff * log2(e), dropping the six lowest bits of diff to stay in 64 bits
+    let scaled = diff + (((diff >> 6) * LOG2_E_M1) >> 26);
+    let int = scaled >> 32;
+    if int >= 32 {
+        return 0;
+    }
+    let mut res = ONE;
+    for (bit, &factor) in EXP2_NEG_POW.iter().enumerate() {
+        if scaled & (1 << (31 - bit)) != 0 {
+            res = (res * factor) >> 32;
+        }
+    }
+    res >> int
+}
+
+/// `log2(1 + val)` for a Q0.32 `val` in `[0, 1)`, as Q0.32
+fn log2_1p(val: u64) -> u64 {
+    // 1 + val as Q1.31
+    let mut x = (ONE + val) >> 1;
+    let mut res = 0;
+    for bit in (0..32).rev() {
+        x = (x * x) >> 31;
+        if x >= ONE {
+            x >>= 1;
+            res |= 1 << bit;
+        }
+    }
+    res
+}
+
+/// `ln(val)` for a Q32.32 `val` of at least one, as Q32.32
+fn ln(val: u64) -> u64 {
+    let int_bits = val.ilog2() - 32;
+    let mant = val >> int_bits;
+    u64::from(int_bits) * LN_2 + ((log2_1p(mant - ONE) * LN_2) >> 32)
+}
+
+/// `ln_sum_exp` of raw fixed-point values with `frac` fractional bits
+fn ln_sum_exp_bits(values: &[i32], frac: u32) -> i32 {
+    let max = values.iter().copied().max().unwrap_or(i32::MIN);
+    if max == i32::MIN {
+        return max;
+    }
+    let sum = values
+        .iter()
+        .filter(|&&val| val != i32::MIN)
+        .map(|&val| exp_neg(u64::from(max.abs_diff(val)) << (32 - frac)))
+        .fold(0, u64::saturating_add);
+    let total = (ln(sum) + (1 << (31 - frac))) >> (32 - frac);
+    max.saturating_add(i32::try_from(total).unwrap_or(i32::MAX))
+}
+
+/// Log-domain operations on fixed-point values using only integer arithmetic
+pub trait FixedLogExp: Sized {
+    /// Compute [`ln_add_exp`][crate::LogAddExp::ln_add_exp] without floating point
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fixed::types::I16F16;
+    /// use logaddexp::FixedLogExp;
+    ///
+    /// I16F16::ZERO.ln_add_exp(I16F16::ZERO); // 2_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_add_exp(self, other: Self) -> Self;
+
+    /// Compute [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] of a slice without floating point
+    ///
+    /// This rounds once, so it's more accurate than repeated `ln_add_exp`. An empty slice returns
+    /// the minimum value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fixed::types::I8F24;
+    /// use logaddexp::FixedLogExp;
+    ///
+    /// I8F24::ln_sum_exp(&[I8F24::ZERO; 4]); // 4_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_sum_exp(values: &[Self]) -> Self;
+}
+
+macro_rules! impl_fixed_log_exp {
+    ($($fixed:ty),*) => {$(
+        impl FixedLogExp for $fixed {
+            fn ln_add_exp(self, other: Self) -> Self {
+                Self::ln_sum_exp(&[self, other])
+            }
+
+            fn ln_sum_exp(values: &[Self]) -> Self {
+                let bits: Vec<i32> = values.iter().map(|val| val.to_bits()).collect();
+                Self::from_bits(ln_sum_exp_bits(&bits, Self::FRAC_NBITS))
+            }
+        }
+    )*};
+}
+
+impl_fixed_log_exp!(I16F16, I8F24);
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::FixedLogExp;
+    use crate::{LogAddExp, LogSumExp};
+    use fixed::types::{I16F16, I8F24};
+
+    #[test]
+    fn test_ln_add_exp() {
+        let ulp_16 = I16F16::DELTA.to_num::<f64>();
+        let ulp_24 = I8F24::DELTA.to_num::<f64>();
+        for a in -80..80 {
+            for b in [-20.0, -3.3, -0.01, 0.0, 0.7, 5.0, 19.9] {
+                let a = f64::from(a) * 0.37;
+                let exact = a.ln_add_exp(b);
+
+                let res = I16F16::from_num(a).ln_add_exp(I16F16::from_num(b));
+                let expected = I16F16::from_num(a)
+                    .to_num::<f64>()
+                    .ln_add_exp(I16F16::from_num(b).to_num::<f64>());
+                assert!((res.to_num::<f64>() - expected).abs() <= ulp_16);
+                assert_close!(res.to_num::<f64>(), exact, atol = 1e-4);
+
+                if exact.abs() < 127.0 {
+                    let res = I8F24::from_num(a).ln_add_exp(I8F24::from_num(b));
+                    let expected = I8F24::from_num(a)
+                        .to_num::<f64>()
+                        .ln_add_exp(I8F24::from_num(b).to_num::<f64>());
+                    assert!((res.to_num::<f64>() - expected).abs() <= ulp_24);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ln_sum_exp() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let fixed: Vec<I8F24> = vals.iter().map(|&val| I8F24::from_num(val)).collect();
+        let expected = fixed.iter().map(|val| val.to_num::<f64>()).ln_sum_exp();
+        let res = I8F24::ln_sum_exp(&fixed);
+        assert!((res.to_num::<f64>() - expected).abs() <= I8F24::DELTA.to_num::<f64>());
+    }
+
+    #[test]
+    fn test_special() {
+        let zero = I16F16::ZERO;
+        assert_eq!(I16F16::MIN.ln_add_exp(zero), zero);
+        assert_eq!(zero.ln_add_exp(I16F16::MIN), zero);
+        assert_eq!(I16F16::MIN.ln_add_exp(I16F16::MIN), I16F16::MIN);
+        assert_eq!(I16F16::ln_sum_exp(&[]), I16F16::MIN);
+        assert_eq!(I16F16::MAX.ln_add_exp(I16F16::MAX), I16F16::MAX);
+        assert_eq!(
+            I16F16::MAX.ln_add_exp(I16F16::from_num(-30_000)),
+            I16F16::MAX
+        );
+        assert_eq!(I8F24::MAX.ln_add_exp(I8F24::MIN), I8F24::MAX);
+    }
+}
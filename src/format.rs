@@ -0,0 +1,203 @@
+//! Printing and parsing log values as numbers in linear space
+//!
+//! A probability like `1e-3421` underflows every float type, but its log, about `-7877`, is an
+//! ordinary `f64`. These convert between the two representations through base ten without ever
+//! computing the linear value, so extreme probabilities can be logged and read from configuration
+//! in the notation people expect.
+use super::math::{cast, exp, ln};
+use num_traits::Float;
+use std::error::Error;
+use std::f64::consts::LN_10;
+use std::fmt;
+
+/// Formats a log value as its exponential in scientific notation
+///
+/// This is created by [`display_linear`].
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayLinear<T>(T);
+
+/// Format a log value as its exponential in scientific notation
+///
+/// The result is printed as `{mantissa}e{exponent}` like the [`LowerExp`][fmt::LowerExp] format of
+/// a float, but the exponent can be far outside the range of any float. A precision sets the
+/// number of digits after the decimal point, and without one the mantissa is rounded to six digits
+/// with trailing zeros removed. Negative infinity prints as `0e0`, positive infinity as `inf`, and
+/// NaN as `NaN`.
+///
+/// The mantissa is computed from the fractional part of `ln_value / ln(10)`, so it has a relative
+/// error of about `|ln_value|` ulps.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::display_linear;
+///
+/// let ln_prob = -3421.0 * 10_f64.ln();
+/// assert_eq!(display_linear(ln_prob).to_string(), "1e-3421");
+/// assert_eq!(format!("{:.2}", display_linear(ln_prob + 2_f64.ln())), "2.00e-3421");
+/// ```
+#[must_use]
+pub fn display_linear<T>(ln_value: T) -> DisplayLinear<T>
+where
+    T: Float,
+{
+    DisplayLinear(ln_value)
+}
+
+impl<T> fmt::Display for DisplayLinear<T>
+where
+    T: Float,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ln_value = self.0.to_f64().unwrap_or(f64::NAN);
+        if ln_value.is_nan() {
+            return f.pad_integral(true, "", "NaN");
+        } else if ln_value == f64::NEG_INFINITY {
+            return f.pad_integral(true, "", "0e0");
+        } else if ln_value == f64::INFINITY {
+            return f.pad_integral(true, "", "inf");
+        }
+        let digits = f.precision().unwrap_or(6);
+        let scale = 10_f64.powi(i32::try_from(digits.min(17)).unwrap_or(17));
+        let mut exponent = (ln_value / LN_10).floor();
+        let mut mantissa = exp(ln_value - exponent * LN_10);
+        if mantissa < 1.0 {
+            mantissa *= 10.0;
+            exponent -= 1.0;
+        }
+        mantissa = (mantissa * scale).round() / scale;
+        if mantissa >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1.0;
+        }
+        // adding zero turns negative zero positive
+        let exponent = exponent + 0.0;
+        // pad_integral applies the width without treating the precision as a maximum length
+        if f.precision().is_some() {
+            f.pad_integral(true, "", &format!("{mantissa:.digits$}e{exponent}"))
+        } else {
+            let mantissa = format!("{mantissa:.digits$}");
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            f.pad_integral(true, "", &format!("{mantissa}e{exponent}"))
+        }
+    }
+}
+
+impl<T> fmt::LowerExp for DisplayLinear<T>
+where
+    T: Float,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// An error for a string that isn't a nonnegative number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLinearError;
+
+impl fmt::Display for ParseLinearError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid nonnegative number")
+    }
+}
+
+impl Error for ParseLinearError {}
+
+/// Parse a nonnegative number and return its natural log
+///
+/// This accepts anything [`f64`] parses, optionally followed by an exponent of `e` or `E` and an
+/// integer, but the exponent can be arbitrarily large, so `"1e-3421"` parses to
+/// `-3421 * ln(10)` instead of the log of zero. It's the inverse of [`display_linear`]. Surrounding
+/// whitespace is ignored.
+///
+/// # Errors
+///
+/// If the string isn't a number, or the number is negative or NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::parse_linear;
+///
+/// let ln_prob: f64 = parse_linear("2.5e-3421").unwrap(); // 2.5_f64.ln() - 3421.0 * 10_f64.ln()
+/// assert!(parse_linear::<f64>("-1").is_err());
+/// ```
+pub fn parse_linear<T>(input: &str) -> Result<T, ParseLinearError>
+where
+    T: Float,
+{
+    let input = input.trim();
+    let (mantissa, exponent) = match input.find(['e', 'E']) {
+        Some(split) => (
+            &input[..split],
+            input[split + 1..]
+                .parse::<i64>()
+                .map_err(|_| ParseLinearError)?,
+        ),
+        None => (input, 0),
+    };
+    let mantissa: f64 = mantissa.parse().map_err(|_| ParseLinearError)?;
+    if mantissa.is_nan() || mantissa < 0.0 {
+        return Err(ParseLinearError);
+    }
+    // the exponent is far larger than any meaningful number of decimal digits
+    #[allow(clippy::cast_precision_loss)]
+    let shift = exponent as f64 * LN_10;
+    Ok(cast::<T>(ln(mantissa) + shift))
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{display_linear, parse_linear, ParseLinearError};
+    use std::f64::consts::LN_10;
+
+    #[test]
+    fn test_display_linear() {
+        assert_eq!(display_linear(-3421.0 * LN_10).to_string(), "1e-3421");
+        assert_eq!(display_linear(0_f64).to_string(), "1e0");
+        assert_eq!(display_linear(1000_f64.ln()).to_string(), "1e3");
+        assert_eq!(display_linear(0.25_f32.ln()).to_string(), "2.5e-1");
+        assert_eq!(
+            display_linear(2_f64.ln() + 5000.0 * LN_10).to_string(),
+            "2e5000"
+        );
+        assert_eq!(format!("{:e}", display_linear(1234.5_f64.ln())), "1.2345e3");
+        assert_eq!(format!("{:.3}", display_linear(2_f64.ln())), "2.000e0");
+        assert_eq!(format!("{:.1}", display_linear(9.99_f64.ln())), "1.0e1");
+        assert_eq!(format!("{:>8}", display_linear(2_f64.ln())), "     2e0");
+
+        assert_eq!(display_linear(f64::NEG_INFINITY).to_string(), "0e0");
+        assert_eq!(display_linear(f64::INFINITY).to_string(), "inf");
+        assert_eq!(display_linear(f64::NAN).to_string(), "NaN");
+    }
+
+    #[test]
+    fn test_parse_linear() {
+        let res: f64 = parse_linear("2.5e-3421").unwrap();
+        assert_close!(res, 2.5_f64.ln() - 3421.0 * LN_10);
+        let res: f64 = parse_linear(" 0.001 ").unwrap();
+        assert_close!(res, 0.001_f64.ln());
+        let res: f32 = parse_linear("3E+2").unwrap();
+        assert_close!(res, 300_f32.ln());
+        assert_eq!(parse_linear::<f64>("0e5"), Ok(f64::NEG_INFINITY));
+        assert_eq!(parse_linear::<f64>("inf"), Ok(f64::INFINITY));
+
+        assert_eq!(parse_linear::<f64>("-1e-5"), Err(ParseLinearError));
+        assert_eq!(parse_linear::<f64>("abc"), Err(ParseLinearError));
+        assert_eq!(parse_linear::<f64>("NaN"), Err(ParseLinearError));
+        assert_eq!(parse_linear::<f64>("1e"), Err(ParseLinearError));
+        assert_eq!(parse_linear::<f64>("1e2.5"), Err(ParseLinearError));
+        assert_eq!(parse_linear::<f64>(""), Err(ParseLinearError));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for ln_val in [-1e5, -7877.3, -3.2, 0.0, 0.7, 1234.5] {
+            let text = format!("{:.15}", display_linear(ln_val));
+            let res: f64 = parse_linear(&text).unwrap();
+            assert_close!(res, ln_val, atol = 1e-9);
+        }
+    }
+}
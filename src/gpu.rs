@@ -0,0 +1,471 @@
+//! Batched `ln_sum_exp` and log softmax on the GPU with wgpu compute shaders
+//!
+//! Everything here works on `f32` storage buffers of row-major matrices, so data can stay on the
+//! GPU between other passes of a pipeline. Each row is reduced by one workgroup in two passes, a
+//! max and then a shifted sum, exactly like the CPU kernels.
+//!
+//! Shaders may not follow IEEE semantics for infinities and NaN, so unlike the rest of the crate,
+//! the results for rows with non-finite values depend on the GPU. Rows of finite values match
+//! [`ln_sum_exp_slice`][crate::ln_sum_exp_slice] up to `f32` rounding.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use logaddexp::gpu::{self, LnSumExpKernels};
+//! use wgpu::util::DeviceExt;
+//!
+//! # fn run(device: &wgpu::Device, queue: &wgpu::Queue) {
+//! let values: Vec<f32> = (0..6).map(|n| n as f32).collect();
+//! let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+//!     label: None,
+//!     contents: &values.iter().flat_map(|val| val.to_le_bytes()).collect::<Vec<_>>(),
+//!     usage: wgpu::BufferUsages::STORAGE,
+//! });
+//! let kernels = LnSumExpKernels::new(device);
+//! let output = kernels.ln_sum_exp_batched(device, queue, &buffer, (2, 3));
+//! let res = gpu::read_buffer(device, queue, &output); // one value per row
+//! # }
+//! ```
+use std::sync::mpsc;
+use wgpu::util::DeviceExt;
+
+/// Workgroups per dimension of a dispatch, the minimum every device supports
+const MAX_GROUPS: u32 = 65535;
+
+const SHADER: &str = r"
+struct Params {
+    rows: u32,
+    cols: u32,
+}
+
+@group(0) @binding(0) var<storage, read> input: array<f32>;
+@group(0) @binding(1) var<storage, read_write> output: array<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+const SIZE: u32 = 256u;
+var<workgroup> scratch: array<f32, 256>;
+
+fn reduce_max(lid: u32, val: f32) -> f32 {
+    scratch[lid] = val;
+    workgroupBarrier();
+    for (var stride = SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if (lid < stride) {
+            scratch[lid] = max(scratch[lid], scratch[lid + stride]);
+        }
+        workgroupBarrier();
+    }
+    let res = scratch[0];
+    workgroupBarrier();
+    return res;
+}
+
+fn reduce_sum(lid: u32, val: f32) -> f32 {
+    scratch[lid] = val;
+    workgroupBarrier();
+    for (var stride = SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if (lid < stride) {
+            scratch[lid] = scratch[lid] + scratch[lid + stride];
+        }
+        workgroupBarrier();
+    }
+    let res = scratch[0];
+    workgroupBarrier();
+    return res;
+}
+
+fn row_ln_sum_exp(row: u32, lid: u32) -> f32 {
+    let base = row * params.cols;
+    // the lowest finite value, since shaders may not support infinity
+    var local_max = -3.40282347e38;
+    for (var i = lid; i < params.cols; i = i + SIZE) {
+        local_max = max(local_max, input[base + i]);
+    }
+    let row_max = reduce_max(lid, local_max);
+    var local_sum = 0.0;
+    for (var i = lid; i < params.cols; i = i + SIZE) {
+        local_sum = local_sum + exp(input[base + i] - row_max);
+    }
+    return row_max + log(reduce_sum(lid, local_sum));
+}
+
+@compute @workgroup_size(256)
+fn ln_sum_exp(
+    @builtin(workgroup_id) wid: vec3<u32>,
+    @builtin(local_invocation_index) lid: u32,
+) {
+    let row = wid.x + wid.y * 65535u;
+    if (row >= params.rows) {
+        return;
+    }
+    let res = row_ln_sum_exp(row, lid);
+    if (lid == 0u) {
+        output[row] = res;
+    }
+}
+
+@compute @workgroup_size(256)
+fn log_softmax(
+    @builtin(workgroup_id) wid: vec3<u32>,
+    @builtin(local_invocation_index) lid: u32,
+) {
+    let row = wid.x + wid.y * 65535u;
+    if (row >= params.rows) {
+        return;
+    }
+    let total = row_ln_sum_exp(row, lid);
+    let base = row * params.cols;
+    for (var i = lid; i < params.cols; i = i + SIZE) {
+        output[base + i] = input[base + i] - total;
+    }
+}
+";
+
+/// Compiled compute pipelines for batched `ln_sum_exp` and log softmax
+///
+/// Creating the pipelines compiles the shader, so create this once per device and reuse it.
+#[derive(Debug)]
+pub struct LnSumExpKernels {
+    ln_sum_exp: wgpu::ComputePipeline,
+    log_softmax: wgpu::ComputePipeline,
+}
+
+impl LnSumExpKernels {
+    /// Compile the kernels for a device
+    #[must_use]
+    pub fn new(device: &wgpu::Device) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("logaddexp"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        LnSumExpKernels {
+            ln_sum_exp: pipeline("ln_sum_exp"),
+            log_softmax: pipeline("log_softmax"),
+        }
+    }
+
+    /// Compute `ln_sum_exp` of every row of a row-major `(rows, cols)` matrix
+    ///
+    /// `buffer` must be a storage buffer of at least `rows * cols` `f32` values. The result is a
+    /// new storage buffer with one value per row that can be copied from. Rows with no columns are
+    /// negative infinity.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix has more than `u32::MAX` values, or `buffer` is smaller than the matrix.
+    #[must_use]
+    pub fn ln_sum_exp_batched(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        shape: (usize, usize),
+    ) -> wgpu::Buffer {
+        dispatch(&self.ln_sum_exp, device, queue, buffer, shape, shape.0)
+    }
+
+    /// Compute the log softmax of every row of a row-major `(rows, cols)` matrix
+    ///
+    /// This is the same as [`ln_sum_exp_batched`][Self::ln_sum_exp_batched], but the result has
+    /// the same shape as the input, with every value shifted by its row's `ln_sum_exp`.
+    ///
+    /// # Panics
+    ///
+    /// If the matrix has more than `u32::MAX` values, or `buffer` is smaller than the matrix.
+    #[must_use]
+    pub fn log_softmax_batched(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        buffer: &wgpu::Buffer,
+        shape: (usize, usize),
+    ) -> wgpu::Buffer {
+        dispatch(
+            &self.log_softmax,
+            device,
+            queue,
+            buffer,
+            shape,
+            shape.0 * shape.1,
+        )
+    }
+}
+
+/// Run one of the kernels on a matrix and return its output buffer
+fn dispatch(
+    pipeline: &wgpu::ComputePipeline,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    (rows, cols): (usize, usize),
+    output_len: usize,
+) -> wgpu::Buffer {
+    let size = rows
+        .checked_mul(cols)
+        .and_then(|size| u32::try_from(size).ok())
+        .expect("matrix too large");
+    let rows = u32::try_from(rows).unwrap();
+    let cols = u32::try_from(cols).unwrap();
+    assert!(
+        buffer.size() >= u64::from(size) * 4,
+        "buffer is smaller than its shape"
+    );
+
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("logaddexp params"),
+        contents: &[rows.to_le_bytes(), cols.to_le_bytes()].concat(),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let output = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("logaddexp output"),
+        // zero sized bindings aren't allowed
+        size: (output_len.max(1) * 4) as u64,
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    if rows == 0 {
+        return output;
+    }
+    if cols == 0 {
+        // nothing to bind as input, and every row is empty
+        queue.write_buffer(
+            &output,
+            0,
+            &f32::NEG_INFINITY.to_le_bytes().repeat(output_len),
+        );
+        return output;
+    }
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("logaddexp bindings"),
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: output.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params.as_entire_binding(),
+            },
+        ],
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("logaddexp"),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("logaddexp"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(rows.min(MAX_GROUPS), rows.div_ceil(MAX_GROUPS), 1);
+    }
+    queue.submit([encoder.finish()]);
+    output
+}
+
+/// Compute `ln_sum_exp` of every row of a row-major `(rows, cols)` matrix
+///
+/// This compiles the kernels on every call, so use [`LnSumExpKernels`] when calling repeatedly.
+/// See [`LnSumExpKernels::ln_sum_exp_batched`].
+///
+/// # Panics
+///
+/// If the matrix has more than `u32::MAX` values, or `buffer` is smaller than the matrix.
+#[must_use]
+pub fn ln_sum_exp_batched(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    shape: (usize, usize),
+) -> wgpu::Buffer {
+    LnSumExpKernels::new(device).ln_sum_exp_batched(device, queue, buffer, shape)
+}
+
+/// Compute the log softmax of every row of a row-major `(rows, cols)` matrix
+///
+/// This compiles the kernels on every call, so use [`LnSumExpKernels`] when calling repeatedly.
+/// See [`LnSumExpKernels::log_softmax_batched`].
+///
+/// # Panics
+///
+/// If the matrix has more than `u32::MAX` values, or `buffer` is smaller than the matrix.
+#[must_use]
+pub fn log_softmax_batched(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    shape: (usize, usize),
+) -> wgpu::Buffer {
+    LnSumExpKernels::new(device).log_softmax_batched(device, queue, buffer, shape)
+}
+
+/// Copy a buffer of `f32` values back to the host, blocking until it's available
+///
+/// `buffer` must have been created with [`COPY_SRC`][wgpu::BufferUsages::COPY_SRC] usage, like
+/// the outputs of the kernels.
+///
+/// # Panics
+///
+/// If the device is lost before the copy finishes.
+#[must_use]
+pub fn read_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<f32> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("logaddexp staging"),
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("logaddexp readback"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+    queue.submit([encoder.finish()]);
+
+    let (send, recv) = mpsc::channel();
+    staging.map_async(wgpu::MapMode::Read, .., move |res| {
+        let _ = send.send(res);
+    });
+    device
+        .poll(wgpu::PollType::wait_indefinitely())
+        .expect("device lost");
+    recv.recv()
+        .expect("map callback dropped")
+        .expect("failed to map buffer");
+    let res = staging
+        .get_mapped_range(..)
+        .expect("buffer is mapped")
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    staging.unmap();
+    res
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{read_buffer, LnSumExpKernels};
+    use crate::{ln_normalize_collect, ln_sum_exp_slice};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use wgpu::util::DeviceExt;
+
+    /// Native wgpu futures are ready immediately, so they can be polled without a runtime
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(res) = future.as_mut().poll(&mut cx) {
+                return res;
+            }
+        }
+    }
+
+    /// A device if this machine has any adapter, otherwise the test is skipped
+    fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::default();
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()));
+        let adapter = adapter.ok()?;
+        block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()
+    }
+
+    /// A device on the noop backend, which doesn't execute anything, but still validates shaders
+    /// and usage
+    #[cfg(feature = "gpu-noop")]
+    fn noop_device() -> (wgpu::Device, wgpu::Queue) {
+        let mut desc = wgpu::InstanceDescriptor::new_without_display_handle();
+        desc.backends = wgpu::Backends::NOOP;
+        desc.backend_options.noop.enable = true;
+        let instance = wgpu::Instance::new(desc);
+        let adapter =
+            block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).unwrap();
+        let (device, queue) =
+            block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).unwrap();
+        device.on_uncaptured_error(std::sync::Arc::new(|err| panic!("{err}")));
+        (device, queue)
+    }
+
+    fn upload(device: &wgpu::Device, values: &[f32]) -> wgpu::Buffer {
+        let bytes: Vec<u8> = values.iter().flat_map(|val| val.to_le_bytes()).collect();
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: &bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    }
+
+    #[test]
+    fn test_ln_sum_exp_batched() {
+        let Some((device, queue)) = device() else {
+            return;
+        };
+        let kernels = LnSumExpKernels::new(&device);
+        for (rows, cols) in [(3, 1), (5, 7), (2, 1000)] {
+            let values: Vec<f32> = (0..rows * cols)
+                .map(|n| (f32::from(u16::try_from(n).unwrap()) * 0.37).sin() * 20.0)
+                .collect();
+            let buffer = upload(&device, &values);
+
+            let output = kernels.ln_sum_exp_batched(&device, &queue, &buffer, (rows, cols));
+            let res = read_buffer(&device, &queue, &output);
+            for (row, &val) in values.chunks(cols).zip(&res) {
+                assert_close!(val, ln_sum_exp_slice(row), rtol = 1e-5, atol = 1e-5);
+            }
+
+            let output = kernels.log_softmax_batched(&device, &queue, &buffer, (rows, cols));
+            let res = read_buffer(&device, &queue, &output);
+            for (row, vals) in values.chunks(cols).zip(res.chunks(cols)) {
+                let (_, expected) = ln_normalize_collect(row);
+                for (&val, &exp) in vals.iter().zip(&expected) {
+                    assert_close!(val, exp, rtol = 1e-5, atol = 1e-5);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "gpu-noop")]
+    #[test]
+    fn test_shader_validates() {
+        let (device, queue) = noop_device();
+        let kernels = LnSumExpKernels::new(&device);
+        let buffer = upload(&device, &[1.0, 2.0, 3.0, 4.0]);
+        let output = kernels.ln_sum_exp_batched(&device, &queue, &buffer, (2, 2));
+        assert_eq!(read_buffer(&device, &queue, &output).len(), 2);
+        let output = kernels.log_softmax_batched(&device, &queue, &buffer, (2, 2));
+        assert_eq!(read_buffer(&device, &queue, &output).len(), 4);
+        let output = kernels.ln_sum_exp_batched(&device, &queue, &buffer, (2, 0));
+        assert_eq!(
+            read_buffer(&device, &queue, &output),
+            [f32::NEG_INFINITY; 2]
+        );
+    }
+
+    #[cfg(feature = "gpu-noop")]
+    #[test]
+    #[should_panic(expected = "buffer is smaller than its shape")]
+    fn test_buffer_too_small() {
+        let (device, queue) = noop_device();
+        let kernels = LnSumExpKernels::new(&device);
+        let buffer = upload(&device, &[1.0, 2.0, 3.0]);
+        let _ = kernels.ln_sum_exp_batched(&device, &queue, &buffer, (2, 2));
+    }
+}
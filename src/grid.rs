@@ -0,0 +1,256 @@
+//! Evenly spaced grids in log space
+use super::math::{cast, exp, ln};
+use num_traits::Float;
+use std::iter::FusedIterator;
+
+/// An iterator over evenly spaced log values
+///
+/// This is created by [`ln_linspace`].
+#[derive(Debug, Clone)]
+pub struct LnLinspace<T> {
+    start: T,
+    end: T,
+    /// the number of intervals as `T`
+    steps: T,
+    /// index of the last point
+    last: usize,
+    /// next index from the front
+    front: usize,
+    /// one past the next index from the back
+    back: usize,
+}
+
+impl<T> LnLinspace<T>
+where
+    T: Float,
+{
+    /// The point at `index`, with the endpoints exact
+    fn get(&self, index: usize) -> T {
+        if index == 0 {
+            self.start
+        } else if index == self.last {
+            self.end
+        } else {
+            self.start + (self.end - self.start) * (cast::<T>(index) / self.steps)
+        }
+    }
+}
+
+impl<T> Iterator for LnLinspace<T>
+where
+    T: Float,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.front < self.back {
+            let res = self.get(self.front);
+            self.front += 1;
+            Some(res)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T> DoubleEndedIterator for LnLinspace<T>
+where
+    T: Float,
+{
+    fn next_back(&mut self) -> Option<T> {
+        if self.front < self.back {
+            self.back -= 1;
+            Some(self.get(self.back))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for LnLinspace<T> where T: Float {}
+
+impl<T> FusedIterator for LnLinspace<T> where T: Float {}
+
+/// Iterate over `len` evenly spaced log values from `start_ln` to `end_ln`
+///
+/// The first and last values are exactly `start_ln` and `end_ln`, so a grid of log temperatures
+/// or log quadrature nodes ends where it's supposed to regardless of rounding. A single point is
+/// just `start_ln`, and a length of zero is empty.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_linspace;
+///
+/// let grid: Vec<f64> = ln_linspace(0.0, 1.0, 5).collect();
+/// assert_eq!(grid, [0.0, 0.25, 0.5, 0.75, 1.0]);
+/// ```
+#[must_use]
+pub fn ln_linspace<T>(start_ln: T, end_ln: T, len: usize) -> LnLinspace<T>
+where
+    T: Float,
+{
+    let last = len.saturating_sub(1);
+    LnLinspace {
+        start: start_ln,
+        end: end_ln,
+        steps: cast::<T>(last),
+        last,
+        front: 0,
+        back: len,
+    }
+}
+
+/// An iterator over values evenly spaced in log space
+///
+/// This is created by [`geomspace`].
+#[derive(Debug, Clone)]
+pub struct Geomspace<T> {
+    start: T,
+    end: T,
+    inner: LnLinspace<T>,
+}
+
+impl<T> Geomspace<T>
+where
+    T: Float,
+{
+    /// The point at `index` from its log, with the endpoints exact
+    fn get(&self, index: usize, ln_val: T) -> T {
+        if index == 0 {
+            self.start
+        } else if index == self.inner.last {
+            self.end
+        } else {
+            exp(ln_val)
+        }
+    }
+}
+
+impl<T> Iterator for Geomspace<T>
+where
+    T: Float,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let index = self.inner.front;
+        let ln_val = self.inner.next()?;
+        Some(self.get(index, ln_val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Geomspace<T>
+where
+    T: Float,
+{
+    fn next_back(&mut self) -> Option<T> {
+        let ln_val = self.inner.next_back()?;
+        Some(self.get(self.inner.back, ln_val))
+    }
+}
+
+impl<T> ExactSizeIterator for Geomspace<T> where T: Float {}
+
+impl<T> FusedIterator for Geomspace<T> where T: Float {}
+
+/// Iterate over `len` positive values from `start` to `end` that are evenly spaced in log space
+///
+/// This is the exponential of [`ln_linspace`] of the logs of the endpoints, so consecutive values
+/// have a constant ratio, e.g. for a ladder of inverse temperatures. The first and last values
+/// are exactly `start` and `end`. Endpoints that aren't positive produce NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::geomspace;
+///
+/// let betas: Vec<f64> = geomspace(1e-3, 1.0, 4).collect();
+/// // [1e-3, 1e-2, 1e-1, 1.0]
+/// ```
+#[must_use]
+pub fn geomspace<T>(start: T, end: T, len: usize) -> Geomspace<T>
+where
+    T: Float,
+{
+    Geomspace {
+        start,
+        end,
+        inner: ln_linspace(ln(start), ln(end), len),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{geomspace, ln_linspace};
+
+    #[test]
+    fn test_ln_linspace() {
+        let grid: Vec<f64> = ln_linspace(0.0, 1.0, 5).collect();
+        assert_eq!(grid, [0.0, 0.25, 0.5, 0.75, 1.0]);
+
+        let start = 0.1_f64.ln();
+        let end = 7.3_f64.ln();
+        let grid: Vec<f64> = ln_linspace(start, end, 37).collect();
+        assert_eq!(grid.len(), 37);
+        assert_eq!(grid[0], start);
+        assert_eq!(grid[36], end);
+        for pair in grid.windows(2) {
+            assert_close!(pair[1] - pair[0], (end - start) / 36.0);
+        }
+
+        let rev: Vec<f64> = ln_linspace(start, end, 37).rev().collect();
+        assert!(rev.iter().rev().eq(&grid));
+
+        let mut iter = ln_linspace(3_f32, -3.0, 4);
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next(), Some(3.0));
+        assert_eq!(iter.next_back(), Some(-3.0));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next(), Some(1.0));
+        assert_eq!(iter.next_back(), Some(-1.0));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn test_ln_linspace_short() {
+        assert_eq!(
+            ln_linspace(1.0, 2.0, 0).collect::<Vec<f64>>(),
+            Vec::<f64>::new()
+        );
+        assert_eq!(ln_linspace(1.0, 2.0, 1).collect::<Vec<f64>>(), [1.0]);
+        assert_eq!(ln_linspace(1.0, 2.0, 2).collect::<Vec<f64>>(), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_geomspace() {
+        let grid: Vec<f64> = geomspace(1e-3, 1.0, 4).collect();
+        assert_eq!(grid[0], 1e-3);
+        assert_close!(grid[1], 1e-2);
+        assert_close!(grid[2], 1e-1);
+        assert_eq!(grid[3], 1.0);
+
+        let grid: Vec<f64> = geomspace(0.3, 1.7, 11).collect();
+        assert_eq!(grid[0], 0.3);
+        assert_eq!(grid[10], 1.7);
+        for pair in grid.windows(2) {
+            assert_close!(pair[1] / pair[0], (1.7_f64 / 0.3).powf(0.1));
+        }
+        let rev: Vec<f64> = geomspace(0.3, 1.7, 11).rev().collect();
+        assert!(rev.iter().rev().eq(&grid));
+
+        assert_eq!(geomspace(2.0, 8.0, 1).collect::<Vec<f64>>(), [2.0]);
+        assert!(geomspace(-1_f64, 1.0, 3).nth(1).unwrap().is_nan());
+    }
+}
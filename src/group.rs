@@ -0,0 +1,200 @@
+//! Grouped `ln_sum_exp` reductions
+use super::math::{exp, ln};
+use super::StreamingLnSumExp;
+use num_traits::Float;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Compute `ln_sum_exp` separately for every group of items
+///
+/// `key_fn` splits every item into its group key and its log value. This is a single pass over
+/// the items that keeps one [`StreamingLnSumExp`] per group, so the iterator doesn't need to be
+/// [Clone].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_by_key;
+///
+/// let scores = [("a", 1.0), ("b", 2.0), ("a", 3.0)];
+/// let groups = ln_sum_exp_by_key(scores, |pair| pair);
+/// groups["a"]; // (1_f64.exp() + 3_f64.exp()).ln()
+/// groups["b"]; // 2.0
+/// ```
+pub fn ln_sum_exp_by_key<I, K, T, F>(iter: I, mut key_fn: F) -> HashMap<K, T>
+where
+    I: IntoIterator,
+    K: Eq + Hash,
+    T: Float,
+    F: FnMut(I::Item) -> (K, T),
+{
+    let mut groups: HashMap<K, StreamingLnSumExp<T>> = HashMap::new();
+    for item in iter {
+        let (key, val) = key_fn(item);
+        groups.entry(key).or_default().push(val);
+    }
+    groups
+        .into_iter()
+        .map(|(key, acc)| (key, acc.ln_sum_exp()))
+        .collect()
+}
+
+/// Compute `ln_sum_exp` of the values that satisfy a predicate and of those that don't
+///
+/// This returns `(matching, rest)` from a single pass with two accumulators, so the iterator
+/// doesn't need to be [Clone].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_partition;
+///
+/// let (pos, neg) = ln_sum_exp_partition([1.0, 2.0, 3.0], |&v| v > 1.5);
+/// let ln_odds = pos - neg;
+/// ```
+pub fn ln_sum_exp_partition<I, T, P>(iter: I, mut pred: P) -> (T, T)
+where
+    I: IntoIterator<Item = T>,
+    T: Float,
+    P: FnMut(&T) -> bool,
+{
+    let mut matching = StreamingLnSumExp::new();
+    let mut rest = StreamingLnSumExp::new();
+    for val in iter {
+        if pred(&val) {
+            matching.push(val);
+        } else {
+            rest.push(val);
+        }
+    }
+    (matching.ln_sum_exp(), rest.ln_sum_exp())
+}
+
+/// Compute `ln_sum_exp` for every segment of a slice
+///
+/// `segment_ids[i]` is the segment that `values[i]` belongs to, and the ids don't need to be
+/// sorted. The result has one entry per segment, and segments without any values are negative
+/// infinity.
+///
+/// # Panics
+///
+/// If `values` and `segment_ids` have different lengths, or if any id is not less than
+/// `num_segments`. Ids after every segment has found a positive infinity aren't read.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::segment_ln_sum_exp;
+///
+/// let res = segment_ln_sum_exp(&[1.0, 2.0, 3.0], &[0, 2, 0], 3);
+/// // [(1_f64.exp() + 3_f64.exp()).ln(), f64::NEG_INFINITY, 2.0]
+/// ```
+#[must_use]
+pub fn segment_ln_sum_exp<T>(values: &[T], segment_ids: &[usize], num_segments: usize) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        values.len(),
+        segment_ids.len(),
+        "values and segment_ids must have the same length"
+    );
+    let mut maxes = vec![T::neg_infinity(); num_segments];
+    let mut has_nan = vec![false; num_segments];
+    let mut infinite = 0;
+    for (&val, &id) in values.iter().zip(segment_ids) {
+        if val > maxes[id] {
+            maxes[id] = val;
+            if val == T::infinity() {
+                infinite += 1;
+                // nothing can change an infinite max, so stop once every segment has one
+                if infinite == num_segments {
+                    return maxes;
+                }
+            }
+        }
+        has_nan[id] |= val.is_nan();
+    }
+    let mut sums = vec![T::zero(); num_segments];
+    for (&val, &id) in values.iter().zip(segment_ids) {
+        if maxes[id].is_finite() {
+            sums[id] = sums[id] + exp(val - maxes[id]);
+        }
+    }
+    maxes
+        .into_iter()
+        .zip(sums)
+        .zip(has_nan)
+        .map(|((max, sum), nan)| {
+            if max.is_finite() {
+                ln(sum) + max
+            } else if max == T::neg_infinity() && nan {
+                T::nan()
+            } else {
+                max
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_by_key, ln_sum_exp_partition, segment_ln_sum_exp};
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_by_key() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let groups = ln_sum_exp_by_key(vals.iter().enumerate(), |(i, &val)| (i % 3, val));
+        assert_eq!(groups.len(), 3);
+        for (&key, &val) in &groups {
+            let expected = vals.iter().copied().skip(key).step_by(3).ln_sum_exp();
+            assert_close!(val, expected);
+        }
+
+        let empty = ln_sum_exp_by_key(Vec::<(u8, f64)>::new(), |pair| pair);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_ln_sum_exp_partition() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let (low, high) = ln_sum_exp_partition(vals.iter().copied(), |&v| v < 2.0);
+        assert_close!(low, vals[..7].iter().copied().ln_sum_exp());
+        assert_close!(high, vals[7..].iter().copied().ln_sum_exp());
+
+        let (all, none) = ln_sum_exp_partition(vals.iter().copied(), |_| true);
+        assert_close!(all, vals.iter().copied().ln_sum_exp());
+        assert_eq!(none, f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_segment_ln_sum_exp() {
+        let vals = [1.0, 2.0, 3.0, f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+        let res = segment_ln_sum_exp(&vals, &[0, 2, 0, 3, 4, 5], 7);
+        assert_eq!(res.len(), 7);
+        assert_close!(res[0], [1.0, 3.0].into_iter().ln_sum_exp());
+        assert_eq!(res[1], f64::NEG_INFINITY);
+        assert_eq!(res[2], 2.0);
+        assert_eq!(res[3], f64::INFINITY);
+        assert_eq!(res[4], f64::NEG_INFINITY);
+        assert!(res[5].is_nan());
+        assert_eq!(res[6], f64::NEG_INFINITY);
+
+        let vals = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+        let res = segment_ln_sum_exp(&vals, &[0, 0, 1, 1], 2);
+        assert_eq!(res[0], f64::INFINITY);
+        assert!(res[1].is_nan());
+        assert_eq!(
+            segment_ln_sum_exp(&[f64::INFINITY, 1.0], &[0, 0], 1),
+            [f64::INFINITY]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_segment_ln_sum_exp_length() {
+        let _ = segment_ln_sum_exp(&[1.0, 2.0], &[0], 1);
+    }
+}
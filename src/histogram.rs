@@ -0,0 +1,239 @@
+//! A histogram that accumulates weights in log space
+use super::math::{cast, ln};
+use super::StreamingLnSumExp;
+use num_traits::Float;
+
+/// A histogram over equal width bins whose masses are log weights
+///
+/// Each bin accumulates the `ln_sum_exp` of the log weights of the values that fall in it, so
+/// importance samples with weights far outside the range of `exp` can be binned without
+/// normalizing them first. Adding a value with a log weight of zero counts it once, so the masses
+/// are log counts for unweighted data.
+///
+/// Bins are half open, except the last one, which includes the upper edge. Values outside the
+/// range, and NaN values, aren't binned.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LogHistogram;
+///
+/// let mut hist = LogHistogram::new(0.0, 2.0, 2);
+/// hist.add(0.5, 1000.0);
+/// hist.add(1.5, 1000.0 + 3_f64.ln());
+/// hist.ln_density(1); // 0.75_f64.ln()
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogHistogram<T> {
+    low: T,
+    high: T,
+    bins: Vec<StreamingLnSumExp<T>>,
+}
+
+impl<T> LogHistogram<T>
+where
+    T: Float,
+{
+    /// Create an empty histogram with `bins` equal width bins between `low` and `high`
+    ///
+    /// # Panics
+    ///
+    /// If `bins` is zero, `low` isn't less than `high`, or the width of the range is infinite.
+    #[must_use]
+    pub fn new(low: T, high: T, bins: usize) -> Self {
+        assert!(bins > 0, "histogram must have at least one bin");
+        assert!(
+            low < high && (high - low).is_finite(),
+            "histogram range must be finite and nonempty"
+        );
+        LogHistogram {
+            low,
+            high,
+            bins: vec![StreamingLnSumExp::new(); bins],
+        }
+    }
+
+    /// The number of bins
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Always false, since a histogram has at least one bin
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bins.is_empty()
+    }
+
+    /// The bin that `value` falls in, or `None` if it's out of range or NaN
+    #[must_use]
+    pub fn bin(&self, value: T) -> Option<usize> {
+        if value < self.low || value > self.high || value.is_nan() {
+            None
+        } else {
+            let scaled = (value - self.low) / (self.high - self.low) * self.len_t();
+            let index = scaled.floor().to_usize().unwrap_or(usize::MAX);
+            Some(index.min(self.len() - 1))
+        }
+    }
+
+    /// Add `value` with weight `exp(log_weight)`
+    ///
+    /// This returns the bin the value was added to, or `None` if it wasn't binned.
+    pub fn add(&mut self, value: T, log_weight: T) -> Option<usize> {
+        let index = self.bin(value)?;
+        self.bins[index].push(log_weight);
+        Some(index)
+    }
+
+    /// Add every value from another histogram
+    ///
+    /// # Panics
+    ///
+    /// If the histograms don't have the same range and number of bins.
+    pub fn merge(&mut self, other: &Self) {
+        assert!(
+            self.low == other.low && self.high == other.high && self.len() == other.len(),
+            "histograms must have the same bins"
+        );
+        for (bin, other) in self.bins.iter_mut().zip(&other.bins) {
+            bin.merge(other);
+        }
+    }
+
+    /// The log of the total weight in bin `index`
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    #[must_use]
+    pub fn ln_mass(&self, index: usize) -> T {
+        self.bins[index].ln_sum_exp()
+    }
+
+    /// The log of the total weight in every bin
+    #[must_use]
+    pub fn ln_total(&self) -> T {
+        let mut total = StreamingLnSumExp::new();
+        for bin in &self.bins {
+            total.merge(bin);
+        }
+        total.ln_sum_exp()
+    }
+
+    /// The log of the normalized density in bin `index`
+    ///
+    /// This is the bin's mass divided by the total mass and the bin width, so the exponentials of
+    /// the densities integrate to one over the range. A histogram without any weight returns NaN.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    #[must_use]
+    pub fn ln_density(&self, index: usize) -> T {
+        self.ln_mass(index) - self.ln_total() - self.ln_width()
+    }
+
+    /// The log of the normalized density in every bin
+    ///
+    /// This is the same as calling [`ln_density`][Self::ln_density] for every bin, but only
+    /// computes the total once.
+    #[must_use]
+    pub fn ln_densities(&self) -> Vec<T> {
+        let norm = self.ln_total() + self.ln_width();
+        self.bins
+            .iter()
+            .map(|bin| bin.ln_sum_exp() - norm)
+            .collect()
+    }
+
+    /// The number of bins as `T`
+    fn len_t(&self) -> T {
+        cast::<T>(self.len())
+    }
+
+    /// The log of the width of a bin
+    fn ln_width(&self) -> T {
+        ln(self.high - self.low) - ln(self.len_t())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LogHistogram;
+
+    #[test]
+    fn test_add() {
+        let mut hist = LogHistogram::new(0.0, 1.0, 4);
+        assert_eq!(hist.len(), 4);
+        assert!(!hist.is_empty());
+        assert_eq!(hist.add(0.0, 0.0), Some(0));
+        assert_eq!(hist.add(0.3, 0.0), Some(1));
+        assert_eq!(hist.add(0.3, 0.0), Some(1));
+        assert_eq!(hist.add(1.0, 0.0), Some(3));
+        assert_eq!(hist.add(-0.1, 0.0), None);
+        assert_eq!(hist.add(1.1, 0.0), None);
+        assert_eq!(hist.add(f64::NAN, 0.0), None);
+
+        assert_eq!(hist.ln_mass(0), 0.0);
+        assert_close!(hist.ln_mass(1), 2_f64.ln());
+        assert_eq!(hist.ln_mass(2), f64::NEG_INFINITY);
+        assert_close!(hist.ln_total(), 4_f64.ln());
+    }
+
+    #[test]
+    fn test_ln_density() {
+        let mut hist = LogHistogram::new(-1.0, 1.0, 2);
+        hist.add(-0.5, -2000.0);
+        hist.add(0.5, -2000.0 + 3_f64.ln());
+        // each bin has width one
+        assert_close!(hist.ln_density(0), 0.25_f64.ln());
+        assert_close!(hist.ln_density(1), 0.75_f64.ln());
+
+        let mut hist = LogHistogram::new(0_f32, 10.0, 5);
+        for val in 0_u8..10 {
+            hist.add(f32::from(val), 500.0);
+        }
+        for density in hist.ln_densities() {
+            assert_close!(density, 0.1_f32.ln());
+        }
+
+        let hist = LogHistogram::new(0_f64, 1.0, 3);
+        assert!(hist.ln_density(0).is_nan());
+    }
+
+    #[test]
+    fn test_merge() {
+        let vals: Vec<f64> = (0..100).map(|n| f64::from(n).sin()).collect();
+        let mut all = LogHistogram::new(-1.0, 1.0, 7);
+        let mut left = LogHistogram::new(-1.0, 1.0, 7);
+        let mut right = LogHistogram::new(-1.0, 1.0, 7);
+        for (i, &val) in vals.iter().enumerate() {
+            let log_weight = val * 100.0;
+            all.add(val, log_weight);
+            if i % 3 == 0 {
+                left.add(val, log_weight);
+            } else {
+                right.add(val, log_weight);
+            }
+        }
+        left.merge(&right);
+        for (res, expected) in left.ln_densities().into_iter().zip(all.ln_densities()) {
+            assert_close!(res, expected);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "histograms must have the same bins")]
+    fn test_merge_mismatch() {
+        let mut hist = LogHistogram::new(0.0, 1.0, 3);
+        hist.merge(&LogHistogram::new(0.0, 1.0, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "histogram range must be finite and nonempty")]
+    fn test_new_empty_range() {
+        let _ = LogHistogram::new(1.0, 1.0, 3);
+    }
+}
@@ -0,0 +1,148 @@
+//! Log-domain operations on `inari` intervals
+//!
+//! [`Interval`] doesn't implement [`Float`][num_traits::Float], so like
+//! [`NotNanLogExp`][crate::NotNanLogExp] it gets its own trait. `ln_add_exp` and `ln_sum_exp` are
+//! increasing in every argument, so an enclosure of the result only needs an enclosure of the
+//! result at the lower endpoints and at the upper endpoints. Both are computed with `inari`'s
+//! interval arithmetic, so the returned interval always contains the exact result for every
+//! choice of values in the inputs, not just the result of rounding.
+//!
+//! Intervals can't contain negative infinity, so a lower bound of negative infinity means the
+//! probability could be arbitrarily close to zero.
+use inari::Interval;
+
+/// A lower and upper bound on `ln_sum_exp` of exact values
+///
+/// Negative infinity is treated as an exact zero probability.
+fn ln_sum_exp_bounds(points: impl Iterator<Item = f64> + Clone) -> (f64, f64) {
+    let max = points.clone().fold(f64::NEG_INFINITY, f64::max);
+    if max.is_infinite() {
+        (max, max)
+    } else {
+        let point = |val| Interval::try_from((val, val)).expect("finite values are intervals");
+        let shift = point(max);
+        let sum = points
+            .filter(|&val| val != f64::NEG_INFINITY)
+            .map(|val| (point(val) - shift).exp())
+            .fold(point(0.0), |sum, term| sum + term);
+        let res = sum.ln() + shift;
+        (res.inf(), res.sup())
+    }
+}
+
+/// Log-domain operations on intervals with rigorous bounds
+pub trait IntervalLogExp: Sized {
+    /// Compute an enclosure of [`ln_add_exp`][crate::LogAddExp::ln_add_exp] of every pair of
+    /// values in the intervals
+    ///
+    /// The result is empty if either input is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inari::interval;
+    /// use logaddexp::IntervalLogExp;
+    ///
+    /// let res = interval!(0.0, 0.0).unwrap().ln_add_exp(interval!(0.0, 0.0).unwrap());
+    /// assert!(res.contains(2_f64.ln()));
+    /// ```
+    #[must_use]
+    fn ln_add_exp(self, other: Self) -> Self;
+}
+
+impl IntervalLogExp for Interval {
+    fn ln_add_exp(self, other: Self) -> Self {
+        ln_sum_exp_interval([self, other])
+    }
+}
+
+/// Compute an enclosure of `ln_sum_exp` of every choice of values in the intervals
+///
+/// An empty iterator, or one with an empty interval, returns an empty interval, since the log of
+/// zero isn't a real number.
+///
+/// # Examples
+///
+/// ```
+/// use inari::interval;
+/// use logaddexp::{ln_sum_exp_interval, LogAddExp};
+///
+/// let vals = [interval!(1.0, 1.5).unwrap(), interval!(2.0, 2.0).unwrap()];
+/// let res = ln_sum_exp_interval(vals);
+/// assert!(res.contains(1_f64.ln_add_exp(2.0)));
+/// assert!(res.contains(1.5_f64.ln_add_exp(2.0)));
+/// ```
+// the expect is unreachable
+#[allow(clippy::missing_panics_doc)]
+pub fn ln_sum_exp_interval<I>(vals: I) -> Interval
+where
+    I: IntoIterator<Item = Interval>,
+{
+    let vals: Vec<_> = vals.into_iter().collect();
+    if vals.is_empty() || vals.iter().any(|val| val.is_empty()) {
+        Interval::EMPTY
+    } else {
+        let (lower, _) = ln_sum_exp_bounds(vals.iter().copied().map(Interval::inf));
+        let (_, upper) = ln_sum_exp_bounds(vals.iter().copied().map(Interval::sup));
+        Interval::try_from((lower, upper)).expect("bounds are ordered")
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_interval, IntervalLogExp};
+    use crate::{LogAddExp, LogSumExp};
+    use inari::{interval, Interval};
+
+    fn point(val: f64) -> Interval {
+        interval!(val, val).unwrap()
+    }
+
+    #[test]
+    fn test_ln_add_exp() {
+        for (a, b) in [(0.0, 0.0), (1.0, 2.0), (-700.0, 700.0), (1e-20, -1e-20)] {
+            let res = point(a).ln_add_exp(point(b));
+            assert!(res.contains(a.ln_add_exp(b)));
+            assert!(res.wid() <= 1e-12 * res.mag().max(1.0));
+        }
+
+        let res = interval!(0.0, 1.0).unwrap().ln_add_exp(point(0.0));
+        assert!(res.contains(2_f64.ln()));
+        assert!(res.contains(1_f64.ln_add_exp(0.0)));
+        assert!(res.inf() <= 2_f64.ln());
+
+        let res = interval!(f64::NEG_INFINITY, 0.0)
+            .unwrap()
+            .ln_add_exp(point(1.0));
+        assert_eq!(res.inf(), 1.0);
+        assert!(res.contains(0_f64.ln_add_exp(1.0)));
+
+        let res = interval!(0.0, f64::INFINITY)
+            .unwrap()
+            .ln_add_exp(point(1.0));
+        assert_eq!(res.sup(), f64::INFINITY);
+
+        assert!(Interval::EMPTY.ln_add_exp(point(1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_ln_sum_exp_interval() {
+        let vals: Vec<f64> = (1..100).map(|n| f64::from(n).ln()).collect();
+        let res = ln_sum_exp_interval(vals.iter().map(|&val| point(val)));
+        assert!(res.contains(vals.iter().copied().ln_sum_exp()));
+        assert!(res.wid() < 1e-12);
+
+        // the rounded logs are only within an ulp of the exact ones, so widen them to enclose the
+        // exact sum
+        let res = ln_sum_exp_interval(
+            vals.iter()
+                .map(|&val| interval!(val.next_down(), val.next_up()).unwrap()),
+        );
+        assert!(res.contains(4950_f64.ln()));
+        assert!(res.wid() < 1e-12);
+
+        assert!(ln_sum_exp_interval([]).is_empty());
+        assert!(ln_sum_exp_interval([point(1.0), Interval::EMPTY]).is_empty());
+    }
+}
@@ -22,12 +22,214 @@
 //!
 //! (1..100).into_iter().map(|v| v as f64).ln_sum_exp();
 //! ```
+//!
+//! # Reproducibility
+//!
+//! With the `reproducible` feature, every `exp` and `ln` in the CPU kernels is computed with
+//! [`libm`](https://docs.rs/libm) instead of the platform's math library, so `f32` and `f64`
+//! results are bit-identical on Linux, macOS, Windows, and wasm. This doesn't cover the GPU, CUDA,
+//! and wasm SIMD kernels, which evaluate `exp` themselves.
 #![warn(missing_docs)]
 #![warn(clippy::pedantic)]
 
-use num_traits::{Float, FloatConst, Zero};
+use math::{cast, exp, exp_m1, ln, ln_1p};
+use num_traits::{Float, Zero};
 use std::ops::Add;
 
+#[cfg(test)]
+macro_rules! assert_close {
+    ($a:expr, $b:expr, rtol = $rtol:expr, atol = $atol:expr) => {{
+        let a = $a;
+        let b = $b;
+        assert!(
+            (a - b).abs() <= $atol + $rtol * b.abs(),
+            "assertion failed: `(left !== right)`\n  left: `{:?}`,\n right: `{:?}`",
+            a,
+            b,
+        );
+    }};
+    ($a:expr, $b:expr, atol = $atol:expr, rtol = $rtol:expr) => {
+        assert_close!($a, $b, rtol = $rtol, atol = $atol);
+    };
+    ($a:expr, $b:expr, rtol = $rtol:expr) => {
+        assert_close!($a, $b, rtol = $rtol, atol = 1e-8);
+    };
+    ($a:expr, $b:expr, atol = $atol:expr) => {
+        assert_close!($a, $b, atol = $atol, rtol = 1e-5);
+    };
+    ($a:expr, $b:expr) => {
+        assert_close!($a, $b, rtol = 1e-5);
+    };
+}
+
+mod arity;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+mod atomic;
+mod attention;
+mod batch;
+#[cfg(feature = "burn")]
+pub mod burn;
+#[cfg(feature = "candle")]
+pub mod candle;
+mod close;
+mod combinatorics;
+mod const_fn;
+mod counts;
+#[cfg(feature = "cuda")]
+pub mod cuda;
+mod cumulative;
+mod decayed;
+#[cfg(feature = "decimal")]
+mod decimal;
+#[cfg(feature = "rand")]
+mod distributions;
+mod divergence;
+mod elementwise;
+mod error;
+mod fenwick;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fixed")]
+mod fixed_point;
+mod format;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+mod grid;
+mod group;
+mod histogram;
+#[cfg(feature = "interval")]
+mod interval;
+mod logodds;
+mod loss;
+mod lut;
+mod map;
+mod masked;
+mod math;
+mod mixture;
+mod moments;
+mod nested;
+mod ops;
+#[cfg(feature = "ordered")]
+mod ordered;
+mod parallel;
+mod partition;
+mod phred;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "posit")]
+mod posit;
+#[cfg(feature = "python")]
+pub mod python;
+mod quantized;
+mod real;
+mod rolling;
+mod segment;
+mod sharded;
+mod sigmoid;
+mod signed;
+#[cfg(all(feature = "wasm", target_arch = "wasm32", target_feature = "simd128"))]
+mod simd128;
+mod sketch;
+mod slice;
+mod softmax;
+mod sparse;
+mod streaming;
+mod strided;
+mod sum;
+#[cfg(feature = "tch")]
+pub mod tch;
+mod truncated;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod windowed;
+
+pub use arity::{ln_add_exp3, ln_add_exp4, ln_sum_exp_array, TupleLogAddExp};
+pub use atomic::AtomicLnSumExp;
+pub use attention::{attention_log_softmax, AttentionShape};
+pub use batch::ln_sum_exp_offsets;
+#[cfg(feature = "parallel")]
+pub use batch::par_ln_sum_exp_offsets;
+pub use close::ln_is_close;
+pub use combinatorics::{
+    ln_binomial, ln_multinomial, multinomial_ln_pmf, multinomial_ln_pmf_normalized, NotNormalized,
+};
+pub use const_fn::{const_ln_add_exp_f32, const_ln_add_exp_f64};
+pub use counts::{ln_sum_exp_counts, ln_sum_exp_with_counts};
+pub use cumulative::{ln_cum_sum_exp, ln_cum_sum_exp_rev, ln_quantile, ln_quantile_cdf};
+pub use decayed::DecayedLnSumExp;
+#[cfg(feature = "decimal")]
+pub use decimal::DecimalLogExp;
+#[cfg(feature = "rand")]
+pub use distributions::{LnExponential, LnGamma, LnLogUniform};
+pub use divergence::{
+    cross_entropy, entropy, entropy_normalized, js_div, kl_div, kl_div_normalized,
+};
+pub use elementwise::{
+    checked_ln_sub_exp_slices, ln_add_exp_scalar, ln_sub_exp_in_place, ln_sub_exp_slices,
+    NegativeDifference,
+};
+pub use error::ln_sum_exp_with_error;
+pub use fenwick::LogSumExpFenwickTree;
+#[cfg(feature = "fixed")]
+pub use fixed_point::FixedLogExp;
+pub use format::{display_linear, parse_linear, DisplayLinear, ParseLinearError};
+pub use grid::{geomspace, ln_linspace, Geomspace, LnLinspace};
+pub use group::{ln_sum_exp_by_key, ln_sum_exp_partition, segment_ln_sum_exp};
+pub use histogram::LogHistogram;
+#[cfg(feature = "interval")]
+pub use interval::{ln_sum_exp_interval, IntervalLogExp};
+pub use logodds::{
+    combine_ln_bayes_factors, ln_odds_to_ln_prob, ln_prob_to_ln_odds, logit_average,
+    logit_average_weighted, posterior_ln_odds, posterior_ln_prob,
+};
+#[cfg(feature = "parallel")]
+pub use loss::par_softmax_cross_entropy;
+pub use loss::{bce_with_logits, bce_with_logits_mean, softmax_cross_entropy, CrossEntropyTargets};
+pub use lut::LnAddExpTable;
+pub use map::{ln_sum_exp_map, log_normalize_map, LogAddExpMerge};
+pub use masked::{ln_sum_exp_masked, ln_sum_exp_masked_iter};
+pub use mixture::mixture_ln_pdf;
+pub use moments::{
+    ln_harmonic_mean_exp, ln_mean_exp_log_weighted, ln_mean_exp_weighted, ln_var_exp,
+    StreamingLnMoments,
+};
+pub use nested::{ln_sum_exp_nested, ln_sum_exp_nested_cols, ln_sum_exp_nested_rows};
+pub use ops::Ln;
+#[cfg(feature = "ordered")]
+pub use ordered::{ln_sum_exp_not_nan, NotNanLogExp};
+pub use parallel::{ln_sum_exp_parallel, ln_sum_exp_parallel_deterministic};
+pub use partition::ln_partition_schedule;
+pub use phred::{
+    ln_prob_to_phred, phred_all_wrong, phred_any_wrong, phred_merge_agreeing, phred_round_capped,
+    phred_to_ln_prob,
+};
+#[cfg(feature = "posit")]
+pub use posit::PositLogExp;
+pub use quantized::QuantizedLn;
+pub use real::{ln_add_exp_real, ln_sub_exp_real, ln_sum_exp_real};
+pub use rolling::RollingLnSumExp;
+pub use segment::LogSumExpSegmentTree;
+pub use sharded::ShardedLnSumExp;
+pub use sigmoid::{
+    checked_logit, log_sigmoid, log_sigmoid_in_place, logit, logit_clamped, NotAProbability,
+};
+pub use signed::ln_sum_exp_signed;
+pub use sketch::LnSumExpSketch;
+pub use slice::ln_sum_exp_slice;
+pub use softmax::{
+    bayes_update, ln_normalize_collect, log_softmax_jvp, log_softmax_vjp, softmax_collect,
+    softmax_jvp, softmax_vjp,
+};
+pub use sparse::{
+    sparse_ln_sum_exp, sparse_ln_sum_exp_with_fill, sparse_softmax, sparse_softmax_with_fill,
+};
+pub use streaming::{ln_sum_exp_by, StreamingLnSumExp};
+pub use strided::{ln_sum_exp_axes, ln_sum_exp_cols, ln_sum_exp_rows, ln_sum_exp_strided};
+pub use sum::LnExp;
+pub use truncated::{ln_sum_exp_top_k, ln_sum_exp_top_k_with_rest, ln_sum_exp_truncated};
+pub use windowed::TimeWindowedLnSumExp;
+
 /// A trait for computing `ln_add_exp`
 pub trait LogAddExp<Rhs = Self> {
     /// The result of the computation
@@ -36,7 +238,9 @@ pub trait LogAddExp<Rhs = Self> {
     /// Compute the log of the addition of the exponentials
     ///
     /// This computes the same value value as `(self.exp() + other.exp()).ln()` but in a more
-    /// numerically stable way then computing it using that formula.
+    /// numerically stable way then computing it using that formula. Positive infinity wins over
+    /// NaN, like it does in [`ln_sum_exp`][LogSumExp::ln_sum_exp], so the result doesn't depend on
+    /// how many values are combined at once.
     ///
     /// # Examples
     ///
@@ -47,23 +251,33 @@ pub trait LogAddExp<Rhs = Self> {
     fn ln_add_exp(self, other: Rhs) -> Self::Output;
 }
 
+/// `ln(2)` converted to `T`, which avoids requiring `FloatConst`
+fn ln_2<T: Float>() -> T {
+    cast::<T>(std::f64::consts::LN_2)
+}
+
 impl<T> LogAddExp for T
 where
-    T: Float + FloatConst,
+    T: Float,
 {
     type Output = T;
 
     fn ln_add_exp(self, other: Self) -> Self {
         if self == other {
-            self + Self::LN_2()
+            self + ln_2()
         } else {
             let diff = self - other;
             if diff.is_nan() {
-                diff
+                // equal infinities were handled above, so one of the values is NaN
+                if self == Self::infinity() || other == Self::infinity() {
+                    Self::infinity()
+                } else {
+                    diff
+                }
             } else if diff > Self::zero() {
-                self + (-diff).exp().ln_1p()
+                self + ln_1p(exp(-diff))
             } else {
-                other + diff.exp().ln_1p()
+                other + ln_1p(exp(diff))
             }
         }
     }
@@ -71,7 +285,7 @@ where
 
 impl<'a, T> LogAddExp<&'a T> for T
 where
-    T: Float + FloatConst,
+    T: Float,
 {
     type Output = T;
 
@@ -80,6 +294,80 @@ where
     }
 }
 
+/// Compute `ln_add_exp` of two values that convert losslessly to `f64`
+///
+/// This allows mixing `f32` and `f64` operands without casts. It isn't a [`LogAddExp`]
+/// implementation between `f32` and `f64` because extra implementations on the primitives make
+/// calls with literal arguments, like `x.ln_add_exp(0.0)`, ambiguous.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp_mixed;
+/// ln_add_exp_mixed(1_f32, 2_f64); // 1_f64.ln_add_exp(2.0)
+/// ```
+#[must_use]
+pub fn ln_add_exp_mixed(a: impl Into<f64>, b: impl Into<f64>) -> f64 {
+    a.into().ln_add_exp(b.into())
+}
+
+/// A trait for computing `ln_sub_exp`
+pub trait LogSubExp<Rhs = Self> {
+    /// The result of the computation
+    type Output;
+
+    /// Compute the log of the difference of the exponentials
+    ///
+    /// This computes the same value as `(self.exp() - other.exp()).ln()` but in a more numerically
+    /// stable way then computing it using that formula. If `other` is greater than `self` the
+    /// difference is negative and the result is NaN.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogSubExp;
+    /// 3_f64.ln().ln_sub_exp(0.0); // 2_f64.ln()
+    /// ```
+    fn ln_sub_exp(self, other: Rhs) -> Self::Output;
+}
+
+impl<T> LogSubExp for T
+where
+    T: Float,
+{
+    type Output = T;
+
+    fn ln_sub_exp(self, other: Self) -> Self {
+        if self == other {
+            if self == Self::infinity() {
+                Self::nan()
+            } else {
+                Self::neg_infinity()
+            }
+        } else {
+            let diff = other - self;
+            if diff.is_nan() || diff > Self::zero() {
+                Self::nan()
+            } else if diff > -ln_2::<T>() {
+                self + ln(-exp_m1(diff))
+            } else {
+                self + ln_1p(-exp(diff))
+            }
+        }
+    }
+}
+
+impl<'a, T> LogSubExp<&'a T> for T
+where
+    T: Float,
+{
+    type Output = T;
+
+    fn ln_sub_exp(self, other: &'a Self) -> T {
+        self.ln_sub_exp(*other)
+    }
+}
+
 /// A trait for computing `ln_sum_exp`
 pub trait LogSumExp {
     /// The result of the computation
@@ -91,6 +379,10 @@ pub trait LogSumExp {
     /// numerically stable way then computing it using that formula. This is also slightly more
     /// stable then doing `self.reduce(|a, b| a.ln_add_exp(b))`.
     ///
+    /// If any value is positive infinity the result is positive infinity, even if other values
+    /// are NaN, so the traversal stops as soon as it finds one. Otherwise any NaN makes the result
+    /// NaN. Every reduction in the crate handles these values the same way.
+    ///
     /// # Examples
     ///
     /// ```
@@ -98,26 +390,204 @@ pub trait LogSumExp {
     /// [1.0, 2.0, 4.0].into_iter().ln_sum_exp();
     /// ```
     fn ln_sum_exp(self) -> Self::Output;
+
+    /// Compute the log of the sum of exponentials along with the maximum and count
+    ///
+    /// This computes the same value as [`ln_sum_exp`][Self::ln_sum_exp], but also returns
+    /// statistics that it finds along the way, so getting them doesn't require another pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogSumExp;
+    /// let stats = [1.0, 2.0, 4.0].into_iter().ln_sum_exp_with_stats();
+    /// assert_eq!(stats.max, 4.0);
+    /// assert_eq!(stats.count, 3);
+    /// ```
+    fn ln_sum_exp_with_stats(self) -> LnSumExpStats<Self::Output>
+    where
+        Self: Iterator<Item = Self::Output> + Clone + Sized,
+        Self::Output: Float,
+    {
+        let mut vals = self.clone();
+        if let Some(mut max) = vals.next() {
+            let mut count = 1;
+            for val in vals {
+                max = max.max(val);
+                count += 1;
+            }
+            LnSumExpStats {
+                max,
+                ln_sum_exp: ln_sum_exp_with_nan_max(self, max),
+                count,
+            }
+        } else {
+            LnSumExpStats {
+                max: Self::Output::neg_infinity(),
+                ln_sum_exp: Self::Output::neg_infinity(),
+                count: 0,
+            }
+        }
+    }
+
+    /// Compute the log of the sum of exponentials, accumulating the sum in a wider type
+    ///
+    /// This computes the same value as [`ln_sum_exp`][Self::ln_sum_exp], but the shifted
+    /// exponentials are summed as `A` before converting the result back. For long sequences of
+    /// `f32` values, accumulating in `f64` recovers several digits of precision for little cost.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogSumExp;
+    /// [1.0_f32, 2.0, 4.0].into_iter().ln_sum_exp_accumulate::<f64>();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a value can't be converted between `Self::Output` and `A` with
+    /// [`NumCast`][num_traits::NumCast]. This never happens between primitive floats, where out of
+    /// range values become infinite.
+    fn ln_sum_exp_accumulate<A>(self) -> Self::Output
+    where
+        Self: Iterator<Item = Self::Output> + Clone + Sized,
+        Self::Output: Float,
+        A: Float,
+    {
+        let mut vals = self.clone();
+        if let Some(mut max) = vals.next() {
+            // nothing can change an infinite max, so skip the rest of the traversal
+            while max != Self::Output::infinity() {
+                match vals.next() {
+                    Some(val) => max = max.max(val),
+                    None => break,
+                }
+            }
+            if max.is_finite() {
+                let sum = self
+                    .map(|val| cast::<A>(exp(val - max)))
+                    .fold(A::zero(), A::add);
+                cast(cast::<A>(max) + ln(sum))
+            } else {
+                ln_sum_exp_with_nan_max(self, max)
+            }
+        } else {
+            Self::Output::neg_infinity()
+        }
+    }
+
+    /// Compute the log of the sum of exponentials as a wider type
+    ///
+    /// Every value is converted to `O` before computing [`ln_sum_exp`][Self::ln_sum_exp], so
+    /// compact `f32` values can produce an `f64` result without collecting them first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogSumExp;
+    /// let res: f64 = [1.0_f32, 2.0, 4.0].into_iter().ln_sum_exp_as::<f64>();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a value can't be converted to `O` with [`NumCast`][num_traits::NumCast]. This
+    /// never happens between primitive floats, where out of range values become infinite.
+    fn ln_sum_exp_as<O>(self) -> O
+    where
+        Self: Iterator<Item = Self::Output> + Clone + Sized,
+        Self::Output: Float,
+        O: Float,
+    {
+        self.map(cast::<O>).ln_sum_exp()
+    }
+}
+
+/// The result of [`ln_sum_exp_with_stats`][LogSumExp::ln_sum_exp_with_stats]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LnSumExpStats<T> {
+    /// The maximum value, ignoring NaN like [`Float::max`], or negative infinity if empty
+    pub max: T,
+    /// The log of the sum of exponentials
+    pub ln_sum_exp: T,
+    /// The number of values
+    pub count: usize,
+}
+
+/// Compute `ln_sum_exp` in a single pass given the maximum value
+///
+/// [`ln_sum_exp`][LogSumExp::ln_sum_exp] first finds the maximum, which requires iterating
+/// twice. If the maximum is already known, this skips that pass, so it works for iterators that
+/// aren't [Clone]. `max` doesn't need to be exact: any upper bound gives the same result as long
+/// as it isn't so far above the values that their shifted exponentials underflow. Values larger
+/// than `max` are still included, but risk overflow.
+///
+/// If `max` isn't finite it's returned unchanged, matching what `ln_sum_exp` does when the
+/// maximum is infinite.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_with_max;
+/// ln_sum_exp_with_max([1.0, 2.0, 4.0], 4.0);
+/// ```
+pub fn ln_sum_exp_with_max<I>(vals: I, max: I::Item) -> I::Item
+where
+    I: IntoIterator,
+    I::Item: Float,
+{
+    if max.is_finite() {
+        let sum = vals
+            .into_iter()
+            .map(|val| exp(val - max))
+            .reduce(I::Item::add)
+            .unwrap_or_else(I::Item::zero);
+        ln(sum) + max
+    } else {
+        max
+    }
+}
+
+/// [`ln_sum_exp_with_max`] for a maximum that ignored NaN
+///
+/// NaN still has to win when every other value is negative infinity, which the sum alone can't
+/// detect.
+fn ln_sum_exp_with_nan_max<I>(vals: I, max: I::Item) -> I::Item
+where
+    I: Iterator + Clone,
+    I::Item: Float,
+{
+    if max == I::Item::neg_infinity() && vals.clone().any(I::Item::is_nan) {
+        I::Item::nan()
+    } else {
+        ln_sum_exp_with_max(vals, max)
+    }
 }
 
 impl<T> LogSumExp for T
 where
     T: Iterator + Clone,
-    T::Item: Float + FloatConst,
+    T::Item: Float,
 {
     type Output = T::Item;
 
     fn ln_sum_exp(self) -> Self::Output {
-        if let Some(max) = self.clone().reduce(Self::Output::max) {
-            if max.is_finite() {
-                let sum = self
-                    .map(|val| (val - max).exp())
-                    .reduce(Self::Output::add)
-                    .unwrap_or_else(Self::Output::zero);
-                sum.ln() + max
-            } else {
-                max
+        // two values are faster and more accurate as a single ln_add_exp
+        if self.size_hint() == (2, Some(2)) {
+            let mut vals = self.clone();
+            if let (Some(first), Some(second), None) = (vals.next(), vals.next(), vals.next()) {
+                return first.ln_add_exp(second);
+            }
+        }
+        let mut vals = self.clone();
+        if let Some(mut max) = vals.next() {
+            // nothing can change an infinite max, so skip the rest of the traversal
+            while max != Self::Output::infinity() {
+                match vals.next() {
+                    Some(val) => max = max.max(val),
+                    None => break,
+                }
             }
+            ln_sum_exp_with_nan_max(self, max)
         } else {
             Self::Output::neg_infinity()
         }
@@ -125,33 +595,10 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::float_cmp)]
 mod tests {
-    use super::{LogAddExp, LogSumExp};
-
-    macro_rules! assert_close {
-        ($a:expr, $b:expr, rtol = $rtol:expr, atol = $atol:expr) => {{
-            let a = $a;
-            let b = $b;
-            assert!(
-                (a - b).abs() <= $atol + $rtol * b.abs(),
-                "assertion failed: `(left !== right)`\n  left: `{:?}`,\n right: `{:?}`",
-                a,
-                b,
-            );
-        }};
-        ($a:expr, $b:expr, atol = $atol:expr, rtol = $rtol:expr) => {
-            assert_close!($a, $b, rol = $rtol, atol = $atol);
-        };
-        ($a:expr, $b:expr, rtol = $rtol:expr) => {
-            assert_close!($a, $b, rtol = $rtol, atol = 1e-8);
-        };
-        ($a:expr, $b:expr, atol = $atol:expr) => {
-            assert_close!($a, $b, atol = $atol, rtol = 1e-5);
-        };
-        ($a:expr, $b:expr) => {
-            assert_close!($a, $b, rtol = 1e-5);
-        };
-    }
+    use super::{ln_add_exp_mixed, ln_sum_exp_with_max, LogAddExp, LogSubExp, LogSumExp};
+    use std::cell::Cell;
 
     #[test]
     fn test_ln_add_exp() {
@@ -161,6 +608,9 @@ mod tests {
         assert_close!(2_f64.ln().ln_add_exp(&0.0), 3_f64.ln());
         assert!(f64::NAN.ln_add_exp(&1.0).is_nan());
         assert!(1.0.ln_add_exp(f64::NAN).is_nan());
+        assert_eq!(f64::INFINITY.ln_add_exp(f64::NAN), f64::INFINITY);
+        assert_eq!(f64::NAN.ln_add_exp(f64::INFINITY), f64::INFINITY);
+        assert!(f64::NEG_INFINITY.ln_add_exp(f64::NAN).is_nan());
         assert_eq!(f64::INFINITY.ln_add_exp(&0.0), f64::INFINITY);
         assert_eq!(1.0.ln_add_exp(f64::INFINITY), f64::INFINITY);
         assert_eq!(f64::INFINITY.ln_add_exp(f64::INFINITY), f64::INFINITY);
@@ -172,13 +622,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ln_add_exp_mixed() {
+        let expected = 1_f64.ln_add_exp(0.5);
+        assert_eq!(ln_add_exp_mixed(1_f32, 0.5_f64), expected);
+        assert_eq!(ln_add_exp_mixed(1_f64, 0.5_f32), expected);
+        assert_eq!(ln_add_exp_mixed(1e10_f32, 1e10_f64), 1e10 + 2_f64.ln());
+        assert!(ln_add_exp_mixed(f32::NAN, 1_f64).is_nan());
+    }
+
+    #[test]
+    fn test_ln_sub_exp() {
+        assert_close!(3_f64.ln().ln_sub_exp(0.0), 2_f64.ln());
+        assert_close!(2.0.ln_sub_exp(1.0), (2_f64.exp() - 1_f64.exp()).ln());
+        assert_close!(1e-10.ln_sub_exp(&0.0), 1e-10_f64.ln(), rtol = 1e-4);
+        assert_close!(100_f64.ln_sub_exp(1.0), 100.0);
+        assert_eq!(1.0.ln_sub_exp(1.0), f64::NEG_INFINITY);
+        assert_eq!(1.0.ln_sub_exp(f64::NEG_INFINITY), 1.0);
+        assert_eq!(f64::INFINITY.ln_sub_exp(1.0), f64::INFINITY);
+        assert_eq!(
+            f64::NEG_INFINITY.ln_sub_exp(f64::NEG_INFINITY),
+            f64::NEG_INFINITY
+        );
+        assert!(1_f64.ln_sub_exp(2.0).is_nan());
+        assert!(f64::INFINITY.ln_sub_exp(f64::INFINITY).is_nan());
+        assert!(f64::NAN.ln_sub_exp(1.0).is_nan());
+        assert!(1.0.ln_sub_exp(f64::NAN).is_nan());
+    }
+
     #[test]
     fn test_ln_sum_exp() {
-        let raw = (1..10).into_iter().map(|n| (n as f64).ln());
+        let raw = (1..10).map(|n| f64::from(n).ln());
 
         let binary = raw.clone().reduce(f64::ln_add_exp).unwrap();
-        let expected: u64 = (1..10).sum();
-        assert_close!(binary, (expected as f64).ln());
+        let expected: u32 = (1..10).sum();
+        assert_close!(binary, f64::from(expected).ln());
 
         let actual = raw.ln_sum_exp();
         assert_close!(actual, binary);
@@ -195,5 +673,117 @@ mod tests {
         );
 
         assert!([f64::NAN, 1.0].into_iter().ln_sum_exp().is_nan());
+        assert!([f64::NEG_INFINITY, f64::NAN, f64::NEG_INFINITY]
+            .into_iter()
+            .ln_sum_exp()
+            .is_nan());
+        assert_eq!(
+            [f64::NAN, 1.0, f64::INFINITY].into_iter().ln_sum_exp(),
+            f64::INFINITY
+        );
+
+        let calls = Cell::new(0);
+        let infinite = [1.0, f64::INFINITY, 2.0, 3.0]
+            .into_iter()
+            .inspect(|_| calls.set(calls.get() + 1));
+        assert_eq!(infinite.ln_sum_exp(), f64::INFINITY);
+        assert_eq!(calls.get(), 2);
+
+        assert_eq!([1.0, 2.0].into_iter().ln_sum_exp(), 1.0.ln_add_exp(2.0));
+        assert_eq!(
+            [f64::INFINITY, f64::NAN].into_iter().ln_sum_exp(),
+            [f64::INFINITY, f64::NAN, 0.0].into_iter().ln_sum_exp()
+        );
+        assert_eq!(
+            [f64::NEG_INFINITY, f64::NEG_INFINITY]
+                .into_iter()
+                .ln_sum_exp(),
+            f64::NEG_INFINITY
+        );
+    }
+
+    #[test]
+    fn test_ln_sum_exp_with_max() {
+        let raw = (1..10).map(|n| f64::from(n).ln());
+        let expected = raw.clone().ln_sum_exp();
+        assert_close!(ln_sum_exp_with_max(raw.clone(), 9_f64.ln()), expected);
+        assert_close!(ln_sum_exp_with_max(raw.clone(), 10.0), expected);
+        assert_close!(ln_sum_exp_with_max(raw, 0.0), expected);
+        assert_eq!(
+            ln_sum_exp_with_max([1.0, 2.0], f64::INFINITY),
+            f64::INFINITY
+        );
+        assert_eq!(
+            ln_sum_exp_with_max([f64::NEG_INFINITY], f64::NEG_INFINITY),
+            f64::NEG_INFINITY
+        );
+    }
+
+    #[test]
+    fn test_ln_sum_exp_with_stats() {
+        let raw = (1..10).map(|n| f64::from(n).ln());
+        let stats = raw.clone().ln_sum_exp_with_stats();
+        assert_eq!(stats.ln_sum_exp, raw.ln_sum_exp());
+        assert_eq!(stats.max, 9_f64.ln());
+        assert_eq!(stats.count, 9);
+
+        let empty = <[f64; 0]>::into_iter([]).ln_sum_exp_with_stats();
+        assert_eq!(empty.max, f64::NEG_INFINITY);
+        assert_eq!(empty.ln_sum_exp, f64::NEG_INFINITY);
+        assert_eq!(empty.count, 0);
+
+        let infinite = [f64::INFINITY, 1.0, 2.0]
+            .into_iter()
+            .ln_sum_exp_with_stats();
+        assert_eq!(infinite.ln_sum_exp, f64::INFINITY);
+        assert_eq!(infinite.count, 3);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_accumulate() {
+        let raw = (0..1_000_000_u32).map(|n| f32::from(u8::try_from(n % 10).unwrap()) * -0.1);
+        let exact = raw.clone().map(f64::from).ln_sum_exp();
+        let plain = raw.clone().ln_sum_exp();
+        let wide = raw.clone().ln_sum_exp_accumulate::<f64>();
+        assert_close!(f64::from(wide), exact, rtol = 1e-7);
+        assert!((f64::from(wide) - exact).abs() <= (f64::from(plain) - exact).abs());
+
+        assert_eq!(
+            <[f32; 0]>::into_iter([]).ln_sum_exp_accumulate::<f64>(),
+            f32::NEG_INFINITY
+        );
+        assert_eq!(
+            [f32::INFINITY, 1.0]
+                .into_iter()
+                .ln_sum_exp_accumulate::<f64>(),
+            f32::INFINITY
+        );
+        assert!([f32::NAN, 1.0]
+            .into_iter()
+            .ln_sum_exp_accumulate::<f64>()
+            .is_nan());
+
+        let calls = Cell::new(0);
+        let infinite = [1_f32, f32::INFINITY, f32::NAN, 3.0]
+            .into_iter()
+            .inspect(|_| calls.set(calls.get() + 1));
+        assert_eq!(infinite.ln_sum_exp_accumulate::<f64>(), f32::INFINITY);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_as() {
+        let raw = [1.0_f32, -2.0, 30.0, 0.5];
+        let wide = raw.into_iter().map(f64::from).ln_sum_exp();
+        assert_eq!(raw.into_iter().ln_sum_exp_as::<f64>(), wide);
+        assert_eq!(
+            raw.into_iter().ln_sum_exp_as::<f32>(),
+            raw.into_iter().ln_sum_exp()
+        );
+        // would overflow as f32
+        assert_close!(
+            [1e38_f32, 1e38].into_iter().ln_sum_exp_as::<f64>(),
+            1e38 + 2_f64.ln()
+        );
     }
 }
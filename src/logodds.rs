@@ -0,0 +1,243 @@
+//! Converting between log probabilities and log odds, and combining evidence
+//!
+//! Bayes' rule in odds form is `posterior odds = prior odds × Bayes factor`, so in log space
+//! independent evidence is just added to the prior log odds. The error-prone part is converting
+//! between log probabilities and log odds, which naively computes `1 - p` and loses everything
+//! when `p` is close to one. These conversions go through [`log_sigmoid`] and [`LogSubExp`]
+//! instead.
+use super::math::cast;
+use super::{log_sigmoid, LogSubExp};
+use num_traits::{Float, Zero};
+
+/// Convert a log probability to log odds
+///
+/// This computes `ln(p / (1 - p))` from `ln(p)`, which stays accurate when `p` is close to zero
+/// or one. A log probability of zero gives infinite log odds, and a positive log probability,
+/// which isn't a probability, gives NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_prob_to_ln_odds;
+/// ln_prob_to_ln_odds(0.75_f64.ln()); // 3_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_prob_to_ln_odds<T>(ln_prob: T) -> T
+where
+    T: Float,
+{
+    ln_prob - T::zero().ln_sub_exp(ln_prob)
+}
+
+/// Convert log odds to a log probability
+///
+/// This computes `ln(o / (1 + o))` from `ln(o)`, which is the inverse of [`ln_prob_to_ln_odds`].
+/// It's the same as [`log_sigmoid`].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_odds_to_ln_prob;
+/// ln_odds_to_ln_prob(3_f64.ln()); // 0.75_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_odds_to_ln_prob<T>(ln_odds: T) -> T
+where
+    T: Float,
+{
+    log_sigmoid(ln_odds)
+}
+
+/// Combine independent log Bayes factors into one
+///
+/// The Bayes factor of independent pieces of evidence is the product of their Bayes factors, so
+/// this is their sum. Factors of positive and negative infinity, i.e. evidence that is certain
+/// each way, are contradictory and produce NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::combine_ln_bayes_factors;
+/// combine_ln_bayes_factors([2_f64.ln(), 3_f64.ln()]); // 6_f64.ln()
+/// ```
+pub fn combine_ln_bayes_factors<I>(ln_factors: I) -> I::Item
+where
+    I: IntoIterator,
+    I::Item: Float,
+{
+    ln_factors
+        .into_iter()
+        .fold(I::Item::zero(), |acc, factor| acc + factor)
+}
+
+/// Update prior log odds with the log Bayes factors of independent evidence
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::posterior_ln_odds;
+/// posterior_ln_odds(0.25_f64.ln(), [2_f64.ln(), 6_f64.ln()]); // 3_f64.ln()
+/// ```
+pub fn posterior_ln_odds<T, I>(prior_ln_odds: T, ln_factors: I) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+{
+    prior_ln_odds + combine_ln_bayes_factors(ln_factors)
+}
+
+/// Update a prior log probability with the log Bayes factors of independent evidence
+///
+/// This converts to log odds, adds the evidence, and converts back, so it's accurate even when the
+/// prior or the posterior is very close to zero or one.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::posterior_ln_prob;
+/// posterior_ln_prob(0.2_f64.ln(), [12_f64.ln()]); // 0.75_f64.ln()
+/// ```
+pub fn posterior_ln_prob<T, I>(prior_ln_prob: T, ln_factors: I) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+{
+    ln_odds_to_ln_prob(posterior_ln_odds(
+        ln_prob_to_ln_odds(prior_ln_prob),
+        ln_factors,
+    ))
+}
+
+/// Average log probabilities in logit space
+///
+/// This converts every log probability to log odds with [`ln_prob_to_ln_odds`], takes their mean,
+/// and converts back with [`ln_odds_to_ln_prob`]. Averaging logits is the geometric mean of the
+/// odds, which is the usual way to pool calibrated forecasts, and unlike averaging probabilities
+/// it keeps the precision of estimates very close to zero or one. An empty slice returns NaN, as
+/// do estimates of both exactly zero and exactly one.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::logit_average;
+/// logit_average(&[0.5_f64.ln(), 0.9_f64.ln()]); // 0.75_f64.ln()
+/// ```
+#[must_use]
+pub fn logit_average<T>(log_probs: &[T]) -> T
+where
+    T: Float,
+{
+    let total = log_probs.iter().fold(T::zero(), |acc, &log_prob| {
+        acc + ln_prob_to_ln_odds(log_prob)
+    });
+    ln_odds_to_ln_prob(total / cast::<T>(log_probs.len()))
+}
+
+/// Average log probabilities in logit space with weights
+///
+/// This is [`logit_average`] with the mean of the log odds weighted by `weights`, which don't need
+/// to be normalized. If the weights sum to zero the result is NaN.
+///
+/// # Panics
+///
+/// If `log_probs` and `weights` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::logit_average_weighted;
+/// logit_average_weighted(&[0.5_f64.ln(), 0.9_f64.ln()], &[1.0, 1.0]); // 0.75_f64.ln()
+/// ```
+#[must_use]
+pub fn logit_average_weighted<T>(log_probs: &[T], weights: &[T]) -> T
+where
+    T: Float,
+{
+    assert_eq!(
+        log_probs.len(),
+        weights.len(),
+        "log probabilities and weights must have the same length"
+    );
+    let (total, weight) = log_probs.iter().zip(weights).fold(
+        (T::zero(), T::zero()),
+        |(total, weight_sum), (&log_prob, &weight)| {
+            (
+                total + weight * ln_prob_to_ln_odds(log_prob),
+                weight_sum + weight,
+            )
+        },
+    );
+    ln_odds_to_ln_prob(total / weight)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        combine_ln_bayes_factors, ln_odds_to_ln_prob, ln_prob_to_ln_odds, logit_average,
+        logit_average_weighted, posterior_ln_odds, posterior_ln_prob,
+    };
+
+    #[test]
+    fn test_conversions() {
+        for prob in [1e-300, 1e-10, 0.1, 0.5, 0.75, 0.999] {
+            let ln_odds = ln_prob_to_ln_odds(f64::ln(prob));
+            assert_close!(ln_odds, (prob / (1.0 - prob)).ln());
+            assert_close!(ln_odds_to_ln_prob(ln_odds), prob.ln(), atol = 1e-12);
+        }
+        // p = 1 - 1e-20 isn't representable, but its log is
+        let ln_odds = ln_prob_to_ln_odds(-1e-20_f64);
+        assert_close!(ln_odds, 20.0 * 10_f64.ln());
+        assert_close!(ln_odds_to_ln_prob(ln_odds), -1e-20);
+        assert_close!(ln_odds_to_ln_prob(-800_f64), -800.0);
+
+        assert_eq!(ln_prob_to_ln_odds(0_f64), f64::INFINITY);
+        assert_eq!(ln_prob_to_ln_odds(f64::NEG_INFINITY), f64::NEG_INFINITY);
+        assert!(ln_prob_to_ln_odds(0.5_f64).is_nan());
+        assert_eq!(ln_odds_to_ln_prob(f64::INFINITY), 0.0);
+        assert_eq!(ln_odds_to_ln_prob(f64::NEG_INFINITY), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_evidence() {
+        let factors = [2_f64.ln(), 3_f64.ln(), -0.5];
+        assert_close!(combine_ln_bayes_factors(factors), 6_f64.ln() - 0.5);
+        assert_eq!(combine_ln_bayes_factors::<[f64; 0]>([]), 0.0);
+        assert!(combine_ln_bayes_factors([f64::INFINITY, f64::NEG_INFINITY]).is_nan());
+
+        assert_close!(posterior_ln_odds(0.25_f64.ln(), [12_f64.ln()]), 3_f64.ln());
+        assert_close!(
+            posterior_ln_prob(0.2_f64.ln(), [12_f64.ln()]),
+            0.75_f64.ln()
+        );
+        assert_close!(posterior_ln_prob(-1e-15_f64, [1e-15]), -5e-16, rtol = 1e-8);
+        assert_eq!(posterior_ln_prob(-1.0, [f64::INFINITY]), 0.0);
+    }
+
+    #[test]
+    fn test_logit_average() {
+        // the odds are 1 and 9, so their geometric mean is 3
+        assert_close!(logit_average(&[0.5_f64.ln(), 0.9_f64.ln()]), 0.75_f64.ln());
+        assert_close!(logit_average(&[0.3_f64.ln()]), 0.3_f64.ln());
+        // estimates far too close to one for a probability are still averaged
+        assert_close!(logit_average(&[-1e-20_f64, -1e-30]), -1e-25, rtol = 1e-10);
+        assert!(logit_average::<f64>(&[]).is_nan());
+        assert!(logit_average(&[0.0, f64::NEG_INFINITY]).is_nan());
+        assert_eq!(logit_average(&[0.0, 0.5_f64.ln()]), 0.0);
+
+        let log_probs = [0.5_f64.ln(), 0.9_f64.ln(), 0.1_f64.ln()];
+        assert_close!(
+            logit_average_weighted(&log_probs, &[1.0, 1.0, 0.0]),
+            0.75_f64.ln()
+        );
+        assert_close!(
+            logit_average_weighted(&log_probs, &[2.0, 2.0, 2.0]),
+            logit_average(&log_probs)
+        );
+        assert_close!(
+            logit_average_weighted(&log_probs, &[0.0, 3.0, 1.0]),
+            ln_odds_to_ln_prob(9_f64.ln() / 2.0)
+        );
+        assert!(logit_average_weighted(&log_probs, &[0.0; 3]).is_nan());
+    }
+}
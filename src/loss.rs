@@ -0,0 +1,259 @@
+//! Losses computed from logits
+use super::ln_sum_exp_slice;
+use super::math::{cast, exp, ln_1p};
+use num_traits::Float;
+
+/// Compute the binary cross-entropy of a logit against a target probability
+///
+/// This is `-(t ln σ(x) + (1 - t) ln(1 - σ(x)))` where `σ` is the logistic function, computed as
+/// `max(x, 0) - x t + ln_1p(exp(-|x|))`, so it never overflows and stays accurate for logits of
+/// any magnitude. The target is usually zero or one, but any probability works.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::bce_with_logits;
+///
+/// bce_with_logits(0.0, 1.0); // 2_f64.ln()
+/// bce_with_logits(-1000.0, 1.0); // 1000.0
+/// ```
+#[must_use]
+pub fn bce_with_logits<T>(logit: T, target: T) -> T
+where
+    T: Float,
+{
+    logit.max(T::zero()) - logit * target + ln_1p(exp(-logit.abs()))
+}
+
+/// Compute the mean [`bce_with_logits`] of a batch of logits and targets
+///
+/// An empty batch returns NaN.
+///
+/// # Panics
+///
+/// If `logits` and `targets` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::bce_with_logits_mean;
+///
+/// bce_with_logits_mean(&[0.0, 0.0], &[1.0, 0.0]); // 2_f64.ln()
+/// ```
+#[must_use]
+pub fn bce_with_logits_mean<T>(logits: &[T], targets: &[T]) -> T
+where
+    T: Float,
+{
+    assert_eq!(
+        logits.len(),
+        targets.len(),
+        "logits and targets must have the same length"
+    );
+    let total = logits
+        .iter()
+        .zip(targets)
+        .fold(T::zero(), |acc, (&logit, &target)| {
+            acc + bce_with_logits(logit, target)
+        });
+    total / cast::<T>(logits.len())
+}
+
+/// The targets of [`softmax_cross_entropy`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossEntropyTargets<'a, T> {
+    /// The index of the correct class of every row
+    Classes(&'a [usize]),
+    /// A target probability for every logit, laid out like the logits
+    Probs(&'a [T]),
+}
+
+impl<T> CrossEntropyTargets<'_, T>
+where
+    T: Float,
+{
+    /// Check that the targets match the shape of the logits
+    fn check(&self, logits: &[T], (rows, cols): (usize, usize)) {
+        assert_eq!(
+            logits.len(),
+            rows * cols,
+            "logits must have rows * cols values"
+        );
+        match self {
+            Self::Classes(classes) => {
+                assert_eq!(classes.len(), rows, "there must be a class for every row");
+            }
+            Self::Probs(probs) => {
+                assert_eq!(
+                    probs.len(),
+                    rows * cols,
+                    "probs must have rows * cols values"
+                );
+            }
+        }
+    }
+
+    /// The loss of row `ind`
+    fn row_loss(&self, logits: &[T], cols: usize, ind: usize) -> T {
+        let row = &logits[ind * cols..(ind + 1) * cols];
+        let total = ln_sum_exp_slice(row);
+        match self {
+            Self::Classes(classes) => total - row[classes[ind]],
+            // zero probabilities are skipped so they don't multiply infinite logits
+            Self::Probs(probs) => probs[ind * cols..(ind + 1) * cols]
+                .iter()
+                .zip(row)
+                .filter(|(&prob, _)| prob != T::zero())
+                .fold(T::zero(), |acc, (&prob, &logit)| {
+                    acc + prob * (total - logit)
+                }),
+        }
+    }
+}
+
+/// Compute the softmax cross-entropy of every row of a matrix of logits
+///
+/// `logits` is a row-major matrix with `shape = (rows, cols)`, and the result has the loss of
+/// every row, `-Σ p_j ln softmax(x)_j`. Each row's `ln_sum_exp` is computed with
+/// [`ln_sum_exp_slice`] and combined with the target logits directly, so the log softmax is never
+/// materialized. Class targets only read one logit per row. Soft label targets skip classes with
+/// zero probability, so those classes can have logits of negative infinity.
+///
+/// # Panics
+///
+/// If `logits` or `targets` don't match `shape`, or if a class index is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::{softmax_cross_entropy, CrossEntropyTargets};
+///
+/// let logits = [0.0, 0.0, 1.0, 1.0];
+/// softmax_cross_entropy(&logits, (2, 2), CrossEntropyTargets::Classes(&[0, 1]));
+/// // [2_f64.ln(), 2_f64.ln()]
+/// softmax_cross_entropy(&logits, (2, 2), CrossEntropyTargets::Probs(&[0.5, 0.5, 1.0, 0.0]));
+/// // [2_f64.ln(), 2_f64.ln()]
+/// ```
+#[must_use]
+pub fn softmax_cross_entropy<T>(
+    logits: &[T],
+    shape: (usize, usize),
+    targets: CrossEntropyTargets<'_, T>,
+) -> Vec<T>
+where
+    T: Float,
+{
+    targets.check(logits, shape);
+    (0..shape.0)
+        .map(|ind| targets.row_loss(logits, shape.1, ind))
+        .collect()
+}
+
+/// Compute the softmax cross-entropy of every row of a matrix of logits in parallel
+///
+/// This is identical to [`softmax_cross_entropy`] but distributes rows over the rayon thread
+/// pool.
+///
+/// # Panics
+///
+/// If `logits` or `targets` don't match `shape`, or if a class index is out of bounds.
+#[cfg(feature = "parallel")]
+#[must_use]
+pub fn par_softmax_cross_entropy<T>(
+    logits: &[T],
+    shape: (usize, usize),
+    targets: CrossEntropyTargets<'_, T>,
+) -> Vec<T>
+where
+    T: Float + Send + Sync,
+{
+    use rayon::prelude::*;
+
+    targets.check(logits, shape);
+    (0..shape.0)
+        .into_par_iter()
+        .map(|ind| targets.row_loss(logits, shape.1, ind))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        bce_with_logits, bce_with_logits_mean, softmax_cross_entropy, CrossEntropyTargets,
+    };
+    use crate::LogSumExp;
+
+    fn naive(logit: f64, target: f64) -> f64 {
+        let prob = 1.0 / (1.0 + (-logit).exp());
+        -(target * prob.ln() + (1.0 - target) * (1.0 - prob).ln())
+    }
+
+    #[test]
+    fn test_bce_with_logits() {
+        for logit in [-10.0, -1.0, 0.0, 0.3, 5.0] {
+            for target in [0.0, 0.25, 1.0] {
+                assert_close!(bce_with_logits(logit, target), naive(logit, target));
+            }
+        }
+        assert_eq!(bce_with_logits(-1000.0, 1.0), 1000.0);
+        assert_eq!(bce_with_logits(1000.0, 0.0), 1000.0);
+        assert_close!(bce_with_logits(40.0, 1.0), (-40_f64).exp(), rtol = 1e-10);
+        assert_close!(bce_with_logits(-40.0, 0.0), (-40_f64).exp(), rtol = 1e-10);
+        assert_close!(bce_with_logits(1e30_f32, 0.5), 5e29);
+        assert!(bce_with_logits(f64::NAN, 1.0).is_nan());
+    }
+
+    #[test]
+    fn test_bce_with_logits_mean() {
+        let logits = [-3.0, 0.5, 2.0];
+        let targets = [0.0, 1.0, 1.0];
+        let expected = (naive(-3.0, 0.0) + naive(0.5, 1.0) + naive(2.0, 1.0)) / 3.0;
+        assert_close!(bce_with_logits_mean(&logits, &targets), expected);
+        assert!(bce_with_logits_mean::<f64>(&[], &[]).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_bce_with_logits_mean_mismatch() {
+        let _ = bce_with_logits_mean(&[1.0], &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_softmax_cross_entropy() {
+        let logits: Vec<f64> = (0..12).map(|n| f64::from(n).sin() * 3.0).collect();
+        let classes = [2, 0, 3];
+        let res = softmax_cross_entropy(&logits, (3, 4), CrossEntropyTargets::Classes(&classes));
+        for (ind, (row, &class)) in logits.chunks(4).zip(&classes).enumerate() {
+            assert_close!(res[ind], row.iter().copied().ln_sum_exp() - row[class]);
+        }
+
+        let one_hot = [0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let soft = softmax_cross_entropy(&logits, (3, 4), CrossEntropyTargets::Probs(&one_hot));
+        for (hard, soft) in res.iter().zip(&soft) {
+            assert_close!(*soft, *hard);
+        }
+
+        let probs = [0.5, 0.5, 0.0];
+        let logits = [1.0, 1.0, f64::NEG_INFINITY];
+        let res = softmax_cross_entropy(&logits, (1, 3), CrossEntropyTargets::Probs(&probs));
+        assert_close!(res[0], 2_f64.ln());
+
+        #[cfg(feature = "parallel")]
+        {
+            let logits: Vec<f64> = (0..400).map(|n| f64::from(n).cos()).collect();
+            let classes: Vec<usize> = (0..40).map(|n| n % 10).collect();
+            let targets = CrossEntropyTargets::Classes(&classes);
+            assert_eq!(
+                super::par_softmax_cross_entropy(&logits, (40, 10), targets),
+                softmax_cross_entropy(&logits, (40, 10), targets)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "class for every row")]
+    fn test_softmax_cross_entropy_mismatch() {
+        let _ = softmax_cross_entropy(&[1.0, 2.0], (1, 2), CrossEntropyTargets::Classes(&[]));
+    }
+}
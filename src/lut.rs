@@ -0,0 +1,213 @@
+//! A table-driven `ln_add_exp` for targets without a fast math library
+//!
+//! `ln_add_exp(a, b)` is `max(a, b) + ln_1p(exp(-|a - b|))`, and only the correction term needs
+//! transcendental functions. [`LnAddExpTable`] samples it at `N` evenly spaced differences and
+//! linearly interpolates between them, so evaluating it is a multiply, a truncation, and an
+//! interpolation between two entries. The table is built with the same software kernels as
+//! [`const_ln_add_exp_f64`][crate::const_ln_add_exp_f64], so it can be a `static` in flash instead
+//! of pulling `exp` and `ln_1p` into the binary.
+use super::const_fn::{exp_nonpositive, ln_1p_unit};
+use std::f32::consts::LN_2;
+
+/// Differences at or beyond this have a correction below `f32` precision
+const MAX_DIFF: f32 = 16.0;
+
+/// An approximate [`ln_add_exp`][crate::LogAddExp::ln_add_exp] for `f32` from a table of `N`
+/// samples
+///
+/// The correction term is sampled on `[0, 16]` and is zero past that, where it's smaller than
+/// `1.2e-7`. Linear interpolation has an absolute error of about `h² / 32` where
+/// `h = 16 / (N - 1)` is the spacing, so 64 entries are accurate to about `2e-3`, 256 to `1.2e-4`,
+/// and 1024 to `8e-6`. The table is `4 N` bytes.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnAddExpTable;
+///
+/// static TABLE: LnAddExpTable<256> = LnAddExpTable::new();
+///
+/// TABLE.ln_add_exp(0.0, 0.0); // 2_f32.ln()
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LnAddExpTable<const N: usize> {
+    table: [f32; N],
+    inv_step: f32,
+}
+
+impl<const N: usize> LnAddExpTable<N> {
+    /// Build the table
+    ///
+    /// This is `const`, so the table can be computed at compile time.
+    ///
+    /// # Panics
+    ///
+    /// If `N` is less than two, which fails to compile when evaluated in a `const` context.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    pub const fn new() -> Self {
+        assert!(N >= 2, "a table needs at least two entries");
+        let step = MAX_DIFF as f64 / (N - 1) as f64;
+        let mut table = [0.0; N];
+        let mut i = 0;
+        while i < N {
+            table[i] = ln_1p_unit(exp_nonpositive(-(i as f64) * step)) as f32;
+            i += 1;
+        }
+        Self {
+            table,
+            inv_step: (1.0 / step) as f32,
+        }
+    }
+
+    /// Approximate `ln_1p(exp(-diff))` for a non-negative `diff`
+    ///
+    /// This is the amount `ln_add_exp` adds to the larger of two values that are `diff` apart.
+    /// Large or infinite differences return zero, and NaN returns NaN.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LnAddExpTable;
+    ///
+    /// let table = LnAddExpTable::<64>::new();
+    /// table.correction(1.0); // (-1_f32).exp().ln_1p()
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn correction(&self, diff: f32) -> f32 {
+        if diff.is_nan() {
+            diff
+        } else if diff >= MAX_DIFF {
+            0.0
+        } else {
+            let pos = diff.max(0.0) * self.inv_step;
+            // rounding can put pos on the last sample, so interpolate from the one before it
+            let ind = (pos as usize).min(N - 2);
+            let frac = pos - ind as f32;
+            let (low, high) = (self.table[ind], self.table[ind + 1]);
+            low + frac * (high - low)
+        }
+    }
+
+    /// Approximate [`ln_add_exp`][crate::LogAddExp::ln_add_exp] with the table
+    ///
+    /// Infinities and NaN are handled exactly like the exact version.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LnAddExpTable;
+    ///
+    /// static TABLE: LnAddExpTable<1024> = LnAddExpTable::new();
+    ///
+    /// TABLE.ln_add_exp(100_f32.ln(), 0.0); // 101_f32.ln()
+    /// ```
+    #[must_use]
+    #[allow(clippy::float_cmp)]
+    pub fn ln_add_exp(&self, a: f32, b: f32) -> f32 {
+        if a == b {
+            a + LN_2
+        } else {
+            let diff = (a - b).abs();
+            if diff.is_nan() {
+                diff
+            } else if diff.is_infinite() {
+                a.max(b)
+            } else {
+                a.max(b) + self.correction(diff)
+            }
+        }
+    }
+
+    /// Approximate `ln_sum_exp` of a slice by folding [`ln_add_exp`][Self::ln_add_exp]
+    ///
+    /// Every step adds the table's error, so this is only as accurate as the table times the
+    /// length of the slice. An empty slice returns negative infinity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LnAddExpTable;
+    ///
+    /// static TABLE: LnAddExpTable<1024> = LnAddExpTable::new();
+    ///
+    /// TABLE.ln_sum_exp(&[0.0; 4]); // 4_f32.ln()
+    /// ```
+    #[must_use]
+    pub fn ln_sum_exp(&self, values: &[f32]) -> f32 {
+        values
+            .iter()
+            .fold(f32::NEG_INFINITY, |acc, &val| self.ln_add_exp(acc, val))
+    }
+}
+
+impl<const N: usize> Default for LnAddExpTable<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LnAddExpTable;
+    use crate::LogAddExp;
+
+    static TABLE: LnAddExpTable<256> = LnAddExpTable::new();
+
+    #[test]
+    fn test_correction() {
+        let small = LnAddExpTable::<64>::new();
+        let large = LnAddExpTable::<1024>::new();
+        for i in 0..2000_u16 {
+            let diff = f32::from(i) / 100.0;
+            let exact = (-diff).exp().ln_1p();
+            assert_close!(small.correction(diff), exact, atol = 2e-3);
+            assert_close!(TABLE.correction(diff), exact, atol = 1.3e-4);
+            assert_close!(large.correction(diff), exact, atol = 1e-5);
+        }
+        assert_close!(TABLE.correction(0.0), 2_f32.ln(), atol = 1e-7);
+        assert_eq!(TABLE.correction(f32::INFINITY), 0.0);
+        assert!(TABLE.correction(f32::NAN).is_nan());
+        assert_close!(
+            LnAddExpTable::<2>::new().correction(8.0),
+            0.346_57,
+            atol = 1e-4
+        );
+    }
+
+    #[test]
+    fn test_ln_add_exp() {
+        for a in [-50.0, -3.0, 0.0, 0.5, 20.0] {
+            for b in [-49.0, -3.1, 0.0, 7.0, 36.5] {
+                assert_close!(TABLE.ln_add_exp(a, b), a.ln_add_exp(b), atol = 1.3e-4);
+            }
+        }
+        assert_eq!(TABLE.ln_add_exp(f32::NEG_INFINITY, 1.0), 1.0);
+        assert_eq!(TABLE.ln_add_exp(1.0, f32::INFINITY), f32::INFINITY);
+        assert_eq!(
+            TABLE.ln_add_exp(f32::NEG_INFINITY, f32::INFINITY),
+            f32::INFINITY
+        );
+        assert_eq!(
+            TABLE.ln_add_exp(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            f32::NEG_INFINITY
+        );
+        assert!(TABLE.ln_add_exp(f32::NAN, 1.0).is_nan());
+        assert_eq!(
+            TABLE.ln_add_exp(f32::INFINITY, f32::INFINITY),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn test_ln_sum_exp() {
+        assert_close!(TABLE.ln_sum_exp(&[0.0; 4]), 4_f32.ln(), atol = 1e-3);
+        assert_eq!(TABLE.ln_sum_exp(&[]), f32::NEG_INFINITY);
+    }
+}
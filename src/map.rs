@@ -0,0 +1,198 @@
+//! Log-domain operations on maps of log masses
+use super::{LogAddExp, StreamingLnSumExp};
+use num_traits::Float;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+/// Compute `ln_sum_exp` of the values of a map
+///
+/// This works for anything that iterates by reference like a map, including [`HashMap`] and
+/// [`BTreeMap`].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_map;
+/// use std::collections::HashMap;
+///
+/// let map = HashMap::from([("a", 1.0), ("b", 2.0)]);
+/// ln_sum_exp_map(&map); // (1_f64.exp() + 2_f64.exp()).ln()
+/// ```
+pub fn ln_sum_exp_map<'a, K, T, M>(map: &'a M) -> T
+where
+    K: 'a,
+    T: Float + 'a,
+    &'a M: IntoIterator<Item = (&'a K, &'a T)>,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for (_, &val) in map {
+        acc.push(val);
+    }
+    acc.ln_sum_exp()
+}
+
+/// Normalize the values of a map in place so they're log probabilities
+///
+/// This subtracts the [`ln_sum_exp_map`] of the values from every value without allocating, and
+/// returns the subtracted normalizer.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::log_normalize_map;
+/// use std::collections::BTreeMap;
+///
+/// let mut map = BTreeMap::from([("a", 0.0), ("b", 0.0)]);
+/// let total = log_normalize_map(&mut map); // 2_f64.ln()
+/// // map == {"a": 0.5_f64.ln(), "b": 0.5_f64.ln()}
+/// ```
+pub fn log_normalize_map<K, T, M>(map: &mut M) -> T
+where
+    T: Float,
+    for<'a> &'a M: IntoIterator<Item = (&'a K, &'a T)>,
+    for<'a> &'a mut M: IntoIterator<Item = (&'a K, &'a mut T)>,
+{
+    let total = ln_sum_exp_map(&*map);
+    for (_, val) in map {
+        *val = *val - total;
+    }
+    total
+}
+
+/// A trait for merging maps of log masses
+pub trait LogAddExpMerge {
+    /// Merge two maps key-wise
+    ///
+    /// Keys that only appear in one map keep their value, and keys that appear in both are
+    /// combined with [`ln_add_exp`][LogAddExp::ln_add_exp], so the result represents the sum of
+    /// the masses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogAddExpMerge;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let left = BTreeMap::from([("a", 0.0), ("b", 1.0)]);
+    /// let right = BTreeMap::from([("a", 0.0), ("c", 2.0)]);
+    /// let merged = left.ln_add_exp_merge(right);
+    /// // merged == {"a": 2_f64.ln(), "b": 1.0, "c": 2.0}
+    /// ```
+    #[must_use]
+    fn ln_add_exp_merge(self, other: Self) -> Self;
+
+    /// Merge two maps key-wise and normalize the result
+    ///
+    /// This is the same as [`ln_add_exp_merge`][Self::ln_add_exp_merge], but the values of the
+    /// result are shifted so they're the log probabilities of a distribution.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::LogAddExpMerge;
+    /// use std::collections::HashMap;
+    ///
+    /// let left = HashMap::from([("a", 0.0)]);
+    /// let right = HashMap::from([("a", 0.0), ("b", 2_f64.ln())]);
+    /// let merged = left.ln_add_exp_merge_normalized(right);
+    /// // merged == {"a": 0.5_f64.ln(), "b": 0.5_f64.ln()}
+    /// ```
+    #[must_use]
+    fn ln_add_exp_merge_normalized(self, other: Self) -> Self;
+}
+
+impl<K, T, S> LogAddExpMerge for HashMap<K, T, S>
+where
+    K: Eq + Hash,
+    T: Float,
+    S: BuildHasher,
+{
+    fn ln_add_exp_merge(mut self, other: Self) -> Self {
+        for (key, val) in other {
+            self.entry(key)
+                .and_modify(|existing| *existing = existing.ln_add_exp(val))
+                .or_insert(val);
+        }
+        self
+    }
+
+    fn ln_add_exp_merge_normalized(self, other: Self) -> Self {
+        let mut merged = self.ln_add_exp_merge(other);
+        log_normalize_map(&mut merged);
+        merged
+    }
+}
+
+impl<K, T> LogAddExpMerge for BTreeMap<K, T>
+where
+    K: Ord,
+    T: Float,
+{
+    fn ln_add_exp_merge(mut self, other: Self) -> Self {
+        for (key, val) in other {
+            self.entry(key)
+                .and_modify(|existing| *existing = existing.ln_add_exp(val))
+                .or_insert(val);
+        }
+        self
+    }
+
+    fn ln_add_exp_merge_normalized(self, other: Self) -> Self {
+        let mut merged = self.ln_add_exp_merge(other);
+        log_normalize_map(&mut merged);
+        merged
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_map, log_normalize_map, LogAddExpMerge};
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn test_ln_add_exp_merge() {
+        let left = HashMap::from([("a", 0.0), ("b", 1.0)]);
+        let right = HashMap::from([("a", 0.0), ("c", 2.0)]);
+        let merged = left.clone().ln_add_exp_merge(right.clone());
+        assert_eq!(merged.len(), 3);
+        assert_close!(merged["a"], 2_f64.ln());
+        assert_eq!(merged["b"], 1.0);
+        assert_eq!(merged["c"], 2.0);
+
+        let normed = left.ln_add_exp_merge_normalized(right);
+        let total: f64 = normed.values().map(|val| val.exp()).sum();
+        assert_close!(total, 1.0);
+        assert_close!(normed["a"] - normed["b"], merged["a"] - merged["b"]);
+    }
+
+    #[test]
+    fn test_ln_add_exp_merge_btree() {
+        let left = BTreeMap::from([(1, f64::NEG_INFINITY), (2, 1.0)]);
+        let right = BTreeMap::from([(1, 3.0), (3, 2.0)]);
+        let merged = left.ln_add_exp_merge(right);
+        assert_eq!(
+            merged.into_iter().collect::<Vec<_>>(),
+            [(1, 3.0), (2, 1.0), (3, 2.0)]
+        );
+
+        let empty = BTreeMap::<u8, f64>::new().ln_add_exp_merge_normalized(BTreeMap::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_log_normalize_map() {
+        let mut map = HashMap::from([("a", 1.0), ("b", 2.0), ("c", f64::NEG_INFINITY)]);
+        let expected = (1_f64.exp() + 2_f64.exp()).ln();
+        assert_close!(ln_sum_exp_map(&map), expected);
+        assert_close!(log_normalize_map(&mut map), expected);
+        assert_close!(map["a"], 1.0 - expected);
+        assert_close!(map["b"], 2.0 - expected);
+        assert_eq!(map["c"], f64::NEG_INFINITY);
+        assert_close!(ln_sum_exp_map(&map), 0.0);
+
+        let mut empty = BTreeMap::<u8, f64>::new();
+        assert_eq!(ln_sum_exp_map(&empty), f64::NEG_INFINITY);
+        assert_eq!(log_normalize_map(&mut empty), f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,138 @@
+//! Masked `ln_sum_exp` reductions
+use super::math::{exp, ln};
+use super::slice::LANES;
+use super::StreamingLnSumExp;
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of the values in a slice whose mask is true
+///
+/// Values with a false mask are treated as negative infinity, so a fully masked slice returns
+/// negative infinity.
+///
+/// # Panics
+///
+/// If `values` and `mask` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_masked;
+///
+/// ln_sum_exp_masked(&[1.0, 2.0, 3.0], &[true, false, true]); // (1_f64.exp() + 3_f64.exp()).ln()
+/// ```
+#[must_use]
+pub fn ln_sum_exp_masked<T>(values: &[T], mask: &[bool]) -> T
+where
+    T: Float,
+{
+    assert_eq!(
+        values.len(),
+        mask.len(),
+        "values and mask must have the same length"
+    );
+    let mut maxes = [T::neg_infinity(); LANES];
+    for (vals, keeps) in values.chunks(LANES).zip(mask.chunks(LANES)) {
+        for ((lane, &val), &keep) in maxes.iter_mut().zip(vals).zip(keeps) {
+            if keep && val > *lane {
+                *lane = val;
+            }
+        }
+        if maxes.contains(&T::infinity()) {
+            return T::infinity();
+        }
+    }
+    let max = maxes.into_iter().fold(T::neg_infinity(), T::max);
+
+    if max.is_finite() {
+        let mut sums = [T::zero(); LANES];
+        for (vals, keeps) in values.chunks(LANES).zip(mask.chunks(LANES)) {
+            for ((lane, &val), &keep) in sums.iter_mut().zip(vals).zip(keeps) {
+                if keep {
+                    *lane = *lane + exp(val - max);
+                }
+            }
+        }
+        ln(sums.into_iter().fold(T::zero(), |a, b| a + b)) + max
+    } else if max == T::neg_infinity()
+        && values
+            .iter()
+            .zip(mask)
+            .any(|(val, &keep)| keep && val.is_nan())
+    {
+        T::nan()
+    } else {
+        max
+    }
+}
+
+/// Compute `ln_sum_exp` of the values from an iterator whose mask is true
+///
+/// This is the single pass version of [`ln_sum_exp_masked`] for iterators that aren't [Clone].
+/// Iteration stops when either `values` or `mask` is exhausted, or once an unmasked positive
+/// infinity is found.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_masked_iter;
+///
+/// let vals = [1.0, 2.0, 3.0];
+/// ln_sum_exp_masked_iter(vals, vals.map(|v| v != 2.0)); // (1_f64.exp() + 3_f64.exp()).ln()
+/// ```
+pub fn ln_sum_exp_masked_iter<T, I, M>(values: I, mask: M) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+    M: IntoIterator<Item = bool>,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for (val, keep) in values.into_iter().zip(mask) {
+        if keep {
+            acc.push(val);
+            if val == T::infinity() {
+                return acc.ln_sum_exp();
+            }
+        }
+    }
+    acc.ln_sum_exp()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_masked, ln_sum_exp_masked_iter};
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_masked() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let mask: Vec<bool> = (1..20).map(|n| n % 3 != 0).collect();
+        let expected = vals
+            .iter()
+            .zip(&mask)
+            .filter_map(|(&val, &keep)| keep.then_some(val))
+            .ln_sum_exp();
+        assert_close!(ln_sum_exp_masked(&vals, &mask), expected);
+        assert_close!(
+            ln_sum_exp_masked_iter(vals.iter().copied(), mask.iter().copied()),
+            expected
+        );
+
+        let none = vec![false; vals.len()];
+        assert_eq!(ln_sum_exp_masked(&vals, &none), f64::NEG_INFINITY);
+        assert_eq!(
+            ln_sum_exp_masked_iter(vals.iter().copied(), none),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            ln_sum_exp_masked(&[f64::NAN, f64::INFINITY, 1.0], &[false, false, true]),
+            1.0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_ln_sum_exp_masked_length() {
+        let _ = ln_sum_exp_masked(&[1.0, 2.0], &[true]);
+    }
+}
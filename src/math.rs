@@ -0,0 +1,112 @@
+//! Transcendental functions used by the kernels
+//!
+//! By default these are the [`Float`] methods, which call the platform's math library, so results
+//! can differ in the last bit between Linux, macOS, Windows, and wasm. With the `reproducible`
+//! feature they call [`libm`], a pure Rust port of musl's math library, instead. `f32` values go
+//! through the `f32` functions and every other type through the `f64` functions, so `f32` and `f64`
+//! results are bit-identical on every platform. Rust never contracts multiplies and adds into FMA
+//! instructions on its own, and the slice kernels always combine their lanes in the same order,
+//! so the transcendental functions are the only platform-dependent part of the arithmetic.
+use num_traits::{Float, NumCast, ToPrimitive};
+
+#[cfg(feature = "reproducible")]
+use std::mem::size_of;
+
+/// Apply the `libm` function for the width of `T`
+///
+/// `libm` only has `f32` and `f64` functions, so every type that isn't the width of an `f32` is
+/// narrowed to `f64` and back. That's exact for `f64`, but a wider or custom `Float` loses any
+/// precision beyond an `f64` in the transcendental functions.
+#[cfg(feature = "reproducible")]
+fn libm_call<T: Float>(val: T, single: fn(f32) -> f32, double: fn(f64) -> f64) -> T {
+    if size_of::<T>() == size_of::<f32>() {
+        T::from(single(val.to_f32().unwrap())).unwrap()
+    } else {
+        T::from(double(val.to_f64().unwrap())).unwrap()
+    }
+}
+
+/// Convert a constant or count to `T`
+///
+/// Every float can represent any primitive number, if only approximately, so this never panics
+/// when `T` is a float. Conversions to integers must already be in range.
+pub(crate) fn cast<T: NumCast>(val: impl ToPrimitive) -> T {
+    T::from(val).unwrap()
+}
+
+/// `e^val`
+#[cfg(not(feature = "reproducible"))]
+#[inline]
+pub(crate) fn exp<T: Float>(val: T) -> T {
+    val.exp()
+}
+
+/// `e^val`
+#[cfg(feature = "reproducible")]
+pub(crate) fn exp<T: Float>(val: T) -> T {
+    libm_call(val, libm::expf, libm::exp)
+}
+
+/// `e^val - 1`
+#[cfg(not(feature = "reproducible"))]
+#[inline]
+pub(crate) fn exp_m1<T: Float>(val: T) -> T {
+    val.exp_m1()
+}
+
+/// `e^val - 1`
+#[cfg(feature = "reproducible")]
+pub(crate) fn exp_m1<T: Float>(val: T) -> T {
+    libm_call(val, libm::expm1f, libm::expm1)
+}
+
+/// The natural log of `val`
+#[cfg(not(feature = "reproducible"))]
+#[inline]
+pub(crate) fn ln<T: Float>(val: T) -> T {
+    val.ln()
+}
+
+/// The natural log of `val`
+#[cfg(feature = "reproducible")]
+pub(crate) fn ln<T: Float>(val: T) -> T {
+    libm_call(val, libm::logf, libm::log)
+}
+
+/// The natural log of `1 + val`
+#[cfg(not(feature = "reproducible"))]
+#[inline]
+pub(crate) fn ln_1p<T: Float>(val: T) -> T {
+    val.ln_1p()
+}
+
+/// The natural log of `1 + val`
+#[cfg(feature = "reproducible")]
+pub(crate) fn ln_1p<T: Float>(val: T) -> T {
+    libm_call(val, libm::log1pf, libm::log1p)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{exp, exp_m1, ln, ln_1p};
+
+    #[test]
+    fn test_math() {
+        for val in [-30.0, -1.0, -1e-10, 0.0, 1e-10, 0.5, 1.0, 30.0] {
+            assert_close!(exp(val), f64::exp(val));
+            assert_close!(exp_m1(val), f64::exp_m1(val));
+        }
+        for val in [-30_f32, -1.0, 0.0, 0.5, 30.0] {
+            assert_close!(exp(val), f32::exp(val));
+            assert_close!(ln_1p(val.abs()), f32::ln_1p(val.abs()));
+        }
+        for val in [1e-300, 1e-10, 0.5, 1.0, 2.0, 1e300] {
+            assert_close!(ln(val), f64::ln(val));
+            assert_close!(ln_1p(val), f64::ln_1p(val));
+        }
+        assert_eq!(exp(f64::NEG_INFINITY), 0.0);
+        assert_eq!(ln(0_f32), f32::NEG_INFINITY);
+        assert!(ln_1p(f64::NAN).is_nan());
+    }
+}
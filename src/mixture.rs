@@ -0,0 +1,90 @@
+//! Log densities of mixture models
+use super::ln_sum_exp_slice;
+use num_traits::Float;
+
+/// Compute the log density of a mixture at many points
+///
+/// `component_ln_pdfs` is a row-major matrix with a row for every point and a column for every
+/// component, so `component_ln_pdfs[i * k + j]` is the log density of component `j` at point `i`
+/// when there are `k = log_weights.len()` components. The result has `ln Σ_j w_j f_j(x_i)` for
+/// every point, computed as a weighted [`ln_sum_exp_slice`] of each row. Rows are combined with
+/// the weights in a reused buffer, so the only allocation is the result.
+///
+/// The weights don't need to be normalized, in which case the result is scaled by their sum.
+///
+/// # Panics
+///
+/// If the length of `component_ln_pdfs` isn't a multiple of the number of components, or if there
+/// are no components but there are densities.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::mixture_ln_pdf;
+///
+/// let log_weights = [0.25_f64.ln(), 0.75_f64.ln()];
+/// let ln_pdfs = [0.0, 0.0, 1.0, f64::NEG_INFINITY];
+/// mixture_ln_pdf(&log_weights, &ln_pdfs); // [0.0, 0.25_f64.ln() + 1.0]
+/// ```
+#[must_use]
+pub fn mixture_ln_pdf<T>(log_weights: &[T], component_ln_pdfs: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    let comps = log_weights.len();
+    if comps == 0 {
+        assert!(
+            component_ln_pdfs.is_empty(),
+            "densities must have a column for every component"
+        );
+        return Vec::new();
+    }
+    assert_eq!(
+        component_ln_pdfs.len() % comps,
+        0,
+        "densities must have a column for every component"
+    );
+    let mut joint = Vec::with_capacity(comps);
+    component_ln_pdfs
+        .chunks_exact(comps)
+        .map(|row| {
+            joint.clear();
+            joint.extend(log_weights.iter().zip(row).map(|(&lw, &lp)| lw + lp));
+            ln_sum_exp_slice(&joint)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::mixture_ln_pdf;
+
+    #[test]
+    fn test_mixture_ln_pdf() {
+        let weights: [f64; 3] = [0.2, 0.5, 0.3];
+        let log_weights: Vec<f64> = weights.iter().map(|w| w.ln()).collect();
+        let ln_pdfs: Vec<f64> = (0..30).map(|n| f64::from(n).sin() * 20.0).collect();
+        let res = mixture_ln_pdf(&log_weights, &ln_pdfs);
+        assert_eq!(res.len(), 10);
+        for (row, res) in ln_pdfs.chunks(3).zip(res) {
+            let expected: f64 = row.iter().zip(&weights).map(|(lp, w)| w * lp.exp()).sum();
+            assert_close!(res, expected.ln());
+        }
+
+        // far in the tails, where every density underflows
+        let res = mixture_ln_pdf(&log_weights, &[-1000.0, -1000.0, -1000.0]);
+        assert_close!(res[0], -1000.0);
+        let res = mixture_ln_pdf(&log_weights, &[f64::NEG_INFINITY; 3]);
+        assert_eq!(res, [f64::NEG_INFINITY]);
+
+        assert!(mixture_ln_pdf(&log_weights, &[]).is_empty());
+        assert!(mixture_ln_pdf::<f64>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "column for every component")]
+    fn test_mixture_ln_pdf_shape() {
+        let _ = mixture_ln_pdf(&[0.0, 0.0], &[1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,406 @@
+//! Means of exponentials computed in log space
+use super::math::{cast, exp, ln};
+use super::{LogSubExp, StreamingLnSumExp};
+use num_traits::Float;
+
+/// Compute the log of the harmonic mean of the exponentials of the values
+///
+/// This is `ln(n / Σ exp(-xᵢ))`, computed as `ln(n)` minus the `ln_sum_exp` of the negated values
+/// in a single pass, so it doesn't overflow or underflow for any finite inputs. A value of
+/// negative infinity makes the result negative infinity, and values of positive infinity don't
+/// contribute to the sum. An empty iterator returns `None`.
+///
+/// Given log likelihoods of posterior samples this is the harmonic mean estimator of the log
+/// marginal likelihood. The computation is stable, but the estimator isn't: it's dominated by the
+/// samples with the smallest likelihood, which are rarely drawn from the posterior, so its
+/// variance is often infinite and it converges extremely slowly. Treat the result with suspicion
+/// unless the values are known to be well behaved.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_harmonic_mean_exp;
+/// ln_harmonic_mean_exp([1_f64.ln(), 4_f64.ln()]); // Some(1.6_f64.ln())
+/// ```
+pub fn ln_harmonic_mean_exp<I, T>(values: I) -> Option<T>
+where
+    I: IntoIterator<Item = T>,
+    T: Float,
+{
+    let mut acc = StreamingLnSumExp::new();
+    let mut count = 0_usize;
+    for value in values {
+        acc.push(-value);
+        count += 1;
+    }
+    if count == 0 {
+        None
+    } else {
+        Some(ln(cast::<T>(count)) - acc.ln_sum_exp())
+    }
+}
+
+/// Compute the log of the weighted mean of the exponentials of the values
+///
+/// This takes `(value, weight)` pairs and returns `ln(Σ wᵢ exp(xᵢ) / Σ wᵢ)` in a single pass,
+/// shifting both sums by their maximum like [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp]. With
+/// log likelihoods as values and importance weights as weights this is the importance sampling
+/// estimate of a log expectation. Weights must be nonnegative, and a negative weight produces
+/// NaN. If the total weight is zero, including when the iterator is empty, this returns `None`.
+///
+/// Use [`ln_mean_exp_log_weighted`] if the weights are already in log space.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_mean_exp_weighted;
+/// ln_mean_exp_weighted([(1_f64.ln(), 3.0), (5_f64.ln(), 1.0)]); // Some(2_f64.ln())
+/// ```
+pub fn ln_mean_exp_weighted<I, T>(pairs: I) -> Option<T>
+where
+    I: IntoIterator<Item = (T, T)>,
+    T: Float,
+{
+    ln_mean_exp_log_weighted(pairs.into_iter().map(|(value, weight)| (value, ln(weight))))
+}
+
+/// Compute the log of the weighted mean of the exponentials of the values with log weights
+///
+/// This takes `(value, log_weight)` pairs and returns `ln(Σ exp(xᵢ + lᵢ) / Σ exp(lᵢ))`, which is
+/// the same as [`ln_mean_exp_weighted`] but doesn't overflow or underflow when the weights are
+/// extreme, e.g. unnormalized importance weights. If every log weight is negative infinity,
+/// including when the iterator is empty, this returns `None`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_mean_exp_log_weighted;
+/// ln_mean_exp_log_weighted([(1_f64.ln(), 1000.0 + 3_f64.ln()), (5_f64.ln(), 1000.0)]);
+/// // Some(2_f64.ln())
+/// ```
+pub fn ln_mean_exp_log_weighted<I, T>(pairs: I) -> Option<T>
+where
+    I: IntoIterator<Item = (T, T)>,
+    T: Float,
+{
+    let mut weighted = StreamingLnSumExp::new();
+    let mut total = StreamingLnSumExp::new();
+    for (value, log_weight) in pairs {
+        weighted.push(value + log_weight);
+        total.push(log_weight);
+    }
+    let ln_total = total.ln_sum_exp();
+    if ln_total == T::neg_infinity() {
+        None
+    } else {
+        Some(weighted.ln_sum_exp() - ln_total)
+    }
+}
+
+/// Compute the log of the variance of the exponentials of the values
+///
+/// This is the log of the population variance `Σ (exp(xᵢ) - μ)² / n`, where `μ` is the mean of
+/// the exponentials. It's computed in a single pass from the identity `Var = E[y²] - E[y]²`, where
+/// `ln(E[y²])` is the `ln_sum_exp` of `2xᵢ` and `ln(E[y]²)` is twice the `ln_sum_exp` of `xᵢ`,
+/// both minus the appropriate multiple of `ln(n)`. The difference is taken with
+/// [`ln_sub_exp`][LogSubExp::ln_sub_exp], so it doesn't overflow or underflow, and rounding that
+/// would make the variance negative produces negative infinity instead of NaN. The difference
+/// still cancels, so a variance many orders of magnitude smaller than the squared mean only has
+/// an absolute accuracy relative to the squared mean. An empty iterator returns `None`.
+///
+/// With log importance weights as values, this is the variance of the weights, which is a common
+/// diagnostic for a poor proposal distribution. Use [`StreamingLnMoments`] to track it as values
+/// arrive.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_var_exp;
+/// ln_var_exp([1_f64.ln(), 3_f64.ln()]); // Some(0_f64)
+/// ```
+pub fn ln_var_exp<I, T>(values: I) -> Option<T>
+where
+    I: IntoIterator<Item = T>,
+    T: Float,
+{
+    let mut acc = StreamingLnMoments::new();
+    for value in values {
+        acc.push(value);
+    }
+    acc.ln_var_exp()
+}
+
+/// An accumulator for the moments of the exponentials of values pushed one at a time
+///
+/// This keeps the `ln_sum_exp` of the values, the `ln_sum_exp` of twice the values, i.e. the log
+/// of the sum of the squared exponentials, and the count. That's enough to report the log mean,
+/// the log variance, and the effective sample size at any point without storing the values, so
+/// it's suited to monitoring a long-running importance sampler where the values are log weights.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::StreamingLnMoments;
+///
+/// let mut acc = StreamingLnMoments::new();
+/// acc.push(1_f64.ln());
+/// acc.push(3_f64.ln());
+/// acc.ln_mean_exp(); // Some(2_f64.ln())
+/// acc.ln_var_exp(); // Some(0_f64)
+/// acc.ess(); // 1.6
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingLnMoments<T> {
+    sum: StreamingLnSumExp<T>,
+    sum_sq: StreamingLnSumExp<T>,
+    count: usize,
+}
+
+impl<T> StreamingLnMoments<T>
+where
+    T: Float,
+{
+    /// Create a new empty accumulator
+    #[must_use]
+    pub fn new() -> Self {
+        StreamingLnMoments {
+            sum: StreamingLnSumExp::new(),
+            sum_sq: StreamingLnSumExp::new(),
+            count: 0,
+        }
+    }
+
+    /// Add a value to the accumulator
+    pub fn push(&mut self, value: T) {
+        self.sum.push(value);
+        self.sum_sq.push(value + value);
+        self.count += 1;
+    }
+
+    /// Add every value from another accumulator
+    pub fn merge(&mut self, other: &Self) {
+        self.sum.merge(&other.sum);
+        self.sum_sq.merge(&other.sum_sq);
+        self.count += other.count;
+    }
+
+    /// The number of values pushed
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The `ln_sum_exp` of the values
+    ///
+    /// An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        self.sum.ln_sum_exp()
+    }
+
+    /// The log of the count as `T`
+    fn ln_count(&self) -> T {
+        ln(cast::<T>(self.count))
+    }
+
+    /// The log of the mean of the exponentials of the values
+    ///
+    /// An empty accumulator returns `None`.
+    #[must_use]
+    pub fn ln_mean_exp(&self) -> Option<T> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.sum.ln_sum_exp() - self.ln_count())
+        }
+    }
+
+    /// The log of the population variance of the exponentials of the values
+    ///
+    /// This has the same accuracy as [`ln_var_exp`]. An empty accumulator returns `None`.
+    #[must_use]
+    pub fn ln_var_exp(&self) -> Option<T> {
+        let ln_mean = self.ln_mean_exp()?;
+        let ln_mean_sq = self.sum_sq.ln_sum_exp() - self.ln_count();
+        let ln_sq_mean = ln_mean + ln_mean;
+        let res = ln_mean_sq.ln_sub_exp(ln_sq_mean);
+        if res.is_nan() && ln_sq_mean > ln_mean_sq {
+            Some(T::neg_infinity())
+        } else {
+            Some(res)
+        }
+    }
+
+    /// Kish's effective sample size of the exponentials of the values as weights
+    ///
+    /// This is `(Σ wᵢ)² / Σ wᵢ²` with `wᵢ = exp(xᵢ)`, computed in log space, so it's between one
+    /// and the count for any values that aren't all negative infinity, and the scale of the
+    /// weights doesn't matter. An accumulator without any positive weights returns zero.
+    #[must_use]
+    pub fn ess(&self) -> T {
+        let ln_sum = self.sum.ln_sum_exp();
+        let ln_sum_sq = self.sum_sq.ln_sum_exp();
+        if ln_sum_sq == T::neg_infinity() {
+            T::zero()
+        } else {
+            exp(ln_sum + ln_sum - ln_sum_sq)
+        }
+    }
+}
+
+impl<T> Default for StreamingLnMoments<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        ln_harmonic_mean_exp, ln_mean_exp_log_weighted, ln_mean_exp_weighted, ln_var_exp,
+        StreamingLnMoments,
+    };
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_harmonic_mean_exp() {
+        let res = ln_harmonic_mean_exp([1_f64.ln(), 4_f64.ln()]).unwrap();
+        assert_close!(res, 1.6_f64.ln());
+
+        let res = ln_harmonic_mean_exp([3.5_f64; 5]).unwrap();
+        assert_close!(res, 3.5);
+
+        // far outside the range of exp
+        let res = ln_harmonic_mean_exp([-1000.0, -1000.0 + 4_f64.ln()]).unwrap();
+        assert_close!(res, -1000.0 + 1.6_f64.ln());
+        let res = ln_harmonic_mean_exp([1000_f32, 1000.0]).unwrap();
+        assert_close!(res, 1000.0);
+    }
+
+    #[test]
+    fn test_ln_harmonic_mean_exp_special() {
+        assert_eq!(ln_harmonic_mean_exp::<_, f64>([]), None);
+        assert_eq!(
+            ln_harmonic_mean_exp([1.0, f64::NEG_INFINITY]),
+            Some(f64::NEG_INFINITY)
+        );
+        let res = ln_harmonic_mean_exp([0.0, f64::INFINITY]).unwrap();
+        assert_close!(res, 2_f64.ln());
+        assert_eq!(ln_harmonic_mean_exp([f64::INFINITY]), Some(f64::INFINITY));
+        assert!(ln_harmonic_mean_exp([1.0, f64::NAN]).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_ln_mean_exp_weighted() {
+        let res = ln_mean_exp_weighted([(1_f64.ln(), 3.0), (5_f64.ln(), 1.0)]).unwrap();
+        assert_close!(res, 2_f64.ln());
+
+        let res = ln_mean_exp_weighted([(1000_f64, 0.5), (1000.0, 2.0), (-5.0, 0.0)]).unwrap();
+        assert_close!(res, 1000.0);
+
+        assert_eq!(ln_mean_exp_weighted::<_, f64>([]), None);
+        assert_eq!(ln_mean_exp_weighted([(1.0, 0.0), (2.0, 0.0)]), None);
+        assert!(ln_mean_exp_weighted([(1_f64, 1.0), (2.0, -1.0)])
+            .unwrap()
+            .is_nan());
+    }
+
+    #[test]
+    fn test_ln_mean_exp_log_weighted() {
+        let pairs = [(1_f64.ln(), 1000.0 + 3_f64.ln()), (5_f64.ln(), 1000.0)];
+        let res = ln_mean_exp_log_weighted(pairs).unwrap();
+        assert_close!(res, 2_f64.ln());
+
+        let pairs = [(-800_f32, -800.0), (-800.0, -800.0)];
+        let res = ln_mean_exp_log_weighted(pairs).unwrap();
+        assert_close!(res, -800.0);
+
+        let pairs = [(2.0, f64::NEG_INFINITY), (1.0, 0.0)];
+        assert_eq!(ln_mean_exp_log_weighted(pairs), Some(1.0));
+        assert_eq!(ln_mean_exp_log_weighted([(1.0, f64::NEG_INFINITY)]), None);
+        let pairs = [(f64::NEG_INFINITY, 0.0), (f64::NEG_INFINITY, 1.0)];
+        assert_eq!(ln_mean_exp_log_weighted(pairs), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_ln_var_exp() {
+        let res = ln_var_exp([1_f64.ln(), 3_f64.ln()]).unwrap();
+        assert_close!(res, 0.0);
+
+        let vals = [0.5_f64, 1.5, 2.0, 4.0];
+        let mean = vals.iter().sum::<f64>() / 4.0;
+        let var = vals.iter().map(|val| (val - mean).powi(2)).sum::<f64>() / 4.0;
+        let res = ln_var_exp(vals.iter().map(|val| val.ln())).unwrap();
+        assert_close!(res, var.ln());
+
+        // the exponentials overflow
+        let res = ln_var_exp(vals.iter().map(|val| val.ln() + 1000.0)).unwrap();
+        assert_close!(res, var.ln() + 2000.0);
+        let res = ln_var_exp([0.5_f32, 1.5, 2.0, 4.0].map(|val| val.ln() - 100.0)).unwrap();
+        assert_close!(f64::from(res), var.ln() - 200.0, rtol = 1e-5);
+    }
+
+    #[test]
+    fn test_ln_var_exp_special() {
+        assert_eq!(ln_var_exp::<_, f64>([]), None);
+        assert_eq!(ln_var_exp([0.7_f64]), Some(f64::NEG_INFINITY));
+        // equal values only leave rounding error relative to the squared mean
+        for val in [-3.0, 0.1, 0.3, 12.0] {
+            let res = ln_var_exp([val; 7]).unwrap();
+            assert!(res < 2.0 * val + f64::EPSILON.ln() + 3.0);
+        }
+        let res = ln_var_exp([0.0, f64::NEG_INFINITY]).unwrap();
+        assert_close!(res, 0.25_f64.ln());
+        assert!(ln_var_exp([1.0, f64::INFINITY]).unwrap().is_nan());
+        assert!(ln_var_exp([1.0, f64::NAN]).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_streaming_ln_moments() {
+        let vals: Vec<f64> = (0..100).map(|n| f64::from(n).sin() * 20.0).collect();
+        let mut acc = StreamingLnMoments::new();
+        let mut left = StreamingLnMoments::new();
+        let mut right = StreamingLnMoments::new();
+        for (i, &val) in vals.iter().enumerate() {
+            acc.push(val);
+            if i < 30 {
+                left.push(val);
+            } else {
+                right.push(val);
+            }
+        }
+        left.merge(&right);
+        for acc in [acc, left] {
+            assert_eq!(acc.count(), 100);
+            assert_close!(acc.ln_sum_exp(), vals.iter().copied().ln_sum_exp());
+            let ln_mean = vals.iter().copied().ln_sum_exp() - 100_f64.ln();
+            assert_close!(acc.ln_mean_exp().unwrap(), ln_mean);
+            assert_close!(acc.ln_var_exp().unwrap(), ln_var_exp(vals.clone()).unwrap());
+
+            let weights: Vec<f64> = vals.iter().map(|val| (val - 20.0).exp()).collect();
+            let sum: f64 = weights.iter().sum();
+            let sum_sq: f64 = weights.iter().map(|w| w * w).sum();
+            assert_close!(acc.ess(), sum * sum / sum_sq);
+        }
+    }
+
+    #[test]
+    fn test_streaming_ln_moments_ess() {
+        let mut acc = StreamingLnMoments::new();
+        assert_eq!(acc.ess(), 0.0);
+        assert_eq!(acc.ln_mean_exp(), None);
+        assert_eq!(acc.ln_var_exp(), None);
+        acc.push(f64::NEG_INFINITY);
+        assert_eq!(acc.ess(), 0.0);
+        for _ in 0..10 {
+            acc.push(-1000.0);
+        }
+        assert_close!(acc.ess(), 10.0);
+        acc.push(1000.0);
+        assert_close!(acc.ess(), 1.0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Reductions over nested vectors
+use super::{ln_sum_exp_slice, StreamingLnSumExp};
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of every inner vector
+///
+/// Each row is reduced with [`ln_sum_exp_slice`], so an empty row is negative infinity, the log
+/// of an empty sum.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_nested_rows;
+///
+/// let rows = vec![vec![0.0, 0.0], vec![], vec![1.0]];
+/// ln_sum_exp_nested_rows(&rows); // [2_f64.ln(), f64::NEG_INFINITY, 1.0]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_nested_rows<T, R>(rows: &[R]) -> Vec<T>
+where
+    T: Float,
+    R: AsRef<[T]>,
+{
+    rows.iter()
+        .map(|row| ln_sum_exp_slice(row.as_ref()))
+        .collect()
+}
+
+/// Compute `ln_sum_exp` of every column of nested vectors
+///
+/// Rows don't need to have the same length: column `i` reduces the `i`th value of every row long
+/// enough to have one, so rows shorter than others, including empty rows, only contribute to the
+/// columns they have. The result has as many values as the longest row, and every value is the
+/// reduction of at least one element.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_nested_cols;
+///
+/// let rows = vec![vec![0.0, 1.0], vec![], vec![0.0]];
+/// ln_sum_exp_nested_cols(&rows); // [2_f64.ln(), 1.0]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_nested_cols<T, R>(rows: &[R]) -> Vec<T>
+where
+    T: Float,
+    R: AsRef<[T]>,
+{
+    let mut cols: Vec<StreamingLnSumExp<T>> = Vec::new();
+    for row in rows {
+        let row = row.as_ref();
+        if row.len() > cols.len() {
+            cols.resize_with(row.len(), StreamingLnSumExp::new);
+        }
+        for (col, &val) in cols.iter_mut().zip(row) {
+            col.push(val);
+        }
+    }
+    cols.iter().map(StreamingLnSumExp::ln_sum_exp).collect()
+}
+
+/// Compute `ln_sum_exp` of every value of nested vectors
+///
+/// Empty rows contribute nothing, and if there are no values at all the result is negative
+/// infinity.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_nested;
+///
+/// let rows = vec![vec![0.0, 0.0], vec![], vec![0.0]];
+/// ln_sum_exp_nested(&rows); // 3_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_sum_exp_nested<T, R>(rows: &[R]) -> T
+where
+    T: Float,
+    R: AsRef<[T]>,
+{
+    let acc: StreamingLnSumExp<T> = rows.iter().flat_map(AsRef::as_ref).copied().collect();
+    acc.ln_sum_exp()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_nested, ln_sum_exp_nested_cols, ln_sum_exp_nested_rows};
+    use crate::ln_sum_exp_slice;
+
+    #[test]
+    fn test_ln_sum_exp_nested() {
+        let rows: Vec<Vec<f64>> = vec![vec![1.0, 2.0, 3.0], vec![], vec![1000.0], vec![4.0, 5.0]];
+        let res = ln_sum_exp_nested_rows(&rows);
+        assert_close!(res[0], ln_sum_exp_slice(&[1.0, 2.0, 3.0]));
+        assert_eq!(res[1], f64::NEG_INFINITY);
+        assert_eq!(res[2], 1000.0);
+        assert_close!(res[3], ln_sum_exp_slice(&[4.0, 5.0]));
+
+        let res = ln_sum_exp_nested_cols(&rows);
+        assert_eq!(res.len(), 3);
+        assert_close!(res[0], ln_sum_exp_slice(&[1.0, 1000.0, 4.0]));
+        assert_close!(res[1], ln_sum_exp_slice(&[2.0, 5.0]));
+        assert_eq!(res[2], 3.0);
+
+        let all: Vec<f64> = rows.concat();
+        assert_close!(ln_sum_exp_nested(&rows), ln_sum_exp_slice(&all));
+    }
+
+    #[test]
+    fn test_ln_sum_exp_nested_empty() {
+        let rows: Vec<Vec<f32>> = vec![vec![], vec![]];
+        assert_eq!(ln_sum_exp_nested_rows(&rows), [f32::NEG_INFINITY; 2]);
+        assert!(ln_sum_exp_nested_cols(&rows).is_empty());
+        assert_eq!(ln_sum_exp_nested(&rows), f32::NEG_INFINITY);
+        assert_eq!(ln_sum_exp_nested::<f32, Vec<f32>>(&[]), f32::NEG_INFINITY);
+
+        // slices of arrays work too
+        assert_close!(ln_sum_exp_nested(&[[0_f64; 2]; 2]), 4_f64.ln());
+    }
+}
@@ -0,0 +1,110 @@
+//! Operator sugar for arithmetic on log values
+use super::LogAddExp;
+use num_traits::Float;
+use std::ops::{Add, AddAssign, Mul, MulAssign};
+
+/// A log value where `+` adds and `*` multiplies the exponentials
+///
+/// `Ln(a) + Ln(b)` is `Ln(a.ln_add_exp(b))` and `Ln(a) * Ln(b)` is `Ln(a + b)`, so recurrences
+/// written for probabilities, like the forward algorithm, can be written the same way for log
+/// probabilities. It's only sugar: the value is public and nothing else is overloaded.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::Ln;
+///
+/// let (a, b, c) = (Ln(0.5_f64.ln()), Ln(0.25_f64.ln()), Ln(0.5_f64.ln()));
+/// let Ln(res) = a * c + b * c;
+/// // res == 0.375_f64.ln()
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Ln<T>(pub T);
+
+impl<T> Add for Ln<T>
+where
+    T: Float,
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Ln(self.0.ln_add_exp(other.0))
+    }
+}
+
+impl<T> AddAssign for Ln<T>
+where
+    T: Float,
+{
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl<T> Mul for Ln<T>
+where
+    T: Float,
+{
+    type Output = Self;
+
+    // multiplying exponentials adds their logs
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn mul(self, other: Self) -> Self {
+        Ln(self.0 + other.0)
+    }
+}
+
+impl<T> MulAssign for Ln<T>
+where
+    T: Float,
+{
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::Ln;
+
+    #[test]
+    fn test_ops() {
+        let Ln(res) = Ln(1_f64.ln()) + Ln(2_f64.ln());
+        assert_close!(res, 3_f64.ln());
+        let Ln(res) = Ln(3_f64.ln()) * Ln(4_f64.ln());
+        assert_close!(res, 12_f64.ln());
+        let Ln(res) = Ln(-1000_f32) + Ln(-1000.0);
+        assert_close!(res, -1000.0 + 2_f32.ln());
+
+        let mut acc = Ln(f64::NEG_INFINITY);
+        for n in 1..5 {
+            acc += Ln(f64::from(n).ln());
+            acc *= Ln(2_f64.ln());
+        }
+        // ((((1 * 2 + 2) * 2 + 3) * 2) + 4) * 2
+        assert_close!(acc.0, 52_f64.ln());
+
+        assert_eq!(Ln(f64::NEG_INFINITY) * Ln(1.0), Ln(f64::NEG_INFINITY));
+        assert_eq!(Ln(f64::NEG_INFINITY) + Ln(1.0), Ln(1.0));
+    }
+
+    #[test]
+    fn test_forward() {
+        // two state hidden Markov model
+        let ln = |val: f64| Ln(val.ln());
+        let trans = [[ln(0.7), ln(0.3)], [ln(0.4), ln(0.6)]];
+        let emit = [[ln(0.9), ln(0.1)], [ln(0.2), ln(0.8)]];
+        let obs = [0, 1, 1];
+        let mut alpha = [ln(0.5) * emit[0][obs[0]], ln(0.5) * emit[1][obs[0]]];
+        let mut prob = [0.5 * 0.9, 0.5 * 0.2];
+        for &o in &obs[1..] {
+            alpha = [0, 1].map(|j| (alpha[0] * trans[0][j] + alpha[1] * trans[1][j]) * emit[j][o]);
+            prob = [0, 1].map(|j| {
+                (prob[0] * trans[0][j].0.exp() + prob[1] * trans[1][j].0.exp()) * emit[j][o].0.exp()
+            });
+        }
+        let Ln(res) = alpha[0] + alpha[1];
+        assert_close!(res, (prob[0] + prob[1]).ln());
+    }
+}
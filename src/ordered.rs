@@ -0,0 +1,123 @@
+//! Log-domain operations on `ordered_float` wrappers
+//!
+//! [`OrderedFloat`][ordered_float::OrderedFloat] implements [`Float`], so it already works with
+//! every trait in this crate. [`NotNan`] doesn't, and can't share the `Float` implementations,
+//! so it gets its own trait that preserves the wrapper.
+use super::{LogAddExp, LogSubExp, StreamingLnSumExp};
+use num_traits::float::FloatCore;
+use num_traits::Float;
+use ordered_float::{FloatIsNan, NotNan};
+
+/// Log-domain operations that keep values wrapped in [`NotNan`]
+///
+/// `ln_add_exp` of two non-NaN values is never NaN, so it returns a `NotNan` directly, but
+/// `ln_sub_exp` is NaN when the difference is negative, so it returns an error instead.
+pub trait NotNanLogExp: Sized {
+    /// Compute [`ln_add_exp`][LogAddExp::ln_add_exp] without unwrapping
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::NotNanLogExp;
+    /// use ordered_float::NotNan;
+    ///
+    /// let zero = NotNan::new(0.0).unwrap();
+    /// zero.ln_add_exp(zero); // 2_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_add_exp(self, other: Self) -> Self;
+
+    /// Compute [`ln_sub_exp`][LogSubExp::ln_sub_exp] without unwrapping
+    ///
+    /// # Errors
+    ///
+    /// If `other` is greater than `self`, or both are positive infinity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::NotNanLogExp;
+    /// use ordered_float::NotNan;
+    ///
+    /// let zero = NotNan::new(0.0).unwrap();
+    /// let one = NotNan::new(1.0).unwrap();
+    /// assert!(one.ln_sub_exp(zero).is_ok());
+    /// assert!(zero.ln_sub_exp(one).is_err());
+    /// ```
+    fn ln_sub_exp(self, other: Self) -> Result<Self, FloatIsNan>;
+}
+
+impl<T> NotNanLogExp for NotNan<T>
+where
+    T: Float + FloatCore,
+{
+    fn ln_add_exp(self, other: Self) -> Self {
+        let res = LogAddExp::ln_add_exp(self.into_inner(), other.into_inner());
+        NotNan::new(res).expect("ln_add_exp of non-NaN values isn't NaN")
+    }
+
+    fn ln_sub_exp(self, other: Self) -> Result<Self, FloatIsNan> {
+        NotNan::new(LogSubExp::ln_sub_exp(self.into_inner(), other.into_inner()))
+    }
+}
+
+/// Compute `ln_sum_exp` of [`NotNan`] values
+///
+/// Like `ln_add_exp`, this is never NaN, so the result stays wrapped.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_not_nan;
+/// use ordered_float::NotNan;
+///
+/// let vals = [1.0, 2.0, 4.0].map(|val| NotNan::new(val).unwrap());
+/// ln_sum_exp_not_nan(vals);
+/// ```
+// the expect is unreachable
+#[allow(clippy::missing_panics_doc)]
+pub fn ln_sum_exp_not_nan<I, T>(vals: I) -> NotNan<T>
+where
+    I: IntoIterator<Item = NotNan<T>>,
+    T: Float + FloatCore,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for val in vals {
+        acc.push(val.into_inner());
+    }
+    NotNan::new(acc.ln_sum_exp()).expect("ln_sum_exp of non-NaN values isn't NaN")
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_not_nan, NotNanLogExp};
+    use crate::{LogAddExp, LogSumExp};
+    use ordered_float::{NotNan, OrderedFloat};
+
+    #[test]
+    fn test_not_nan() {
+        let [zero, one, inf, neg_inf] =
+            [0.0, 1.0, f64::INFINITY, f64::NEG_INFINITY].map(|val| NotNan::new(val).unwrap());
+        assert_eq!(*zero.ln_add_exp(one), 0_f64.ln_add_exp(1.0));
+        assert_eq!(*inf.ln_add_exp(neg_inf), f64::INFINITY);
+        assert_close!(*one.ln_sub_exp(zero).unwrap(), 1_f64.exp_m1().ln());
+        assert!(zero.ln_sub_exp(one).is_err());
+        assert!(inf.ln_sub_exp(inf).is_err());
+
+        assert_close!(
+            *ln_sum_exp_not_nan([zero, one, zero]),
+            [0.0, 1.0, 0.0].into_iter().ln_sum_exp()
+        );
+        assert_eq!(*ln_sum_exp_not_nan([inf, neg_inf]), f64::INFINITY);
+        assert_eq!(*ln_sum_exp_not_nan::<_, f64>([]), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_ordered_float() {
+        let res = OrderedFloat(0.0).ln_add_exp(OrderedFloat(1.0));
+        assert_eq!(res, OrderedFloat(0_f64.ln_add_exp(1.0)));
+        let res = [1.0, 2.0].map(OrderedFloat).into_iter().ln_sum_exp();
+        assert_eq!(res, OrderedFloat(1_f64.ln_add_exp(2.0)));
+    }
+}
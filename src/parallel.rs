@@ -0,0 +1,230 @@
+//! Parallel `ln_sum_exp` using only the standard library
+//!
+//! Partial results are always merged in the order of the chunks they came from, never in the
+//! order that threads finish, so thread scheduling doesn't change the result.
+use super::ln_sum_exp_slice;
+use super::math::{exp, ln};
+use super::slice::{slice_max, slice_sum_exp};
+use num_traits::Float;
+use std::panic;
+use std::thread;
+
+/// The number of values in each block of [`ln_sum_exp_parallel_deterministic`]
+const BLOCK_LEN: usize = 4096;
+
+/// The max of a chunk and the sum of its exponentials shifted by the max
+///
+/// If the max isn't finite the sum is only used to propagate NaN.
+fn partial<T: Float>(chunk: &[T]) -> (T, T) {
+    let max = slice_max(chunk);
+    let sum = if max.is_finite() {
+        slice_sum_exp(chunk, max)
+    } else if chunk.iter().any(|val| val.is_nan()) {
+        T::nan()
+    } else {
+        T::zero()
+    };
+    (max, sum)
+}
+
+/// Merge two partial states, keeping NaN in the sum when the max isn't finite
+fn merge<T: Float>((left, left_sum): (T, T), (right, right_sum): (T, T)) -> (T, T) {
+    let max = left.max(right);
+    if max.is_finite() {
+        let sum = left_sum * exp(left - max) + right_sum * exp(right - max);
+        (max, sum)
+    } else if max == T::neg_infinity() {
+        (max, left_sum + right_sum)
+    } else {
+        (max, T::zero())
+    }
+}
+
+/// Merge partial states as a balanced binary tree whose shape only depends on their number
+fn tree_merge<T: Float>(partials: &[(T, T)]) -> (T, T) {
+    match partials {
+        [] => (T::neg_infinity(), T::zero()),
+        [single] => *single,
+        _ => {
+            let mid = partials.len().next_power_of_two() / 2;
+            let (left, right) = partials.split_at(mid);
+            merge(tree_merge(left), tree_merge(right))
+        }
+    }
+}
+
+/// Run `f` on `num_threads` contiguous chunks of `items` and collect the results in order
+fn scoped_map<I, R, F>(items: &[I], num_threads: usize, f: F) -> Vec<R>
+where
+    I: Sync,
+    R: Send,
+    F: Fn(&[I]) -> R + Sync,
+{
+    let chunk_len = items.len().div_ceil(num_threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_len)
+            .map(|chunk| scope.spawn(|| f(chunk)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    })
+}
+
+/// Compute `ln_sum_exp` of a slice by splitting it across scoped threads
+///
+/// The slice is split into `num_threads` contiguous chunks, each reduced to its maximum and
+/// shifted sum on its own thread, and the partial states are merged on the calling thread. The
+/// result matches [`ln_sum_exp_slice`] up to rounding. This doesn't need a thread pool or any
+/// dependencies, but spawning threads isn't free, so it's only faster for large slices.
+///
+/// The result is the same every time for the same `num_threads`, but the rounding changes with
+/// it. Use [`ln_sum_exp_parallel_deterministic`] for a result that's independent of the number
+/// of threads.
+///
+/// A `num_threads` of zero or one computes on the current thread.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_parallel;
+///
+/// let vals: Vec<f64> = (0..100_000).map(|n| f64::from(n).sin()).collect();
+/// ln_sum_exp_parallel(&vals, 4);
+/// ```
+#[must_use]
+pub fn ln_sum_exp_parallel<T>(values: &[T], num_threads: usize) -> T
+where
+    T: Float + Send + Sync,
+{
+    if num_threads <= 1 || values.len() <= num_threads {
+        return ln_sum_exp_slice(values);
+    }
+    let partials = scoped_map(values, num_threads, partial);
+
+    let max = partials
+        .iter()
+        .fold(T::neg_infinity(), |max, &(part, _)| max.max(part));
+    if max.is_finite() {
+        let sum = partials
+            .into_iter()
+            .fold(T::zero(), |sum, (part, part_sum)| {
+                sum + part_sum * exp(part - max)
+            });
+        ln(sum) + max
+    } else if max == T::neg_infinity() && partials.iter().any(|(_, sum)| sum.is_nan()) {
+        T::nan()
+    } else {
+        max
+    }
+}
+
+/// Compute `ln_sum_exp` of a slice in parallel with a result that doesn't depend on the threads
+///
+/// The slice is split into fixed size blocks that don't depend on `num_threads`, and the partial
+/// state of every block is merged in a fixed balanced tree, so the association order of every
+/// addition is determined by the length of the slice alone. The result is bit-identical for any
+/// number of threads, including computing on the current thread when `num_threads` is zero or
+/// one, at the cost of a slightly slower merge than [`ln_sum_exp_parallel`].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_parallel_deterministic;
+///
+/// let vals: Vec<f64> = (0..100_000).map(|n| f64::from(n).sin()).collect();
+/// assert_eq!(
+///     ln_sum_exp_parallel_deterministic(&vals, 4),
+///     ln_sum_exp_parallel_deterministic(&vals, 1),
+/// );
+/// ```
+#[must_use]
+pub fn ln_sum_exp_parallel_deterministic<T>(values: &[T], num_threads: usize) -> T
+where
+    T: Float + Send + Sync,
+{
+    let blocks: Vec<&[T]> = values.chunks(BLOCK_LEN).collect();
+    let partials: Vec<(T, T)> = if num_threads <= 1 || blocks.len() <= 1 {
+        blocks.iter().map(|block| partial(block)).collect()
+    } else {
+        scoped_map(&blocks, num_threads, |blocks| {
+            blocks
+                .iter()
+                .map(|block| partial(block))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+    };
+    let (max, sum) = tree_merge(&partials);
+    if max.is_finite() {
+        ln(sum) + max
+    } else if max == T::neg_infinity() && sum.is_nan() {
+        T::nan()
+    } else {
+        max
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_parallel, ln_sum_exp_parallel_deterministic};
+    use crate::{ln_sum_exp_slice, LogAddExp};
+
+    #[test]
+    fn test_ln_sum_exp_parallel() {
+        let vals: Vec<f64> = (0..1000).map(|n| f64::from(n).sin() * 10.0).collect();
+        let expected = ln_sum_exp_slice(&vals);
+        for threads in [0, 1, 2, 3, 7, 16] {
+            assert_close!(ln_sum_exp_parallel(&vals, threads), expected);
+        }
+        assert_eq!(ln_sum_exp_parallel::<f64>(&[], 4), f64::NEG_INFINITY);
+        assert_eq!(ln_sum_exp_parallel(&[1.0, 2.0], 4), 1_f64.ln_add_exp(2.0));
+    }
+
+    #[test]
+    fn test_ln_sum_exp_parallel_special() {
+        let mut vals = vec![f64::NEG_INFINITY; 100];
+        assert_eq!(ln_sum_exp_parallel(&vals, 4), f64::NEG_INFINITY);
+        vals[10] = f64::NAN;
+        assert!(ln_sum_exp_parallel(&vals, 4).is_nan());
+        vals[90] = 1.0;
+        assert!(ln_sum_exp_parallel(&vals, 4).is_nan());
+        vals[50] = f64::INFINITY;
+        assert_eq!(ln_sum_exp_parallel(&vals, 4), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_parallel_deterministic() {
+        let vals: Vec<f64> = (0..100_000).map(|n| f64::from(n).sin() * 10.0).collect();
+        let expected = ln_sum_exp_parallel_deterministic(&vals, 1);
+        assert_close!(expected, ln_sum_exp_slice(&vals));
+        for threads in [0, 2, 3, 7, 16, 100] {
+            assert_eq!(ln_sum_exp_parallel_deterministic(&vals, threads), expected);
+        }
+        assert_eq!(
+            ln_sum_exp_parallel_deterministic::<f64>(&[], 4),
+            f64::NEG_INFINITY
+        );
+
+        let mut vals = vec![f64::NEG_INFINITY; 10_000];
+        assert_eq!(
+            ln_sum_exp_parallel_deterministic(&vals, 4),
+            f64::NEG_INFINITY
+        );
+        vals[9_000] = f64::NAN;
+        assert!(ln_sum_exp_parallel_deterministic(&vals, 4).is_nan());
+        vals[10] = 1.0;
+        assert!(ln_sum_exp_parallel_deterministic(&vals, 4).is_nan());
+        vals[5_000] = f64::INFINITY;
+        assert_eq!(ln_sum_exp_parallel_deterministic(&vals, 4), f64::INFINITY);
+    }
+}
@@ -0,0 +1,95 @@
+//! Log partition functions across inverse temperatures
+use super::math::{exp, ln};
+use super::LogSumExp;
+use num_traits::Float;
+
+/// Compute `ln Σ exp(β E)` of energies for every inverse temperature in a schedule
+///
+/// This is the log partition function `ln Z(β)` for each `β` in `betas`, with the sign convention
+/// that larger `β E` is more probable; use negated energies for Boltzmann weights `exp(-β E)`.
+/// The largest `β E` is always `β` times the largest or smallest energy, so one pass finds both,
+/// and a second pass streams over the energies once, accumulating every `β` at the same time.
+/// Compared to calling [`ln_sum_exp`][LogSumExp::ln_sum_exp] for each `β`, this reads the
+/// energies twice instead of twice per temperature.
+///
+/// A temperature whose largest `β E` isn't finite, e.g. `β = 0` with infinite energies, falls
+/// back to computing `ln_sum_exp` of `β E` directly.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_partition_schedule;
+///
+/// let energies = [0.0, 1.0, 2.0];
+/// ln_partition_schedule(&energies, &[0.0, 1.0]);
+/// // [3_f64.ln(), (1.0 + 1_f64.exp() + 2_f64.exp()).ln()]
+/// ```
+#[must_use]
+pub fn ln_partition_schedule<T>(energies: &[T], betas: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    let (min, max) = energies
+        .iter()
+        .fold((T::infinity(), T::neg_infinity()), |(min, max), &energy| {
+            (min.min(energy), max.max(energy))
+        });
+    let shifts: Vec<T> = betas
+        .iter()
+        .map(|&beta| {
+            if beta < T::zero() {
+                beta * min
+            } else {
+                beta * max
+            }
+        })
+        .collect();
+    let mut sums = vec![T::zero(); betas.len()];
+    for &energy in energies {
+        for ((sum, &beta), &shift) in sums.iter_mut().zip(betas).zip(&shifts) {
+            *sum = *sum + exp(beta * energy - shift);
+        }
+    }
+    sums.into_iter()
+        .zip(betas)
+        .zip(shifts)
+        .map(|((sum, &beta), shift)| {
+            if shift.is_finite() {
+                ln(sum) + shift
+            } else {
+                energies.iter().map(|&energy| beta * energy).ln_sum_exp()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ln_partition_schedule;
+    use crate::{LogAddExp, LogSumExp};
+
+    #[test]
+    fn test_ln_partition_schedule() {
+        let energies: Vec<f64> = (0..100).map(|n| f64::from(n).sin() * 50.0).collect();
+        let betas = [-2.0, -0.5, 0.0, 0.1, 1.0, 30.0];
+        let res = ln_partition_schedule(&energies, &betas);
+        for (&beta, res) in betas.iter().zip(res) {
+            let expected = energies.iter().map(|&energy| beta * energy).ln_sum_exp();
+            assert_close!(res, expected);
+        }
+        assert_close!(ln_partition_schedule(&energies, &[0.0])[0], 100_f64.ln());
+
+        assert!(ln_partition_schedule(&energies, &[]).is_empty());
+        assert_eq!(
+            ln_partition_schedule(&[], &[1.0, 0.0]),
+            [f64::NEG_INFINITY; 2]
+        );
+
+        let infinite = [f64::NEG_INFINITY, 0.0, 1.0];
+        let res = ln_partition_schedule(&infinite, &[1.0, -1.0]);
+        assert_close!(res[0], 1_f64.ln_add_exp(0.0));
+        assert_eq!(res[1], f64::INFINITY);
+        assert!(ln_partition_schedule(&[f64::NAN, 1.0], &[1.0])[0].is_nan());
+    }
+}
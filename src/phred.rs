@@ -0,0 +1,200 @@
+//! Combining Phred quality scores
+//!
+//! A Phred score is `Q = -10 log10(p)` for an error probability `p`, so it's a scaled log
+//! probability and combining scores is a log-domain problem. These helpers convert scores to natural
+//! log probabilities, combine them with [`LogAddExp`] and [`LogSubExp`], and convert back, so they
+//! stay accurate for both very low and very high qualities. Scores are floats so fractional
+//! results aren't rounded until [`phred_round_capped`].
+use super::math::{cast, ln};
+use super::{LogAddExp, LogSubExp};
+use num_traits::Float;
+use std::f64::consts::LN_10;
+
+/// `ln(10) / 10`, the natural log error probability of one Phred point
+fn ln_per_phred<T: Float>() -> T {
+    cast::<T>(LN_10 / 10.0)
+}
+
+/// Convert a Phred score to the natural log of its error probability
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::phred_to_ln_prob;
+/// phred_to_ln_prob(20_f64); // 0.01_f64.ln()
+/// ```
+#[must_use]
+pub fn phred_to_ln_prob<T>(phred: T) -> T
+where
+    T: Float,
+{
+    -phred * ln_per_phred()
+}
+
+/// Convert the natural log of an error probability to a Phred score
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_prob_to_phred;
+/// ln_prob_to_phred(0.001_f64.ln()); // 30.0
+/// ```
+#[must_use]
+pub fn ln_prob_to_phred<T>(ln_prob: T) -> T
+where
+    T: Float,
+{
+    -ln_prob / ln_per_phred()
+}
+
+/// The Phred score of the probability that every base call is wrong
+///
+/// Errors are assumed independent, so this is the sum of the scores.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::phred_all_wrong;
+/// phred_all_wrong([20.0, 30.0]); // 50.0
+/// ```
+pub fn phred_all_wrong<T, I>(phreds: I) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+{
+    phreds.into_iter().fold(T::zero(), |acc, phred| acc + phred)
+}
+
+/// The Phred score of the probability that at least one base call is wrong
+///
+/// Errors are assumed independent, so this is one minus the probability that every call is right.
+/// For high qualities it's close to the minimum score minus `10 log10(n)`, but it's exact for
+/// any scores. An empty iterator has no wrong calls, so its score is infinite.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::phred_any_wrong;
+/// phred_any_wrong([30.0, 30.0]); // ~26.99
+/// ```
+pub fn phred_any_wrong<T, I>(phreds: I) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+{
+    let zero = T::zero();
+    let ln_all_right = phreds
+        .into_iter()
+        .map(|phred| zero.ln_sub_exp(phred_to_ln_prob(phred)))
+        .fold(zero, |acc, ln_right| acc + ln_right);
+    ln_prob_to_phred(zero.ln_sub_exp(ln_all_right))
+}
+
+/// The Phred score of a consensus base from duplicate reads that all called it
+///
+/// Each read's error is assumed independent and equally likely to be any of the three other
+/// bases, so the posterior error probability is
+/// `3 Π(p/3) / (Π(1 - p) + 3 Π(p/3))`, computed in log space. A single read keeps its score, and
+/// agreeing reads raise it roughly additively. An empty iterator is uninformative, so it returns
+/// the score of a uniformly random base, `-10 log10(3/4)`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::phred_merge_agreeing;
+/// phred_merge_agreeing([20.0, 20.0]); // ~44.8
+/// ```
+pub fn phred_merge_agreeing<T, I>(phreds: I) -> T
+where
+    T: Float,
+    I: IntoIterator<Item = T>,
+{
+    let zero = T::zero();
+    let ln_3 = ln(cast::<T>(3));
+    let (ln_right, ln_wrong) = phreds
+        .into_iter()
+        .fold((zero, ln_3), |(right, wrong), phred| {
+            let ln_prob = phred_to_ln_prob(phred);
+            (right + zero.ln_sub_exp(ln_prob), wrong + ln_prob - ln_3)
+        });
+    ln_prob_to_phred(ln_wrong - ln_right.ln_add_exp(ln_wrong))
+}
+
+/// Round a Phred score to an integer quality, capped at `cap`
+///
+/// Scores are clamped to `0..=cap`, so infinite scores, like those of certain calls, become `cap`.
+/// NaN becomes zero, the least confident quality. FASTQ files commonly cap at 93, or lower for
+/// binned qualities.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::phred_round_capped;
+/// assert_eq!(phred_round_capped(41.6_f64, 40), 40);
+/// assert_eq!(phred_round_capped(26.99_f64, 93), 27);
+/// ```
+// the unwrap is unreachable since the value is clamped
+#[allow(clippy::missing_panics_doc)]
+#[must_use]
+pub fn phred_round_capped<T>(phred: T, cap: u8) -> u8
+where
+    T: Float,
+{
+    if phred.is_nan() {
+        0
+    } else {
+        let cap_val = cast::<T>(cap);
+        phred.round().max(T::zero()).min(cap_val).to_u8().unwrap()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        ln_prob_to_phred, phred_all_wrong, phred_any_wrong, phred_merge_agreeing,
+        phred_round_capped, phred_to_ln_prob,
+    };
+
+    #[test]
+    fn test_conversions() {
+        assert_close!(phred_to_ln_prob(20_f64), 0.01_f64.ln());
+        assert_close!(ln_prob_to_phred(0.001_f64.ln()), 30.0);
+        assert_close!(ln_prob_to_phred(phred_to_ln_prob(93_f32)), 93.0);
+        assert_eq!(phred_to_ln_prob(0_f64), 0.0);
+        assert_eq!(ln_prob_to_phred(f64::NEG_INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_all_any_wrong() {
+        assert_close!(phred_all_wrong([20.0_f64, 30.0, 3.5]), 53.5);
+
+        let expected = -10.0 * (1.0 - 0.999_f64 * 0.999).log10();
+        assert_close!(phred_any_wrong([30.0_f64, 30.0]), expected);
+        // a single call is unchanged, even at qualities where 1 - p rounds to one
+        assert_close!(phred_any_wrong([200.0_f64]), 200.0);
+        assert_close!(phred_any_wrong([200.0_f64; 10]), 190.0);
+        assert_eq!(phred_any_wrong([0.0_f64, 40.0]), 0.0);
+        assert_eq!(phred_any_wrong::<f64, _>([]), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_merge_agreeing() {
+        assert_close!(phred_merge_agreeing([25.0_f64]), 25.0);
+        let err = 0.01_f64;
+        let wrong = 3.0 * (err / 3.0).powi(2);
+        let expected = -10.0 * (wrong / ((1.0 - err).powi(2) + wrong)).log10();
+        assert_close!(phred_merge_agreeing([20.0_f64, 20.0]), expected);
+        assert!(phred_merge_agreeing([30.0_f64, 30.0, 30.0]) > 90.0);
+        assert_close!(phred_merge_agreeing::<f64, _>([]), -10.0 * 0.75_f64.log10());
+    }
+
+    #[test]
+    fn test_round_capped() {
+        assert_eq!(phred_round_capped(41.6_f64, 40), 40);
+        assert_eq!(phred_round_capped(26.5_f64, 93), 27);
+        assert_eq!(phred_round_capped(-3.0_f64, 93), 0);
+        assert_eq!(phred_round_capped(f64::INFINITY, 60), 60);
+        assert_eq!(phred_round_capped(f64::NAN, 60), 0);
+    }
+}
@@ -0,0 +1,196 @@
+//! Expression plugins for Polars
+//!
+//! This registers `ln_sum_exp`, `ln_mean_exp`, and `log_softmax` as
+//! [expression plugins](https://docs.pola.rs/user-guide/plugins/expr_plugins/) in the same
+//! shared library as the [`python`][crate::python] module, so they run inside Polars queries,
+//! including per group in `group_by().agg(...)`. They operate on `Float32` and `Float64` columns,
+//! return the same type, and skip nulls:
+//!
+//! - `ln_sum_exp` is the `ln_sum_exp` of the non-null values, or negative infinity if there are
+//!   none.
+//! - `ln_mean_exp` is the `ln_sum_exp` minus the log of the number of non-null values, or null if
+//!   there are none.
+//! - `log_softmax` subtracts the `ln_sum_exp` from every value, keeping nulls in place.
+//!
+//! Build the library with `maturin build --release --features polars`, then register the
+//! expressions from Python:
+//!
+//! ```python
+//! from pathlib import Path
+//!
+//! import logaddexp
+//! import polars as pl
+//! from polars.plugins import register_plugin_function
+//!
+//! def ln_sum_exp(expr: pl.Expr) -> pl.Expr:
+//!     return register_plugin_function(
+//!         plugin_path=Path(logaddexp.__file__).parent,
+//!         function_name="ln_sum_exp",
+//!         args=expr,
+//!         returns_scalar=True,
+//!     )
+//!
+//! df.group_by("key").agg(ln_sum_exp(pl.col("log_score")))
+//! ```
+//!
+//! `ln_mean_exp` is registered the same way, and `log_softmax` is registered without
+//! `returns_scalar` and with `is_elementwise=False`.
+// the plugin macro generates undocumented extern functions
+#![allow(missing_docs)]
+
+use super::math::ln;
+use super::{ln_sum_exp_slice, StreamingLnSumExp};
+use num_traits::{Float, NumCast};
+use pyo3_polars::derive::polars_expr;
+use pyo3_polars::export::polars_arrow::array::Array;
+use pyo3_polars::export::polars_core::prelude::{
+    polars_bail, ChunkApply, ChunkedArray, DataType, Field, Float32Type, Float64Type, IntoSeries,
+    NewChunkedArray, PolarsFloatType, PolarsResult, Series,
+};
+
+/// The input column's field, since every expression returns the input type
+#[allow(clippy::unnecessary_wraps)]
+fn same_type(input_fields: &[Field]) -> PolarsResult<Field> {
+    Ok(input_fields[0].clone())
+}
+
+/// The `ln_sum_exp` of the non-null values and the number of them
+///
+/// Chunks without nulls are reduced with the slice kernels.
+fn ln_sum_exp_chunked<T>(values: &ChunkedArray<T>) -> (T::Native, usize)
+where
+    T: PolarsFloatType,
+    T::Native: Float,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for chunk in values.downcast_iter() {
+        if chunk.null_count() == 0 {
+            acc.push(ln_sum_exp_slice(chunk.values()));
+        } else {
+            for val in chunk.iter().flatten() {
+                acc.push(*val);
+            }
+        }
+    }
+    (acc.ln_sum_exp(), values.len() - values.null_count())
+}
+
+/// Dispatch a float column to a function generic over its type
+macro_rules! dispatch_float {
+    ($series:expr, $func:ident) => {{
+        let series: &Series = $series;
+        match series.dtype() {
+            DataType::Float32 => Ok($func::<Float32Type>(series.f32()?)),
+            DataType::Float64 => Ok($func::<Float64Type>(series.f64()?)),
+            dtype => polars_bail!(InvalidOperation: "expected a float column, got {}", dtype),
+        }
+    }};
+}
+
+/// The `ln_sum_exp` as a column of one value
+fn ln_sum_exp_series<T>(values: &ChunkedArray<T>) -> Series
+where
+    T: PolarsFloatType,
+    T::Native: Float,
+    ChunkedArray<T>: IntoSeries,
+{
+    let (total, _) = ln_sum_exp_chunked(values);
+    ChunkedArray::<T>::from_slice(values.name().clone(), &[total]).into_series()
+}
+
+/// The `ln_mean_exp` as a column of one value, which is null if there aren't any values
+fn ln_mean_exp_series<T>(values: &ChunkedArray<T>) -> Series
+where
+    T: PolarsFloatType,
+    T::Native: Float,
+    ChunkedArray<T>: IntoSeries,
+{
+    let (total, count) = ln_sum_exp_chunked(values);
+    let mean = <T::Native as NumCast>::from(count)
+        .filter(|_| count > 0)
+        .map(|count| total - ln(count));
+    ChunkedArray::<T>::from_slice_options(values.name().clone(), &[mean]).into_series()
+}
+
+/// Every value minus the `ln_sum_exp`
+fn log_softmax_series<T>(values: &ChunkedArray<T>) -> Series
+where
+    T: PolarsFloatType,
+    T::Native: Float,
+    ChunkedArray<T>: IntoSeries,
+{
+    let (total, _) = ln_sum_exp_chunked(values);
+    values.apply_values(|val| val - total).into_series()
+}
+
+/// The `ln_sum_exp` of a float column
+#[polars_expr(output_type_func = same_type)]
+fn ln_sum_exp(inputs: &[Series]) -> PolarsResult<Series> {
+    dispatch_float!(&inputs[0], ln_sum_exp_series)
+}
+
+/// The `ln_sum_exp` of a float column minus the log of its length
+#[polars_expr(output_type_func = same_type)]
+fn ln_mean_exp(inputs: &[Series]) -> PolarsResult<Series> {
+    dispatch_float!(&inputs[0], ln_mean_exp_series)
+}
+
+/// A float column minus its `ln_sum_exp`
+#[polars_expr(output_type_func = same_type)]
+fn log_softmax(inputs: &[Series]) -> PolarsResult<Series> {
+    dispatch_float!(&inputs[0], log_softmax_series)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_mean_exp_series, ln_sum_exp_series, log_softmax_series};
+    use crate::LogSumExp;
+    use pyo3_polars::export::polars_core::prelude::{
+        Float32Chunked, Float64Chunked, NewChunkedArray,
+    };
+
+    #[test]
+    fn test_ln_sum_exp() {
+        let values = Float64Chunked::from_slice_options(
+            "x".into(),
+            &[Some(1.0), None, Some(2.0), Some(4.0)],
+        );
+        let res = ln_sum_exp_series(&values);
+        let expected = [1.0, 2.0, 4.0].into_iter().ln_sum_exp();
+        assert_close!(res.f64().unwrap().get(0).unwrap(), expected);
+
+        let values = Float32Chunked::from_slice("x".into(), &[1.0, 2.0]);
+        let res = ln_sum_exp_series(&values);
+        assert_close!(
+            res.f32().unwrap().get(0).unwrap(),
+            [1_f32, 2.0].into_iter().ln_sum_exp()
+        );
+
+        let values = Float64Chunked::from_slice_options("x".into(), &[None]);
+        let res = ln_sum_exp_series(&values);
+        assert_eq!(res.f64().unwrap().get(0), Some(f64::NEG_INFINITY));
+    }
+
+    #[test]
+    fn test_ln_mean_exp() {
+        let values = Float64Chunked::from_slice_options("x".into(), &[Some(1.0), None, Some(1.0)]);
+        let res: f64 = ln_mean_exp_series(&values).f64().unwrap().get(0).unwrap();
+        assert_close!(res, 1.0);
+
+        let values = Float64Chunked::from_slice_options("x".into(), &[None]);
+        let res = ln_mean_exp_series(&values);
+        assert_eq!(res.f64().unwrap().get(0), None);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let values = Float64Chunked::from_slice_options("x".into(), &[Some(0.0), None, Some(0.0)]);
+        let res = log_softmax_series(&values);
+        let res: Vec<Option<f64>> = res.f64().unwrap().iter().collect();
+        assert_eq!(res.len(), 3);
+        assert_close!(res[0].unwrap(), 0.5_f64.ln());
+        assert_eq!(res[1], None);
+        assert_close!(res[2].unwrap(), 0.5_f64.ln());
+    }
+}
@@ -0,0 +1,157 @@
+//! Log-domain operations on `softposit` posits
+//!
+//! `softposit`'s posits implement [`Float`][num_traits::Float], so the blanket
+//! [`LogAddExp`][crate::LogAddExp] and [`LogSumExp`][crate::LogSumExp] implementations already
+//! apply to them, but those evaluate `exp` in posit arithmetic, where underflow saturates as
+//! described below. [`PositLogExp`] provides posit-aware versions under distinct names, so they
+//! don't clash with the blanket methods. Shifting by the maximum,
+//! subtracting, and summing are done in posit arithmetic, so results show how posit rounding
+//! affects log-domain accumulation, while `exp` and `ln` are evaluated in `f64` and rounded.
+//!
+//! Posits have no infinities and never round a nonzero value to zero. Instead they saturate at
+//! the smallest positive value, so a term far below the maximum would contribute that value
+//! rather than nothing, and enough of them would inflate the sum. Terms whose exponential
+//! is below the smallest positive posit are dropped instead, which matches what IEEE floats do
+//! when the exponential underflows. `NaR`, "not a real", plays the role of both NaN and negative
+//! infinity, so it's returned for empty inputs or if any value is `NaR`.
+use super::math::{exp, ln};
+use softposit::{P16, P32};
+use std::ops::{Add, Sub};
+
+/// The parts of a posit type the kernel needs
+trait Posit:
+    Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> + From<f64> + Into<f64>
+{
+    /// The not-a-real value
+    const NAR: Self;
+    /// The natural log of the smallest positive value
+    const LN_MIN_POS: f64;
+
+    /// True if this is `NaR`
+    fn nar(self) -> bool;
+}
+
+impl Posit for P32 {
+    const NAR: Self = P32::NAR;
+    // 2^-120
+    const LN_MIN_POS: f64 = -83.177_661_667_193_43;
+
+    fn nar(self) -> bool {
+        self.is_nar()
+    }
+}
+
+impl Posit for P16 {
+    const NAR: Self = P16::NAR;
+    // 2^-28
+    const LN_MIN_POS: f64 = -19.408_121_055_678_468;
+
+    fn nar(self) -> bool {
+        self.is_nar()
+    }
+}
+
+/// `ln_sum_exp` of posits, see the module docs for the special cases
+fn posit_ln_sum_exp<P: Posit>(values: &[P]) -> P {
+    match values.split_first() {
+        Some((&first, rest)) if !values.iter().any(|val| val.nar()) => {
+            let max = rest
+                .iter()
+                .fold(first, |max, &val| if val > max { val } else { max });
+            let sum = values
+                .iter()
+                .map(|&val| Into::<f64>::into(val - max))
+                .filter(|&diff| diff >= P::LN_MIN_POS)
+                .map(|diff| P::from(exp(diff)))
+                .fold(P::from(0.0), P::add);
+            max + P::from(ln(sum.into()))
+        }
+        _ => P::NAR,
+    }
+}
+
+/// Log-domain operations on posits
+pub trait PositLogExp: Sized {
+    /// Compute [`ln_add_exp`][crate::LogAddExp::ln_add_exp] in posit arithmetic
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::PositLogExp;
+    /// use softposit::P32;
+    ///
+    /// P32::from(0.0).ln_add_exp_posit(P32::from(0.0)); // 2_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_add_exp_posit(self, other: Self) -> Self;
+
+    /// Compute [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] of a slice in posit arithmetic
+    ///
+    /// An empty slice returns `NaR`, since posits can't represent the log of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::PositLogExp;
+    /// use softposit::P16;
+    ///
+    /// P16::ln_sum_exp_posit(&[P16::from(0.0); 4]); // 4_f64.ln()
+    /// ```
+    #[must_use]
+    fn ln_sum_exp_posit(values: &[Self]) -> Self;
+}
+
+impl<P: Posit> PositLogExp for P {
+    fn ln_add_exp_posit(self, other: Self) -> Self {
+        posit_ln_sum_exp(&[self, other])
+    }
+
+    fn ln_sum_exp_posit(values: &[Self]) -> Self {
+        posit_ln_sum_exp(values)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::PositLogExp;
+    use crate::{LogAddExp, LogSumExp};
+    use softposit::{P16, P32};
+
+    #[test]
+    fn test_ln_add_exp() {
+        for a in [-50.0, -3.0, 0.0, 0.5, 20.0] {
+            for b in [-49.0, -3.1, 0.0, 7.0, 36.5] {
+                let res = P32::from(a).ln_add_exp_posit(P32::from(b));
+                assert_close!(f64::from(res), a.ln_add_exp(b), rtol = 1e-6, atol = 1e-6);
+                let res = P16::from(a).ln_add_exp_posit(P16::from(b));
+                assert_close!(f64::from(res), a.ln_add_exp(b), rtol = 1e-2, atol = 1e-2);
+            }
+        }
+        assert!(P32::NAR.ln_add_exp_posit(P32::from(1.0)).is_nar());
+        assert!(P16::from(1.0).ln_add_exp_posit(P16::NAR).is_nar());
+    }
+
+    #[test]
+    fn test_ln_sum_exp() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let posits: Vec<P32> = vals.iter().map(|&val| P32::from(val)).collect();
+        let res = P32::ln_sum_exp_posit(&posits);
+        assert_close!(
+            f64::from(res),
+            vals.iter().copied().ln_sum_exp(),
+            rtol = 1e-6
+        );
+
+        assert!(P32::ln_sum_exp_posit(&[]).is_nar());
+        assert!(P16::ln_sum_exp_posit(&[P16::from(1.0), P16::NAR]).is_nar());
+    }
+
+    #[test]
+    fn test_ln_sum_exp_underflow() {
+        // exp(-30) is below the smallest positive p16, so these contribute nothing
+        let mut vals = vec![P16::from(-30.0); 10_000];
+        vals.push(P16::from(0.0));
+        assert_eq!(f64::from(P16::ln_sum_exp_posit(&vals)), 0.0);
+    }
+}
@@ -0,0 +1,145 @@
+//! A Python extension module built with `PyO3`
+//!
+//! This exposes the slice kernels to Python as a module named `logaddexp` that operates on
+//! one dimensional `float64` `NumPy` arrays. Contiguous arrays are read in place through a view of
+//! their buffer, so the input is never copied; other arrays are iterated through their strides.
+//!
+//! The module can be built with [maturin](https://www.maturin.rs), which compiles the crate as a
+//! `cdylib` and packages it as a wheel:
+//!
+//! ```text
+//! maturin build --release --features python
+//! ```
+//!
+//! ```python
+//! import numpy as np
+//! import logaddexp
+//!
+//! logaddexp.logsumexp(np.array([1.0, 2.0, 3.0]))
+//! ```
+// pyo3 functions take their arguments by value
+#![allow(clippy::needless_pass_by_value)]
+
+use super::math::ln;
+use super::signed::ln_sum_exp_signed_iter;
+use super::{ln_normalize_collect, ln_sum_exp_slice, LogSumExp, StreamingLnSumExp};
+use numpy::{PyArray1, PyReadonlyArray1, PyUntypedArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::{pyfunction, pymodule, Bound, PyModule, PyModuleMethods, PyResult, Python};
+use pyo3::wrap_pyfunction;
+
+/// `ln_sum_exp` of an array, reading it in place when it's contiguous
+fn ln_sum_exp_array(values: &PyReadonlyArray1<'_, f64>) -> f64 {
+    match values.as_slice() {
+        Ok(slice) => ln_sum_exp_slice(slice),
+        Err(_) => values.as_array().iter().copied().ln_sum_exp(),
+    }
+}
+
+/// Compute `ln(sum(weights * exp(values)))` for nonnegative weights
+///
+/// A negative weight produces NaN.
+fn weighted_ln_sum_exp<'a>(
+    values: impl Iterator<Item = &'a f64>,
+    weights: impl Iterator<Item = &'a f64>,
+) -> f64 {
+    let mut acc = StreamingLnSumExp::new();
+    for (&val, &weight) in values.zip(weights) {
+        acc.push(val + ln(weight));
+    }
+    acc.ln_sum_exp()
+}
+
+/// Check that the values and weights have the same length
+fn check_len(
+    values: &PyReadonlyArray1<'_, f64>,
+    weights: &PyReadonlyArray1<'_, f64>,
+) -> PyResult<()> {
+    if values.len() == weights.len() {
+        Ok(())
+    } else {
+        Err(PyValueError::new_err(
+            "values and weights must have the same length",
+        ))
+    }
+}
+
+/// Compute `log(sum(exp(values)))`
+#[pyfunction]
+fn logsumexp(values: PyReadonlyArray1<'_, f64>) -> f64 {
+    ln_sum_exp_array(&values)
+}
+
+/// Compute `log(sum(weights * exp(values)))` for nonnegative weights
+#[pyfunction]
+fn logsumexp_weighted(
+    values: PyReadonlyArray1<'_, f64>,
+    weights: PyReadonlyArray1<'_, f64>,
+) -> PyResult<f64> {
+    check_len(&values, &weights)?;
+    Ok(weighted_ln_sum_exp(
+        values.as_array().iter(),
+        weights.as_array().iter(),
+    ))
+}
+
+/// Compute `log(abs(sum(weights * exp(values))))` and the sign of the sum
+#[pyfunction]
+fn logsumexp_signed(
+    values: PyReadonlyArray1<'_, f64>,
+    weights: PyReadonlyArray1<'_, f64>,
+) -> PyResult<(f64, f64)> {
+    check_len(&values, &weights)?;
+    Ok(ln_sum_exp_signed_iter(
+        values
+            .as_array()
+            .iter()
+            .copied()
+            .zip(weights.as_array().iter().copied()),
+    ))
+}
+
+/// Compute `values - logsumexp(values)` as a new array
+#[pyfunction]
+fn log_softmax<'py>(
+    py: Python<'py>,
+    values: PyReadonlyArray1<'py, f64>,
+) -> Bound<'py, PyArray1<f64>> {
+    let log_probs = match values.as_slice() {
+        Ok(slice) => ln_normalize_collect(slice).1,
+        Err(_) => ln_normalize_collect(&values.as_array().to_vec()).1,
+    };
+    PyArray1::from_vec(py, log_probs)
+}
+
+/// The `logaddexp` Python module
+///
+/// # Errors
+///
+/// If a function can't be added to the module.
+#[pymodule]
+pub fn logaddexp(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(logsumexp, module)?)?;
+    module.add_function(wrap_pyfunction!(logsumexp_weighted, module)?)?;
+    module.add_function(wrap_pyfunction!(logsumexp_signed, module)?)?;
+    module.add_function(wrap_pyfunction!(log_softmax, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::weighted_ln_sum_exp;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_weighted() {
+        let vals = [1.0, 2.0, 3.0];
+        let weights = [2.0, 0.0, 0.5];
+        let expected = [1.0 + 2_f64.ln(), 3.0 - 2_f64.ln()]
+            .into_iter()
+            .ln_sum_exp();
+        assert_close!(weighted_ln_sum_exp(vals.iter(), weights.iter()), expected);
+        assert!(weighted_ln_sum_exp(vals.iter(), [1.0, -1.0, 1.0].iter()).is_nan());
+    }
+}
@@ -0,0 +1,245 @@
+//! Reductions over quantized log values
+//!
+//! Large tables of log probabilities are often stored as small integers with a shared scale and
+//! offset. [`QuantizedLn`] holds those parameters, and its reductions dequantize values on the
+//! fly, so the table is read once at its stored width instead of being expanded into floats
+//! first. Since dequantization is monotonic, the maximum is found on the integers directly.
+use super::math::{cast, exp, ln};
+use super::slice::LANES;
+use num_traits::{Bounded, Float, NumCast};
+
+/// The affine map `offset + scale * q` from quantized levels to log values
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::QuantizedLn;
+///
+/// let quant = QuantizedLn::fit::<u8>(-20.0, 0.0);
+/// let table: Vec<u8> = [-1.0, -2.0, -3.0].iter().map(|&val| quant.quantize(val)).collect();
+/// quant.ln_sum_exp(&table); // about ((-1_f64).exp() + (-2_f64).exp() + (-3_f64).exp()).ln()
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizedLn<T> {
+    scale: T,
+    offset: T,
+}
+
+impl<T> QuantizedLn<T>
+where
+    T: Float,
+{
+    /// Create quantization parameters where level `q` is the log value `offset + scale * q`
+    ///
+    /// # Panics
+    ///
+    /// If `scale` isn't positive and finite, or `offset` isn't finite.
+    #[must_use]
+    pub fn new(scale: T, offset: T) -> Self {
+        assert!(
+            scale > T::zero() && scale.is_finite() && offset.is_finite(),
+            "scale must be positive and finite, and offset must be finite"
+        );
+        QuantizedLn { scale, offset }
+    }
+
+    /// Create quantization parameters that map `[min, max]` onto every level of `Q`
+    ///
+    /// # Panics
+    ///
+    /// If `min` and `max` aren't finite with `min` less than `max`.
+    #[must_use]
+    pub fn fit<Q>(min: T, max: T) -> Self
+    where
+        Q: Bounded + NumCast,
+    {
+        assert!(
+            min.is_finite() && max.is_finite() && min < max,
+            "range must be finite and nonempty"
+        );
+        let low = cast::<T>(Q::min_value());
+        let high = cast::<T>(Q::max_value());
+        let scale = (max - min) / (high - low);
+        Self::new(scale, min - low * scale)
+    }
+
+    /// The log value between adjacent levels
+    #[must_use]
+    pub fn scale(&self) -> T {
+        self.scale
+    }
+
+    /// The log value of level zero
+    #[must_use]
+    pub fn offset(&self) -> T {
+        self.offset
+    }
+
+    /// The nearest level to a log value
+    ///
+    /// Values outside the representable range, including infinities, saturate to the lowest or
+    /// highest level, and NaN quantizes to the lowest level.
+    #[must_use]
+    pub fn quantize<Q>(&self, value: T) -> Q
+    where
+        Q: Bounded + NumCast,
+    {
+        let low = cast::<T>(Q::min_value());
+        let high = cast::<T>(Q::max_value());
+        let level = ((value - self.offset) / self.scale).round();
+        cast::<Q>(level.max(low).min(high))
+    }
+
+    /// The log value of a level
+    #[must_use]
+    pub fn dequantize<Q>(&self, level: Q) -> T
+    where
+        T: From<Q>,
+    {
+        self.offset + self.scale * <T as From<Q>>::from(level)
+    }
+
+    /// The largest level and the sum of `exp` of every value shifted by its value
+    fn max_sum<Q>(&self, levels: &[Q]) -> Option<(Q, T)>
+    where
+        Q: Copy + Ord,
+        T: From<Q>,
+    {
+        let &max = levels.iter().max()?;
+        let shift = <T as From<Q>>::from(max);
+        let mut lanes = [T::zero(); LANES];
+        for chunk in levels.chunks(LANES) {
+            for (lane, &level) in lanes.iter_mut().zip(chunk) {
+                *lane = *lane + exp(self.scale * (<T as From<Q>>::from(level) - shift));
+            }
+        }
+        Some((max, lanes.into_iter().fold(T::zero(), |a, b| a + b)))
+    }
+
+    /// Compute `ln_sum_exp` of quantized log values
+    ///
+    /// This is the same as dequantizing every level and calling
+    /// [`ln_sum_exp_slice`][crate::ln_sum_exp_slice], without the intermediate floats. An empty
+    /// slice returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp<Q>(&self, levels: &[Q]) -> T
+    where
+        Q: Copy + Ord,
+        T: From<Q>,
+    {
+        match self.max_sum(levels) {
+            Some((max, sum)) => self.dequantize(max) + ln(sum),
+            None => T::neg_infinity(),
+        }
+    }
+
+    /// Write the normalized log values of quantized log values into `out` and return their
+    /// `ln_sum_exp`
+    ///
+    /// # Panics
+    ///
+    /// If `levels` and `out` have different lengths.
+    pub fn ln_normalize<Q>(&self, levels: &[Q], out: &mut [T]) -> T
+    where
+        Q: Copy + Ord,
+        T: From<Q>,
+    {
+        assert_eq!(
+            levels.len(),
+            out.len(),
+            "levels and out must have the same length"
+        );
+        let total = self.ln_sum_exp(levels);
+        for (res, &level) in out.iter_mut().zip(levels) {
+            *res = self.dequantize(level) - total;
+        }
+        total
+    }
+
+    /// Write the softmax of quantized log values into `out` and return their `ln_sum_exp`
+    ///
+    /// # Panics
+    ///
+    /// If `levels` and `out` have different lengths.
+    pub fn softmax<Q>(&self, levels: &[Q], out: &mut [T]) -> T
+    where
+        Q: Copy + Ord,
+        T: From<Q>,
+    {
+        assert_eq!(
+            levels.len(),
+            out.len(),
+            "levels and out must have the same length"
+        );
+        match self.max_sum(levels) {
+            Some((max, sum)) => {
+                let shift = <T as From<Q>>::from(max);
+                for (res, &level) in out.iter_mut().zip(levels) {
+                    *res = exp(self.scale * (<T as From<Q>>::from(level) - shift)) / sum;
+                }
+                self.dequantize(max) + ln(sum)
+            }
+            None => T::neg_infinity(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::QuantizedLn;
+    use crate::{ln_normalize_collect, ln_sum_exp_slice, softmax_collect};
+
+    #[test]
+    fn test_quantize() {
+        let quant = QuantizedLn::fit::<u8>(-25.5_f64, 0.0);
+        assert_close!(quant.scale(), 0.1);
+        assert_eq!(quant.offset(), -25.5);
+        assert_eq!(quant.quantize::<u8>(0.0), 255);
+        assert_eq!(quant.quantize::<u8>(-25.5), 0);
+        assert_eq!(quant.quantize::<u8>(-1.04), 245);
+        assert_eq!(quant.quantize::<u8>(f64::NEG_INFINITY), 0);
+        assert_eq!(quant.quantize::<u8>(f64::INFINITY), 255);
+        assert_eq!(quant.quantize::<u8>(f64::NAN), 0);
+        assert_close!(quant.dequantize(245_u8), -1.0);
+
+        let quant = QuantizedLn::fit::<u16>(-100_f32, 0.0);
+        let val = quant.dequantize(quant.quantize::<u16>(-3.21));
+        assert_close!(val, -3.21, atol = quant.scale());
+    }
+
+    #[test]
+    fn test_quantized_reductions() {
+        let quant = QuantizedLn::new(0.05, -40.0);
+        let levels: Vec<u16> = (0..100_u16).map(|n| n * 37 % 800).collect();
+        let vals: Vec<f64> = levels
+            .iter()
+            .map(|&level| quant.dequantize(level))
+            .collect();
+
+        let total = ln_sum_exp_slice(&vals);
+        assert_close!(quant.ln_sum_exp(&levels), total);
+
+        let (_, expected) = ln_normalize_collect(&vals);
+        let mut out = vec![0.0; levels.len()];
+        assert_close!(quant.ln_normalize(&levels, &mut out), total);
+        for (&res, &exp) in out.iter().zip(&expected) {
+            assert_close!(res, exp);
+        }
+
+        let (_, expected) = softmax_collect(&vals);
+        assert_close!(quant.softmax(&levels, &mut out), total);
+        for (&res, &exp) in out.iter().zip(&expected) {
+            assert_close!(res, exp);
+        }
+
+        assert_eq!(quant.ln_sum_exp::<u8>(&[]), f64::NEG_INFINITY);
+        assert_eq!(quant.softmax::<u8>(&[], &mut []), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "levels and out must have the same length")]
+    fn test_quantized_mismatch() {
+        let _ = QuantizedLn::new(1.0, 0.0).softmax(&[1_u8, 2], &mut [0.0]);
+    }
+}
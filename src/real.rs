@@ -0,0 +1,109 @@
+//! Log-domain arithmetic for types that only implement [`Real`]
+//!
+//! The [`LogAddExp`][crate::LogAddExp], [`LogSubExp`][crate::LogSubExp], and
+//! [`LogSumExp`][crate::LogSumExp] implementations require [`Float`][num_traits::Float], because
+//! they treat infinities and NaN specially, and negative infinity is the log of an empty sum.
+//! [`Real`] covers types that model the reals without those values, like some wrapper, fixed
+//! precision, and interval types, and every `Float` is also `Real`. These functions only use
+//! `Real` operations, so they don't special-case non-finite values, and an empty sum has no
+//! result. They call the `Real` methods directly, so they aren't affected by the `reproducible`
+//! feature. For IEEE floats, prefer the traits.
+use super::math::cast;
+use num_traits::real::Real;
+
+/// Compute `ln(exp(a) + exp(b))` for [`Real`] values
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_add_exp_real;
+/// ln_add_exp_real(100_f64.ln(), 0.0); // 101_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_add_exp_real<T>(a: T, b: T) -> T
+where
+    T: Real,
+{
+    a.max(b) + (-(a - b).abs()).exp().ln_1p()
+}
+
+/// Compute `ln(exp(a) - exp(b))` for [`Real`] values
+///
+/// `b` must be less than `a`. If they're equal the result is the type's log of zero, and if `b`
+/// is larger the result is the type's log of a negative number.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sub_exp_real;
+/// ln_sub_exp_real(3_f64.ln(), 0.0); // 2_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_sub_exp_real<T>(a: T, b: T) -> T
+where
+    T: Real,
+{
+    let diff = b - a;
+    let ln_2 = cast::<T>(std::f64::consts::LN_2);
+    if diff > -ln_2 {
+        a + (-diff.exp_m1()).ln()
+    } else {
+        a + (-diff.exp()).ln_1p()
+    }
+}
+
+/// Compute `ln Σ exp(xᵢ)` for [`Real`] values in a single pass
+///
+/// Like [`StreamingLnSumExp`][crate::StreamingLnSumExp], this keeps the running maximum and the
+/// sum of the exponentials shifted by it. `Real` has no negative infinity to represent the log of
+/// an empty sum, so an empty iterator returns `None`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_real;
+/// ln_sum_exp_real([1_f64.ln(), 2_f64.ln(), 3_f64.ln()]); // Some(6_f64.ln())
+/// ```
+pub fn ln_sum_exp_real<I, T>(values: I) -> Option<T>
+where
+    I: IntoIterator<Item = T>,
+    T: Real,
+{
+    let mut iter = values.into_iter();
+    let mut max = iter.next()?;
+    let mut sum = T::one();
+    for value in iter {
+        if value > max {
+            sum = sum * (max - value).exp() + T::one();
+            max = value;
+        } else {
+            sum = sum + (value - max).exp();
+        }
+    }
+    Some(max + sum.ln())
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_add_exp_real, ln_sub_exp_real, ln_sum_exp_real};
+    use crate::{LogAddExp, LogSubExp, LogSumExp};
+
+    #[test]
+    fn test_real() {
+        for (a, b) in [(0_f64, 0.0), (1.0, -3.0), (-1000.0, -1000.5), (5.0, 40.0)] {
+            assert_close!(ln_add_exp_real(a, b), a.ln_add_exp(b));
+            let (big, small) = if a > b { (a, b) } else { (b, a) };
+            if big != small {
+                assert_close!(ln_sub_exp_real(big, small), big.ln_sub_exp(small));
+            }
+        }
+        assert_eq!(ln_sub_exp_real(1_f64, 1.0), f64::NEG_INFINITY);
+
+        let vals: Vec<f32> = (0..50_u8).map(|n| f32::from(n).sin() * 80.0).collect();
+        let res = ln_sum_exp_real(vals.iter().copied()).unwrap();
+        assert_close!(res, vals.iter().copied().ln_sum_exp());
+        assert_eq!(ln_sum_exp_real([2_f64]), Some(2.0));
+        assert_eq!(ln_sum_exp_real(std::iter::empty::<f64>()), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! A first-in first-out window that tracks its `ln_sum_exp`
+use super::LogAddExp;
+use num_traits::Float;
+
+/// A queue of log values that can report the `ln_sum_exp` of its contents
+///
+/// Values are pushed onto the back and popped from the front, and every operation is amortized
+/// O(1). Internally this uses the two-stack queue, where the front stack stores suffix aggregates
+/// so that removing the oldest value never requires an inverse of `ln_add_exp`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::RollingLnSumExp;
+///
+/// let mut window = RollingLnSumExp::new();
+/// for val in [1.0, 2.0, 3.0, 4.0] {
+///     window.push(val);
+///     if window.len() > 2 {
+///         window.pop_front();
+///     }
+/// }
+/// window.ln_sum_exp(); // (3_f64.exp() + 4_f64.exp()).ln()
+/// ```
+#[derive(Debug, Clone)]
+pub struct RollingLnSumExp<T> {
+    /// oldest values on top, each paired with the `ln_sum_exp` of itself and everything beneath
+    front: Vec<(T, T)>,
+    /// newest values on top
+    back: Vec<T>,
+    /// `ln_sum_exp` of everything in back
+    back_total: T,
+}
+
+impl<T> RollingLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create a new empty window
+    #[must_use]
+    pub fn new() -> Self {
+        RollingLnSumExp {
+            front: Vec::new(),
+            back: Vec::new(),
+            back_total: T::neg_infinity(),
+        }
+    }
+
+    /// The number of values in the window
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    /// True if the window contains no values
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.front.is_empty() && self.back.is_empty()
+    }
+
+    /// Add a value to the back of the window
+    pub fn push(&mut self, value: T) {
+        self.back.push(value);
+        self.back_total = self.back_total.ln_add_exp(value);
+    }
+
+    /// Remove and return the oldest value in the window
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.front.is_empty() {
+            let mut total = T::neg_infinity();
+            while let Some(val) = self.back.pop() {
+                total = total.ln_add_exp(val);
+                self.front.push((val, total));
+            }
+            self.back_total = T::neg_infinity();
+        }
+        self.front.pop().map(|(val, _)| val)
+    }
+
+    /// The `ln_sum_exp` of every value currently in the window
+    ///
+    /// An empty window returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        match self.front.last() {
+            Some(&(_, total)) => total.ln_add_exp(self.back_total),
+            None => self.back_total,
+        }
+    }
+}
+
+impl<T> Default for RollingLnSumExp<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::RollingLnSumExp;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_rolling_ln_sum_exp() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let mut window = RollingLnSumExp::new();
+        assert!(window.is_empty());
+        assert_eq!(window.ln_sum_exp(), f64::NEG_INFINITY);
+
+        for (i, &val) in vals.iter().enumerate() {
+            window.push(val);
+            if window.len() > 5 {
+                assert_eq!(window.pop_front(), Some(vals[i - 5]));
+            }
+            let start = i.saturating_sub(4);
+            let expected = vals[start..=i].iter().copied().ln_sum_exp();
+            assert_close!(window.ln_sum_exp(), expected);
+        }
+
+        while window.pop_front().is_some() {}
+        assert!(window.is_empty());
+        assert_eq!(window.pop_front(), None);
+        assert_eq!(window.ln_sum_exp(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_rolling_ln_sum_exp_infinite() {
+        let mut window = RollingLnSumExp::new();
+        window.push(f64::INFINITY);
+        window.push(1.0);
+        assert_eq!(window.ln_sum_exp(), f64::INFINITY);
+        window.pop_front();
+        assert_close!(window.ln_sum_exp(), 1.0);
+    }
+}
@@ -0,0 +1,161 @@
+//! A segment tree for range `ln_sum_exp` queries
+use super::LogAddExp;
+use num_traits::Float;
+use std::ops::{Bound, RangeBounds};
+
+/// A fixed size array of log values supporting `ln_sum_exp` over any contiguous range
+///
+/// Both point updates and range queries take O(log n) time.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LogSumExpSegmentTree;
+///
+/// let mut tree = LogSumExpSegmentTree::new(&[1.0, 2.0, 3.0, 4.0]);
+/// tree.range_ln_sum_exp(1..3); // (2_f64.exp() + 3_f64.exp()).ln()
+/// tree.update(2, 0.0);
+/// tree.range_ln_sum_exp(..); // (1_f64.exp() + 2_f64.exp() + 1.0 + 4_f64.exp()).ln()
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogSumExpSegmentTree<T> {
+    /// leaves live in `n..2n` and node `i` combines `2i` and `2i + 1`
+    nodes: Vec<T>,
+}
+
+impl<T> LogSumExpSegmentTree<T>
+where
+    T: Float,
+{
+    /// Build a tree over a slice of log values in O(n)
+    #[must_use]
+    pub fn new(values: &[T]) -> Self {
+        let len = values.len();
+        let mut nodes = vec![T::neg_infinity(); 2 * len];
+        nodes[len..].copy_from_slice(values);
+        for i in (1..len).rev() {
+            nodes[i] = nodes[2 * i].ln_add_exp(nodes[2 * i + 1]);
+        }
+        LogSumExpSegmentTree { nodes }
+    }
+
+    /// The number of values in the tree
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len() / 2
+    }
+
+    /// True if the tree contains no values
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The value at `index`
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> T {
+        assert!(index < self.len(), "index out of bounds");
+        self.nodes[self.len() + index]
+    }
+
+    /// Replace the value at `index`
+    ///
+    /// # Panics
+    ///
+    /// If `index` is out of bounds.
+    pub fn update(&mut self, index: usize, value: T) {
+        assert!(index < self.len(), "index out of bounds");
+        let mut node = self.len() + index;
+        self.nodes[node] = value;
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = self.nodes[2 * node].ln_add_exp(self.nodes[2 * node + 1]);
+        }
+    }
+
+    /// The `ln_sum_exp` of the values in `range`
+    ///
+    /// An empty range returns negative infinity.
+    ///
+    /// # Panics
+    ///
+    /// If `range` is out of bounds or decreasing.
+    #[must_use]
+    pub fn range_ln_sum_exp<R>(&self, range: R) -> T
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        assert!(start <= end && end <= len, "range out of bounds");
+
+        let mut total = T::neg_infinity();
+        let mut left = start + len;
+        let mut right = end + len;
+        while left < right {
+            if left % 2 == 1 {
+                total = total.ln_add_exp(self.nodes[left]);
+                left += 1;
+            }
+            if right % 2 == 1 {
+                right -= 1;
+                total = total.ln_add_exp(self.nodes[right]);
+            }
+            left /= 2;
+            right /= 2;
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LogSumExpSegmentTree;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_range_ln_sum_exp() {
+        let mut vals: Vec<f64> = (1..12).map(|n| f64::from(n).ln()).collect();
+        let mut tree = LogSumExpSegmentTree::new(&vals);
+        assert_eq!(tree.len(), 11);
+        vals[3] = 5.0;
+        tree.update(3, 5.0);
+        assert_eq!(tree.get(3), 5.0);
+
+        for start in 0..vals.len() {
+            for end in start + 1..=vals.len() {
+                assert_close!(
+                    tree.range_ln_sum_exp(start..end),
+                    vals[start..end].iter().copied().ln_sum_exp()
+                );
+            }
+        }
+        assert_eq!(tree.range_ln_sum_exp(4..4), f64::NEG_INFINITY);
+        assert_close!(tree.range_ln_sum_exp(..), vals.iter().copied().ln_sum_exp());
+        assert_close!(
+            tree.range_ln_sum_exp(2..=4),
+            vals[2..=4].iter().copied().ln_sum_exp()
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree = LogSumExpSegmentTree::<f64>::new(&[]);
+        assert!(tree.is_empty());
+        assert_eq!(tree.range_ln_sum_exp(..), f64::NEG_INFINITY);
+    }
+}
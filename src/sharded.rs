@@ -0,0 +1,153 @@
+//! A concurrent accumulator split into shards to avoid contention
+use super::StreamingLnSumExp;
+use num_traits::Float;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+/// Source of shard assignments for new threads
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The shard this thread pushes to, assigned round-robin on first use
+    static SHARD: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// The shard index for the current thread
+fn thread_shard() -> usize {
+    SHARD.with(|shard| {
+        shard.get().unwrap_or_else(|| {
+            let index = NEXT_SHARD.fetch_add(1, Ordering::Relaxed);
+            shard.set(Some(index));
+            index
+        })
+    })
+}
+
+/// A shard padded to its own cache line so that shards don't contend through false sharing
+#[derive(Debug)]
+#[repr(align(128))]
+struct Shard<T>(Mutex<StreamingLnSumExp<T>>);
+
+/// A `ln_sum_exp` accumulator for many threads pushing concurrently
+///
+/// Each thread is assigned one of several shards, each an independent
+/// [`StreamingLnSumExp`] behind its own lock, so threads almost never wait on each other.
+/// [`ln_sum_exp`][Self::ln_sum_exp] merges the shards.
+///
+/// Every push is atomic, but the shards are read one at a time, so the result while threads are
+/// still pushing reflects some of the concurrent pushes and not others. It isn't a consistent
+/// snapshot, but it never includes a partial push or counts a push twice. Once every push has
+/// happened before the read, for example after joining the pushing threads, the result is
+/// exact. Which shard a value lands in depends on thread scheduling, so the rounding of the
+/// result can vary between runs.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ShardedLnSumExp;
+/// use std::thread;
+///
+/// let acc = ShardedLnSumExp::new(4);
+/// thread::scope(|scope| {
+///     scope.spawn(|| acc.push(0.0));
+///     scope.spawn(|| acc.push(0.0));
+/// });
+/// acc.ln_sum_exp(); // 2_f64.ln()
+/// ```
+#[derive(Debug)]
+pub struct ShardedLnSumExp<T> {
+    shards: Box<[Shard<T>]>,
+}
+
+impl<T> ShardedLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create a new empty accumulator with a number of shards
+    ///
+    /// The number of shards should be about the number of threads that push concurrently.
+    ///
+    /// # Panics
+    ///
+    /// If `num_shards` is zero.
+    #[must_use]
+    pub fn new(num_shards: usize) -> Self {
+        assert!(num_shards > 0, "need at least one shard");
+        ShardedLnSumExp {
+            shards: (0..num_shards)
+                .map(|_| Shard(Mutex::new(StreamingLnSumExp::new())))
+                .collect(),
+        }
+    }
+
+    /// Add a value to the accumulator
+    pub fn push(&self, value: T) {
+        let Shard(shard) = &self.shards[thread_shard() % self.shards.len()];
+        // pushing can't panic, so a poisoned shard is still valid
+        shard
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(value);
+    }
+
+    /// The `ln_sum_exp` of the values pushed so far
+    ///
+    /// An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        let mut total = StreamingLnSumExp::new();
+        for Shard(shard) in &*self.shards {
+            total.merge(&shard.lock().unwrap_or_else(PoisonError::into_inner));
+        }
+        total.ln_sum_exp()
+    }
+}
+
+impl<T> Default for ShardedLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create an accumulator with one shard per available core
+    fn default() -> Self {
+        Self::new(thread::available_parallelism().map_or(1, usize::from))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ShardedLnSumExp;
+    use crate::LogSumExp;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_push() {
+        let acc = ShardedLnSumExp::new(3);
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        thread::scope(|scope| {
+            for t in 0..8 {
+                let acc = &acc;
+                scope.spawn(move || {
+                    for n in 0..500 {
+                        acc.push(f64::from(t * 500 + n) * -0.01);
+                    }
+                });
+            }
+        });
+        let expected = (0..4000).map(|n| f64::from(n) * -0.01).ln_sum_exp();
+        assert_close!(acc.ln_sum_exp(), expected);
+
+        let acc = ShardedLnSumExp::default();
+        acc.push(1.0);
+        acc.push(f64::INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one shard")]
+    fn test_no_shards() {
+        let _ = ShardedLnSumExp::<f64>::new(0);
+    }
+}
@@ -0,0 +1,189 @@
+//! The logistic function and its inverse in log space
+use super::math::{ln, ln_1p};
+use super::LogAddExp;
+use num_traits::Float;
+use std::error::Error;
+use std::fmt;
+
+/// Compute the log of the logistic sigmoid
+///
+/// This is `ln(σ(x)) = -ln(1 + exp(-x))`, computed with [`ln_add_exp`][LogAddExp::ln_add_exp] so
+/// it's accurate everywhere. `x.sigmoid().ln()` rounds to negative infinity for `x` below about
+/// `-745` and loses all precision for large positive `x`, while this returns about `x` and
+/// `-exp(-x)` respectively. It's the same as [`ln_odds_to_ln_prob`][crate::ln_odds_to_ln_prob].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::log_sigmoid;
+///
+/// log_sigmoid(0.0); // 0.5_f64.ln()
+/// log_sigmoid(-1000.0); // -1000.0
+/// ```
+#[must_use]
+pub fn log_sigmoid<T>(x: T) -> T
+where
+    T: Float,
+{
+    -T::zero().ln_add_exp(-x)
+}
+
+/// Replace every value in a slice with its [`log_sigmoid`]
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::log_sigmoid_in_place;
+///
+/// let mut vals = [0.0, 3_f64.ln()];
+/// log_sigmoid_in_place(&mut vals);
+/// // vals == [0.5_f64.ln(), 0.75_f64.ln()]
+/// ```
+pub fn log_sigmoid_in_place<T>(values: &mut [T])
+where
+    T: Float,
+{
+    for val in values {
+        *val = log_sigmoid(*val);
+    }
+}
+
+/// Compute the logit, the inverse of the logistic sigmoid
+///
+/// This is `ln(p / (1 - p))`, computed as `ln(p) - ln_1p(-p)` so it's accurate for `p` close to
+/// zero or one. Zero and one map to negative and positive infinity, and values outside `[0, 1]`
+/// produce NaN; see [`logit_clamped`] and [`checked_logit`] to avoid those.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::logit;
+///
+/// logit(0.75); // 3_f64.ln()
+/// ```
+#[must_use]
+pub fn logit<T>(p: T) -> T
+where
+    T: Float,
+{
+    ln(p) - ln_1p(-p)
+}
+
+/// Compute the [`logit`] of a probability clamped to `[eps, 1 - eps]`
+///
+/// Clamping keeps the result finite, so probabilities of exactly zero or one, e.g. from a model
+/// that saturated, don't produce infinities. NaN is still NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::logit_clamped;
+///
+/// logit_clamped(1.0, 1e-7); // about 16.1
+/// ```
+#[must_use]
+pub fn logit_clamped<T>(p: T, eps: T) -> T
+where
+    T: Float,
+{
+    if p.is_nan() {
+        p
+    } else {
+        logit(p.max(eps).min(T::one() - eps))
+    }
+}
+
+/// A value passed to [`checked_logit`] isn't a probability
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NotAProbability<T> {
+    /// The value that's outside `[0, 1]` or NaN
+    pub value: T,
+}
+
+impl<T: fmt::Display> fmt::Display for NotAProbability<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} isn't a probability in [0, 1]", self.value)
+    }
+}
+
+impl<T: fmt::Debug + fmt::Display> Error for NotAProbability<T> {}
+
+/// Compute the [`logit`] of a probability, or return an error if it isn't one
+///
+/// # Errors
+///
+/// If `p` is outside of `[0, 1]` or NaN.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::checked_logit;
+///
+/// assert!(checked_logit(0.5).is_ok());
+/// assert!(checked_logit(1.5).is_err());
+/// ```
+pub fn checked_logit<T>(p: T) -> Result<T, NotAProbability<T>>
+where
+    T: Float,
+{
+    if p >= T::zero() && p <= T::one() {
+        Ok(logit(p))
+    } else {
+        Err(NotAProbability { value: p })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        checked_logit, log_sigmoid, log_sigmoid_in_place, logit, logit_clamped, NotAProbability,
+    };
+
+    #[test]
+    fn test_log_sigmoid() {
+        for x in [-30.0_f64, -2.5, -0.1, 0.0, 0.7, 4.0, 30.0] {
+            assert_close!(log_sigmoid(x), (1.0 / (1.0 + (-x).exp())).ln());
+        }
+        assert_close!(log_sigmoid(-1000_f64), -1000.0);
+        assert_close!(log_sigmoid(40_f64), -(-40_f64).exp(), rtol = 1e-12);
+        assert_close!(log_sigmoid(-200_f32), -200.0);
+        assert_eq!(log_sigmoid(f64::INFINITY), 0.0);
+        assert_eq!(log_sigmoid(f64::NEG_INFINITY), f64::NEG_INFINITY);
+        assert!(log_sigmoid(f64::NAN).is_nan());
+
+        let mut vals = [-800_f64, 0.0, 3_f64.ln()];
+        log_sigmoid_in_place(&mut vals);
+        assert_close!(vals[0], -800.0);
+        assert_close!(vals[1], 0.5_f64.ln());
+        assert_close!(vals[2], 0.75_f64.ln());
+    }
+
+    #[test]
+    fn test_logit() {
+        for p in [1e-300_f64, 1e-5, 0.2, 0.5, 0.75, 0.999_999] {
+            let res = logit(p);
+            assert_close!(res, (p / (1.0 - p)).ln());
+            assert_close!(log_sigmoid(res), f64::ln(p));
+        }
+        // ln(p) is negligible, but 1 - p has to be computed exactly
+        let close = 1.0_f64 - 1e-15;
+        assert_close!(logit(close), -(1.0 - close).ln(), rtol = 1e-12);
+        assert_eq!(logit(0_f64), f64::NEG_INFINITY);
+        assert_eq!(logit(1_f64), f64::INFINITY);
+        assert!(logit(1.5_f64).is_nan());
+        assert!(logit(-0.5_f64).is_nan());
+
+        let eps = 1e-7_f64;
+        assert_close!(logit_clamped(1.0, eps), logit(1.0 - eps));
+        assert_close!(logit_clamped(0.0, eps), logit(eps));
+        assert_close!(logit_clamped(2.0, eps), logit(1.0 - eps));
+        assert_eq!(logit_clamped(0.5, eps), 0.0);
+        assert!(logit_clamped(f64::NAN, eps).is_nan());
+
+        assert_eq!(checked_logit(0.5), Ok(0.0));
+        assert_eq!(checked_logit(1.0), Ok(f64::INFINITY));
+        assert_eq!(checked_logit(1.5), Err(NotAProbability { value: 1.5 }));
+        assert!(checked_logit(f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! `ln_sum_exp` with signed scaling coefficients
+use super::math::ln;
+use super::{LogSubExp, StreamingLnSumExp};
+use num_traits::Float;
+
+/// Compute the log of the absolute value of `Σ scales[i] * exp(values[i])` and its sign
+///
+/// This matches scipy's `logsumexp(a, b=scales, return_sign=True)`. The positive and negative
+/// terms are each accumulated like [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] and then
+/// subtracted with [`ln_sub_exp`][LogSubExp::ln_sub_exp], so the result is accurate unless the
+/// sum cancels almost completely. The sign is one or negative one, or zero when the sum is exactly
+/// zero, in which case the log is negative infinity. NaN inputs, or positive and negative infinite
+/// terms, produce NaN for both.
+///
+/// # Panics
+///
+/// If `values` and `scales` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_signed;
+///
+/// let (ln_abs, sign) = ln_sum_exp_signed(&[1_f64.ln(), 3_f64.ln()], &[2.0, -1.0]);
+/// // ln_abs == 1_f64.ln(), sign == -1.0
+/// ```
+pub fn ln_sum_exp_signed<T>(values: &[T], scales: &[T]) -> (T, T)
+where
+    T: Float,
+{
+    assert_eq!(
+        values.len(),
+        scales.len(),
+        "values and scales must have the same length"
+    );
+    ln_sum_exp_signed_iter(values.iter().copied().zip(scales.iter().copied()))
+}
+
+/// [`ln_sum_exp_signed`] over `(value, scale)` pairs
+pub(crate) fn ln_sum_exp_signed_iter<T>(terms: impl IntoIterator<Item = (T, T)>) -> (T, T)
+where
+    T: Float,
+{
+    let mut pos = StreamingLnSumExp::new();
+    let mut neg = StreamingLnSumExp::new();
+    for (val, scale) in terms {
+        if scale < T::zero() {
+            neg.push(val + ln(-scale));
+        } else {
+            pos.push(val + ln(scale));
+        }
+    }
+    let pos = pos.ln_sum_exp();
+    let neg = neg.ln_sum_exp();
+    let (total, sign) = if pos >= neg {
+        (pos.ln_sub_exp(neg), T::one())
+    } else if neg > pos {
+        (neg.ln_sub_exp(pos), -T::one())
+    } else {
+        (T::nan(), T::nan())
+    };
+    if total == T::neg_infinity() {
+        (total, T::zero())
+    } else if total.is_nan() {
+        (total, T::nan())
+    } else {
+        (total, sign)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ln_sum_exp_signed;
+
+    #[test]
+    fn test_ln_sum_exp_signed() {
+        let vals = [1.0, 2.0, 3.0];
+        let (total, sign) = ln_sum_exp_signed(&vals, &[1.0, 1.0, -1.0]);
+        let expected = 3_f64.exp() - 2_f64.exp() - 1_f64.exp();
+        assert_close!(total, expected.ln());
+        assert_eq!(sign, -1.0);
+
+        let (total, sign) = ln_sum_exp_signed(&vals, &[-1.0, 1.0, 0.0]);
+        assert_close!(total, (2_f64.exp() - 1_f64.exp()).ln());
+        assert_eq!(sign, 1.0);
+
+        // far outside the range of exp
+        let (total, sign) = ln_sum_exp_signed(&[-2000_f32, -2000.0], &[3.0, -1.0]);
+        assert_close!(total, -2000.0 + 2_f32.ln());
+        assert_eq!(sign, 1.0);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_signed_special() {
+        assert_eq!(ln_sum_exp_signed::<f64>(&[], &[]), (f64::NEG_INFINITY, 0.0));
+        assert_eq!(
+            ln_sum_exp_signed(&[1.0, 1.0], &[2.0, -2.0]),
+            (f64::NEG_INFINITY, 0.0)
+        );
+        assert_eq!(ln_sum_exp_signed(&[1.0], &[0.0]), (f64::NEG_INFINITY, 0.0));
+        let (total, sign) = ln_sum_exp_signed(&[f64::NAN], &[1.0]);
+        assert!(total.is_nan() && sign.is_nan());
+        let (total, sign) = ln_sum_exp_signed(&[f64::INFINITY; 2], &[1.0, -1.0]);
+        assert!(total.is_nan() && sign.is_nan());
+        assert_eq!(
+            ln_sum_exp_signed(&[f64::INFINITY, 1.0], &[-1.0, 1.0]),
+            (f64::INFINITY, -1.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "values and scales must have the same length")]
+    fn test_ln_sum_exp_signed_mismatch() {
+        let _ = ln_sum_exp_signed(&[1.0, 2.0], &[1.0]);
+    }
+}
@@ -0,0 +1,170 @@
+//! `f64` slice kernels vectorized with wasm SIMD128
+//!
+//! These are only compiled for `wasm32` when the `simd128` target feature is enabled at build
+//! time, e.g. with `RUSTFLAGS="-C target-feature=+simd128"`. Every browser that supports wasm
+//! has supported SIMD128 for years, but it still has to be opted into when compiling.
+//!
+//! The scalar kernels can't be vectorized by the compiler since wasm doesn't have a vector `exp`,
+//! so this evaluates `exp` with a polynomial on two lanes at a time.
+use super::ln_sum_exp_slice;
+use super::math::{exp, ln};
+use core::arch::wasm32::{
+    f64x2, f64x2_add, f64x2_extract_lane, f64x2_lt, f64x2_mul, f64x2_nearest, f64x2_pmax,
+    f64x2_splat, f64x2_sub, i32x4_trunc_sat_f64x2_zero, i64x2_add, i64x2_extend_low_i32x4,
+    i64x2_shl, i64x2_splat, v128, v128_andnot,
+};
+
+/// Number of values processed per iteration, as two vectors to hide latency
+const WIDTH: usize = 4;
+
+/// The smallest value whose `exp` is still normal
+const MIN_NORMAL_EXP: f64 = -708.0;
+
+/// The high bits of `ln(2)`, so that `k * LN_2_HI` is exact for the exponents we need
+const LN_2_HI: f64 = 6.931_471_803_691_238e-1;
+/// The rest of `ln(2)`
+const LN_2_LO: f64 = 1.908_214_929_270_587_7e-10;
+
+/// Compute `exp(x)` for each lane of nonpositive `x`
+///
+/// This reduces `x = k ln(2) + r` with `|r| <= ln(2) / 2`, evaluates the Taylor series of
+/// `exp(r)` to degree 13, and scales by `2^k` through the exponent bits. Lanes whose result would
+/// be subnormal are flushed to zero, which is far below the rounding error of any sum that also
+/// contains `exp(0)`.
+fn exp_nonpositive(x: v128) -> v128 {
+    let k = f64x2_nearest(f64x2_mul(x, f64x2_splat(std::f64::consts::LOG2_E)));
+    let r = f64x2_sub(
+        f64x2_sub(x, f64x2_mul(k, f64x2_splat(LN_2_HI))),
+        f64x2_mul(k, f64x2_splat(LN_2_LO)),
+    );
+    // horner's method on 1 + r (1 + r / 2 (1 + r / 3 (...)))
+    let mut poly = f64x2_splat(1.0);
+    for n in (1..=13).rev() {
+        poly = f64x2_add(
+            f64x2_splat(1.0),
+            f64x2_mul(r, f64x2_mul(poly, f64x2_splat(1.0 / f64::from(n)))),
+        );
+    }
+    let exp = i64x2_shl(
+        i64x2_add(
+            i64x2_extend_low_i32x4(i32x4_trunc_sat_f64x2_zero(k)),
+            i64x2_splat(1023),
+        ),
+        52,
+    );
+    let res = f64x2_mul(poly, exp);
+    // lt is false for NaN, so NaN is left in place
+    v128_andnot(res, f64x2_lt(x, f64x2_splat(MIN_NORMAL_EXP)))
+}
+
+/// The sum of the lanes of two vectors
+fn horizontal_sum(a: v128, b: v128) -> f64 {
+    let sum = f64x2_add(a, b);
+    f64x2_extract_lane::<0>(sum) + f64x2_extract_lane::<1>(sum)
+}
+
+/// The maximum of a slice ignoring NaN, or negative infinity if there are no other values
+pub(crate) fn slice_max(values: &[f64]) -> f64 {
+    let mut lo = f64x2_splat(f64::NEG_INFINITY);
+    let mut hi = lo;
+    let chunks = values.chunks_exact(WIDTH);
+    let rem = chunks.remainder();
+    for chunk in chunks {
+        // pmax keeps the accumulator when the value is NaN, the same as the scalar kernel
+        lo = f64x2_pmax(lo, f64x2(chunk[0], chunk[1]));
+        hi = f64x2_pmax(hi, f64x2(chunk[2], chunk[3]));
+    }
+    let max = f64x2_pmax(lo, hi);
+    rem.iter().fold(
+        f64x2_extract_lane::<0>(max).max(f64x2_extract_lane::<1>(max)),
+        |max, &val| if val > max { val } else { max },
+    )
+}
+
+/// The sum of `exp(val - shift)` over a slice where `shift` is at least every value
+pub(crate) fn slice_sum_exp(values: &[f64], shift: f64) -> f64 {
+    let shift_vec = f64x2_splat(shift);
+    let mut lo = f64x2_splat(0.0);
+    let mut hi = lo;
+    let chunks = values.chunks_exact(WIDTH);
+    let rem = chunks.remainder();
+    for chunk in chunks {
+        lo = f64x2_add(
+            lo,
+            exp_nonpositive(f64x2_sub(f64x2(chunk[0], chunk[1]), shift_vec)),
+        );
+        hi = f64x2_add(
+            hi,
+            exp_nonpositive(f64x2_sub(f64x2(chunk[2], chunk[3]), shift_vec)),
+        );
+    }
+    rem.iter()
+        .fold(horizontal_sum(lo, hi), |sum, &val| sum + exp(val - shift))
+}
+
+/// Compute `ln_sum_exp` of a slice
+pub(crate) fn ln_sum_exp(values: &[f64]) -> f64 {
+    let max = slice_max(values);
+    if max.is_finite() {
+        ln(slice_sum_exp(values, max)) + max
+    } else {
+        ln_sum_exp_slice(values)
+    }
+}
+
+/// Replace every value with `exp(val - shift)` where `shift` is at least every value
+pub(crate) fn exp_in_place(values: &mut [f64], shift: f64) {
+    let shift_vec = f64x2_splat(shift);
+    let mut chunks = values.chunks_exact_mut(2);
+    for chunk in &mut chunks {
+        let res = exp_nonpositive(f64x2_sub(f64x2(chunk[0], chunk[1]), shift_vec));
+        chunk[0] = f64x2_extract_lane::<0>(res);
+        chunk[1] = f64x2_extract_lane::<1>(res);
+    }
+    for val in chunks.into_remainder() {
+        *val = exp(*val - shift);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{exp_in_place, ln_sum_exp, slice_max};
+    use crate::ln_sum_exp_slice;
+
+    #[test]
+    fn test_exp() {
+        let mut vals: Vec<f64> = (0..2000).map(|n| f64::from(n) * -0.37).collect();
+        let expected: Vec<f64> = vals.iter().map(|val| val.exp()).collect();
+        exp_in_place(&mut vals, 0.0);
+        for (&res, &exp) in vals.iter().zip(&expected) {
+            if exp.is_normal() {
+                assert_close!(res, exp, rtol = 1e-15);
+            } else {
+                assert_eq!(res, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ln_sum_exp() {
+        assert_eq!(ln_sum_exp(&[]), f64::NEG_INFINITY);
+        for len in 1..20 {
+            let vals: Vec<f64> = (0..len).map(|n| f64::from(n).sin() * 100.0).collect();
+            assert_eq!(
+                slice_max(&vals),
+                vals.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+            );
+            assert_close!(ln_sum_exp(&vals), ln_sum_exp_slice(&vals));
+        }
+
+        let mut vals = vec![f64::NEG_INFINITY; 7];
+        assert_eq!(ln_sum_exp(&vals), f64::NEG_INFINITY);
+        vals[2] = f64::NAN;
+        assert!(ln_sum_exp(&vals).is_nan());
+        vals[5] = 1.0;
+        assert!(ln_sum_exp(&vals).is_nan());
+        vals[1] = f64::INFINITY;
+        assert_eq!(ln_sum_exp(&vals), f64::INFINITY);
+    }
+}
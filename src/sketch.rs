@@ -0,0 +1,230 @@
+//! A bounded-memory `ln_sum_exp` with a runtime error bound
+use super::math::{cast, exp, ln, ln_1p};
+use super::truncated::Ordered;
+use super::StreamingLnSumExp;
+use num_traits::Float;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// An accumulator of `ln_sum_exp` over an unbounded stream with fixed memory and an error bound
+///
+/// The `k` largest values are kept exactly and only summed when queried, and every other value is
+/// folded into a remainder that uses compensated summation, so rounding error doesn't grow with
+/// the length of the stream. The remainder only loses precision when a value larger than every
+/// previously evicted value forces it to be rescaled, and since the largest values are kept
+/// exactly, that error is further scaled by the fraction of the mass in the remainder. Larger `k`
+/// trades memory and query time for accuracy, and [`error_bound`][Self::error_bound] reports a
+/// first order bound on the absolute error of the result for the values seen so far.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnSumExpSketch;
+///
+/// let mut sketch = LnSumExpSketch::new(8);
+/// for _ in 0..1_000_000 {
+///     sketch.push(0.0_f32);
+/// }
+/// sketch.ln_sum_exp(); // 1e6_f32.ln()
+/// assert!(sketch.error_bound() < 1e-5);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LnSumExpSketch<T> {
+    k: usize,
+    top: BinaryHeap<Reverse<Ordered<T>>>,
+    nan: bool,
+    /// the largest evicted value
+    shift: T,
+    /// compensated sum of `exp(val - shift)` over evicted values
+    sum: T,
+    comp: T,
+    /// the number of times the remainder was rescaled after it was nonempty
+    rescales: usize,
+    count: usize,
+}
+
+impl<T> LnSumExpSketch<T>
+where
+    T: Float,
+{
+    /// Create a new empty sketch that keeps the `k` largest values exactly
+    #[must_use]
+    pub fn new(k: usize) -> Self {
+        LnSumExpSketch {
+            k,
+            top: BinaryHeap::with_capacity(k + 1),
+            nan: false,
+            shift: T::neg_infinity(),
+            sum: T::zero(),
+            comp: T::zero(),
+            rescales: 0,
+            count: 0,
+        }
+    }
+
+    /// The number of values pushed
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// True if no values have been pushed
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Add a value to the sketch
+    pub fn push(&mut self, value: T) {
+        self.count += 1;
+        if value.is_nan() {
+            self.nan = true;
+            return;
+        }
+        self.top.push(Reverse(Ordered(value)));
+        if self.top.len() > self.k {
+            if let Some(Reverse(Ordered(smallest))) = self.top.pop() {
+                self.evict(smallest);
+            }
+        }
+    }
+
+    /// Fold a value into the compensated remainder
+    fn evict(&mut self, value: T) {
+        let term = if value == self.shift {
+            T::one()
+        } else if value > self.shift {
+            let scale = exp(self.shift - value);
+            if self.sum != T::zero() {
+                self.rescales += 1;
+            }
+            self.sum = self.sum * scale;
+            self.comp = self.comp * scale;
+            self.shift = value;
+            T::one()
+        } else {
+            exp(value - self.shift)
+        };
+        // Neumaier's variant of Kahan summation, which also handles terms larger than the sum
+        let sum = self.sum + term;
+        self.comp = if self.sum.abs() >= term.abs() {
+            self.comp + ((self.sum - sum) + term)
+        } else {
+            self.comp + ((term - sum) + self.sum)
+        };
+        self.sum = sum;
+    }
+
+    /// The `ln_sum_exp` of the evicted values
+    fn ln_rest(&self) -> T {
+        if self.sum == T::zero() {
+            T::neg_infinity()
+        } else if self.shift.is_infinite() {
+            self.shift
+        } else {
+            self.shift + ln(self.sum + self.comp)
+        }
+    }
+
+    /// The approximate `ln_sum_exp` of every value pushed
+    ///
+    /// An empty sketch returns negative infinity, and if any value was NaN the result is NaN.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        let mut acc: StreamingLnSumExp<T> =
+            self.top.iter().map(|&Reverse(Ordered(val))| val).collect();
+        acc.push(self.ln_rest());
+        let res = acc.ln_sum_exp();
+        if self.nan && res != T::infinity() {
+            T::nan()
+        } else {
+            res
+        }
+    }
+
+    /// An upper bound on the absolute error of [`ln_sum_exp`][Self::ln_sum_exp]
+    ///
+    /// If the exponentials of the result have a relative error of at most `δ`, the result is off
+    /// by at most `-ln(1 - δ)`. To first order in machine epsilon `ε`, the `k` exact values and
+    /// the final combination contribute `(k + 3)ε`, and the remainder contributes
+    /// `(rescales + 3)ε` scaled by its fraction of the total mass. Infinite and NaN results
+    /// report zero.
+    #[must_use]
+    pub fn error_bound(&self) -> T {
+        let total = self.ln_sum_exp();
+        if !total.is_finite() {
+            return T::zero();
+        }
+        let three = cast::<T>(3);
+        let frac = exp(self.ln_rest() - total).min(T::one());
+        let exact = cast::<T>(self.k.min(self.count)) + three;
+        let rest = cast::<T>(self.rescales) + three;
+        let delta = T::epsilon() * (exact + frac * rest);
+        if delta < T::one() {
+            -ln_1p(-delta)
+        } else {
+            T::infinity()
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LnSumExpSketch;
+    use crate::LogSumExp;
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)]
+    fn test_sketch() {
+        // values in f32 against an f64 reference
+        let vals: Vec<f64> = (0..100_000)
+            .map(|n| f64::from(f64::from(n).sin() as f32) * 20.0)
+            .collect();
+        let expected = vals.iter().copied().ln_sum_exp();
+        for k in [0, 1, 16, 256] {
+            let mut sketch = LnSumExpSketch::new(k);
+            for &val in &vals {
+                sketch.push(val as f32);
+            }
+            assert_eq!(sketch.len(), vals.len());
+            let err = (f64::from(sketch.ln_sum_exp()) - expected).abs();
+            assert!(err <= f64::from(sketch.error_bound()), "k = {k}");
+        }
+
+        // increasing values rescale the remainder on every push
+        let mut sketch = LnSumExpSketch::new(4);
+        for n in 0..1000 {
+            sketch.push(f64::from(n) / 100.0);
+        }
+        let expected = (0..1000).map(|n| f64::from(n) / 100.0).ln_sum_exp();
+        assert!((sketch.ln_sum_exp() - expected).abs() <= sketch.error_bound());
+    }
+
+    #[test]
+    fn test_sketch_special() {
+        let mut sketch = LnSumExpSketch::new(2);
+        assert!(sketch.is_empty());
+        assert_eq!(sketch.ln_sum_exp(), f64::NEG_INFINITY);
+        sketch.push(f64::NEG_INFINITY);
+        assert_eq!(sketch.ln_sum_exp(), f64::NEG_INFINITY);
+        for _ in 0..3 {
+            sketch.push(f64::INFINITY);
+        }
+        sketch.push(1.0);
+        assert_eq!(sketch.ln_sum_exp(), f64::INFINITY);
+        assert_eq!(sketch.error_bound(), 0.0);
+        sketch.push(f64::NAN);
+        assert_eq!(sketch.ln_sum_exp(), f64::INFINITY);
+
+        let mut sketch = LnSumExpSketch::new(2);
+        sketch.push(1.0);
+        sketch.push(f64::NAN);
+        assert!(sketch.ln_sum_exp().is_nan());
+
+        let mut sketch = LnSumExpSketch::new(0);
+        sketch.push(0.0);
+        sketch.push(0.0);
+        assert_close!(sketch.ln_sum_exp(), 2_f64.ln());
+    }
+}
@@ -0,0 +1,112 @@
+//! Kernels for computing over contiguous slices
+//!
+//! These work on fixed width lanes of independent accumulators so that the compiler can vectorize
+//! the max and sum passes, which a single running accumulator would serialize. Each value always
+//! lands in the same lane and the lanes are combined in a fixed order, so the result doesn't
+//! depend on whether or how the loops get vectorized.
+use super::math::{exp, ln};
+use super::LogAddExp;
+use num_traits::Float;
+
+/// Number of independent accumulators used by the slice kernels
+pub(crate) const LANES: usize = 8;
+
+/// The maximum of a slice ignoring NaN, or negative infinity if there are no other values
+///
+/// This stops early once it finds positive infinity.
+pub(crate) fn slice_max<T: Float>(values: &[T]) -> T {
+    let mut lanes = [T::neg_infinity(); LANES];
+    let chunks = values.chunks_exact(LANES);
+    let rem = chunks.remainder();
+    for chunk in chunks {
+        for (lane, &val) in lanes.iter_mut().zip(chunk) {
+            if val > *lane {
+                *lane = val;
+            }
+        }
+        if lanes.contains(&T::infinity()) {
+            return T::infinity();
+        }
+    }
+    for (lane, &val) in lanes.iter_mut().zip(rem) {
+        if val > *lane {
+            *lane = val;
+        }
+    }
+    lanes.into_iter().fold(T::neg_infinity(), T::max)
+}
+
+/// The sum of `exp(val - shift)` over a slice
+pub(crate) fn slice_sum_exp<T: Float>(values: &[T], shift: T) -> T {
+    let mut lanes = [T::zero(); LANES];
+    let chunks = values.chunks_exact(LANES);
+    let rem = chunks.remainder();
+    for chunk in chunks {
+        for (lane, &val) in lanes.iter_mut().zip(chunk) {
+            *lane = *lane + exp(val - shift);
+        }
+    }
+    for (lane, &val) in lanes.iter_mut().zip(rem) {
+        *lane = *lane + exp(val - shift);
+    }
+    lanes.into_iter().fold(T::zero(), |a, b| a + b)
+}
+
+/// Compute `ln_sum_exp` of a slice
+///
+/// This produces the same result as [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] on the slice's
+/// iterator, but is laid out so the max and sum passes can be vectorized. Slices of two values
+/// use [`ln_add_exp`][LogAddExp::ln_add_exp] directly.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_slice;
+/// ln_sum_exp_slice(&[1.0, 2.0, 4.0]);
+/// ```
+#[must_use]
+pub fn ln_sum_exp_slice<T>(values: &[T]) -> T
+where
+    T: Float,
+{
+    if let &[first, second] = values {
+        return first.ln_add_exp(second);
+    }
+    let max = slice_max(values);
+    if max.is_finite() {
+        ln(slice_sum_exp(values, max)) + max
+    } else if max == T::neg_infinity() && values.iter().any(|val| val.is_nan()) {
+        T::nan()
+    } else {
+        max
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::ln_sum_exp_slice;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_slice() {
+        for len in 0..30 {
+            let vals: Vec<f64> = (0..len).map(|n| f64::from(n).sin() * 10.0).collect();
+            let expected = vals.iter().copied().ln_sum_exp();
+            if len == 0 {
+                assert_eq!(ln_sum_exp_slice(&vals), f64::NEG_INFINITY);
+            } else {
+                assert_close!(ln_sum_exp_slice(&vals), expected);
+            }
+        }
+
+        assert_eq!(ln_sum_exp_slice(&[f64::NEG_INFINITY; 9]), f64::NEG_INFINITY);
+        assert_eq!(ln_sum_exp_slice(&[1.0, f64::INFINITY]), f64::INFINITY);
+        assert!(ln_sum_exp_slice(&[f64::NAN, 1.0]).is_nan());
+        assert!(ln_sum_exp_slice(&[f64::NAN; 3]).is_nan());
+        assert_eq!(
+            ln_sum_exp_slice(&[f64::INFINITY, f64::NAN]),
+            ln_sum_exp_slice(&[f64::INFINITY, f64::NAN, 0.0])
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Normalizing slices of log values
+use super::ln_sum_exp_slice;
+use super::math::{exp, ln};
+use super::slice::{slice_max, LANES};
+use num_traits::Float;
+
+/// Write `exp(val - shift)` for every value into `out` and return their sum
+fn exp_into<T: Float>(values: &[T], shift: T, out: &mut Vec<T>) -> T {
+    out.extend(values.iter().map(|&val| exp(val - shift)));
+    let mut lanes = [T::zero(); LANES];
+    for chunk in out.chunks(LANES) {
+        for (lane, &val) in lanes.iter_mut().zip(chunk) {
+            *lane = *lane + val;
+        }
+    }
+    lanes.into_iter().fold(T::zero(), |a, b| a + b)
+}
+
+/// Compute `ln_sum_exp` of a slice along with the normalized log values
+///
+/// This returns `(ln_sum_exp, log_probs)` where `log_probs[i] = values[i] - ln_sum_exp`. It
+/// makes one max pass and one exp pass over the data, instead of computing `ln_sum_exp` and then
+/// normalizing.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_normalize_collect;
+///
+/// let (total, log_probs) = ln_normalize_collect(&[0.0, 0.0]);
+/// // total == 2_f64.ln(), log_probs == [0.5_f64.ln(); 2]
+/// ```
+#[must_use]
+pub fn ln_normalize_collect<T>(values: &[T]) -> (T, Vec<T>)
+where
+    T: Float,
+{
+    let max = slice_max(values);
+    if max.is_finite() {
+        let mut out = Vec::with_capacity(values.len());
+        let total = ln(exp_into(values, max, &mut out)) + max;
+        for (res, &val) in out.iter_mut().zip(values) {
+            *res = val - total;
+        }
+        (total, out)
+    } else {
+        let total = ln_sum_exp_slice(values);
+        (total, values.iter().map(|&val| val - total).collect())
+    }
+}
+
+/// Compute `ln_sum_exp` of a slice along with the normalized probabilities
+///
+/// This returns `(ln_sum_exp, probs)` where `probs[i] = (values[i] - ln_sum_exp).exp()`, which is
+/// the softmax of the values. Like [`ln_normalize_collect`] this only makes one max pass and one
+/// exp pass.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::softmax_collect;
+///
+/// let (total, probs) = softmax_collect(&[0.0, 0.0]);
+/// // total == 2_f64.ln(), probs == [0.5; 2]
+/// ```
+#[must_use]
+pub fn softmax_collect<T>(values: &[T]) -> (T, Vec<T>)
+where
+    T: Float,
+{
+    let max = slice_max(values);
+    if max.is_finite() {
+        let mut out = Vec::with_capacity(values.len());
+        let sum = exp_into(values, max, &mut out);
+        for res in &mut out {
+            *res = *res / sum;
+        }
+        (ln(sum) + max, out)
+    } else {
+        let total = ln_sum_exp_slice(values);
+        (total, values.iter().map(|&val| exp(val - total)).collect())
+    }
+}
+
+/// Update a log prior with a log likelihood in place and return the log evidence
+///
+/// This adds `log_likelihood` to `log_prior` element-wise and normalizes the result with
+/// [`ln_sum_exp_slice`], so afterwards `log_prior` holds the log posterior. The returned log
+/// evidence, `ln Σ prior × likelihood`, is the log marginal likelihood of the observation, so
+/// summing it over a sequence of updates gives the log likelihood of the whole sequence. It
+/// doesn't allocate, which suits grid filters and discrete trackers that update every tick.
+///
+/// If the observation is impossible under every state the evidence is negative infinity and the
+/// posterior is NaN.
+///
+/// # Panics
+///
+/// If `log_prior` and `log_likelihood` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::bayes_update;
+///
+/// let mut log_belief = [0.5_f64.ln(), 0.5_f64.ln()];
+/// let evidence = bayes_update(&mut log_belief, &[0.9_f64.ln(), 0.3_f64.ln()]); // 0.6_f64.ln()
+/// // log_belief == [0.75_f64.ln(), 0.25_f64.ln()]
+/// ```
+pub fn bayes_update<T>(log_prior: &mut [T], log_likelihood: &[T]) -> T
+where
+    T: Float,
+{
+    assert_eq!(
+        log_prior.len(),
+        log_likelihood.len(),
+        "prior and likelihood must have the same length"
+    );
+    for (prior, &like) in log_prior.iter_mut().zip(log_likelihood) {
+        *prior = *prior + like;
+    }
+    let evidence = ln_sum_exp_slice(log_prior);
+    for val in log_prior.iter_mut() {
+        *val = *val - evidence;
+    }
+    evidence
+}
+
+/// The expectation of `vals` under the distribution with log probabilities `log_probs`
+fn expectation<T: Float>(log_probs: &[T], vals: &[T]) -> T {
+    log_probs
+        .iter()
+        .zip(vals)
+        .fold(T::zero(), |acc, (&log_prob, &val)| {
+            acc + exp(log_prob) * val
+        })
+}
+
+/// Compute the Jacobian-vector product of softmax
+///
+/// For `log_probs` produced by log softmax, and a `tangent` of its inputs, this returns the
+/// directional derivative of the probabilities, `p[i] * (tangent[i] - Σ p[j] * tangent[j])` with
+/// `p = exp(log_probs)`, without materializing the Jacobian. The softmax Jacobian is symmetric,
+/// so this is the same as [`softmax_vjp`].
+///
+/// # Panics
+///
+/// If `log_probs` and `tangent` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::softmax_jvp;
+///
+/// let log_probs = [0.25_f64.ln(), 0.75_f64.ln()];
+/// softmax_jvp(&log_probs, &[1.0, 0.0]); // [0.1875, -0.1875]
+/// ```
+#[must_use]
+pub fn softmax_jvp<T>(log_probs: &[T], tangent: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        log_probs.len(),
+        tangent.len(),
+        "log_probs and tangent must have the same length"
+    );
+    let mean = expectation(log_probs, tangent);
+    log_probs
+        .iter()
+        .zip(tangent)
+        .map(|(&log_prob, &tan)| exp(log_prob) * (tan - mean))
+        .collect()
+}
+
+/// Compute the vector-Jacobian product of softmax
+///
+/// For `log_probs` produced by log softmax, and a `cotangent` of the probabilities, this returns
+/// the gradient with respect to the inputs, `p[i] * (cotangent[i] - Σ p[j] * cotangent[j])` with
+/// `p = exp(log_probs)`, which is the backward pass of softmax.
+///
+/// # Panics
+///
+/// If `log_probs` and `cotangent` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::softmax_vjp;
+///
+/// let log_probs = [0.25_f64.ln(), 0.75_f64.ln()];
+/// softmax_vjp(&log_probs, &[0.0, 1.0]); // [-0.1875, 0.1875]
+/// ```
+#[must_use]
+pub fn softmax_vjp<T>(log_probs: &[T], cotangent: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        log_probs.len(),
+        cotangent.len(),
+        "log_probs and cotangent must have the same length"
+    );
+    softmax_jvp(log_probs, cotangent)
+}
+
+/// Compute the Jacobian-vector product of log softmax
+///
+/// For `log_probs` produced by log softmax, and a `tangent` of its inputs, this returns the
+/// directional derivative of the log probabilities, `tangent[i] - Σ p[j] * tangent[j]` with
+/// `p = exp(log_probs)`.
+///
+/// # Panics
+///
+/// If `log_probs` and `tangent` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::log_softmax_jvp;
+///
+/// let log_probs = [0.25_f64.ln(), 0.75_f64.ln()];
+/// log_softmax_jvp(&log_probs, &[1.0, 0.0]); // [0.75, -0.25]
+/// ```
+#[must_use]
+pub fn log_softmax_jvp<T>(log_probs: &[T], tangent: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        log_probs.len(),
+        tangent.len(),
+        "log_probs and tangent must have the same length"
+    );
+    let mean = expectation(log_probs, tangent);
+    tangent.iter().map(|&tan| tan - mean).collect()
+}
+
+/// Compute the vector-Jacobian product of log softmax
+///
+/// For `log_probs` produced by log softmax, and a `cotangent` of the log probabilities, this
+/// returns the gradient with respect to the inputs, `cotangent[i] - p[i] * Σ cotangent[j]` with
+/// `p = exp(log_probs)`, which is the backward pass of log softmax.
+///
+/// # Panics
+///
+/// If `log_probs` and `cotangent` have different lengths.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::log_softmax_vjp;
+///
+/// let log_probs = [0.25_f64.ln(), 0.75_f64.ln()];
+/// log_softmax_vjp(&log_probs, &[1.0, 0.0]); // [0.75, -0.75]
+/// ```
+#[must_use]
+pub fn log_softmax_vjp<T>(log_probs: &[T], cotangent: &[T]) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        log_probs.len(),
+        cotangent.len(),
+        "log_probs and cotangent must have the same length"
+    );
+    let total = cotangent.iter().fold(T::zero(), |acc, &cot| acc + cot);
+    log_probs
+        .iter()
+        .zip(cotangent)
+        .map(|(&log_prob, &cot)| cot - exp(log_prob) * total)
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        bayes_update, ln_normalize_collect, log_softmax_jvp, log_softmax_vjp, softmax_collect,
+        softmax_jvp, softmax_vjp,
+    };
+    use crate::{LogAddExp, LogSumExp};
+
+    #[test]
+    fn test_ln_normalize_collect() {
+        let vals: Vec<f64> = (0..20).map(|n| f64::from(n).sin() * 100.0).collect();
+        let expected = vals.iter().copied().ln_sum_exp();
+
+        let (total, log_probs) = ln_normalize_collect(&vals);
+        assert_close!(total, expected);
+        assert_close!(log_probs.iter().copied().ln_sum_exp(), 0.0);
+        for (&log_prob, &val) in log_probs.iter().zip(&vals) {
+            assert_close!(log_prob, val - expected);
+        }
+
+        let (total, probs) = softmax_collect(&vals);
+        assert_close!(total, expected);
+        assert_close!(probs.iter().sum::<f64>(), 1.0);
+        for (&prob, &val) in probs.iter().zip(&vals) {
+            assert_close!(prob, (val - expected).exp());
+        }
+    }
+
+    #[test]
+    fn test_ln_normalize_collect_edge() {
+        let (total, log_probs) = ln_normalize_collect::<f64>(&[]);
+        assert_eq!(total, f64::NEG_INFINITY);
+        assert!(log_probs.is_empty());
+
+        let (total, log_probs) = ln_normalize_collect(&[f64::NEG_INFINITY, 0.0]);
+        assert_eq!(total, 0.0);
+        assert_eq!(log_probs, [f64::NEG_INFINITY, 0.0]);
+
+        let (total, probs) = softmax_collect(&[f64::NEG_INFINITY, 0.0]);
+        assert_eq!(total, 0.0);
+        assert_eq!(probs, [0.0, 1.0]);
+
+        let (total, probs) = softmax_collect(&[f64::INFINITY, 0.0]);
+        assert_eq!(total, f64::INFINITY);
+        assert!(probs[0].is_nan());
+        assert_eq!(probs[1], 0.0);
+    }
+
+    #[test]
+    fn test_bayes_update() {
+        let mut belief = [0.5_f64.ln(), 0.5_f64.ln()];
+        let evidence = bayes_update(&mut belief, &[0.9_f64.ln(), 0.3_f64.ln()]);
+        assert_close!(evidence, 0.6_f64.ln());
+        assert_close!(belief[0], 0.75_f64.ln());
+        assert_close!(belief[1], 0.25_f64.ln());
+
+        // repeated updates accumulate the log likelihood of the sequence
+        let mut belief = [0.0; 3];
+        let mut total = bayes_update(&mut belief, &[0.0; 3]);
+        for _ in 0..1000 {
+            total += bayes_update(&mut belief, &[-1.0, -2.0, f64::NEG_INFINITY]);
+        }
+        assert_close!(
+            total,
+            3_f64.ln() + (-1000_f64).ln_add_exp(-2000.0) - 3_f64.ln()
+        );
+        assert_close!(belief[0], 0.0, atol = 1e-12);
+        assert_eq!(belief[2], f64::NEG_INFINITY);
+
+        let mut belief = [0.0, f64::NEG_INFINITY];
+        let evidence = bayes_update(&mut belief, &[f64::NEG_INFINITY, 0.0]);
+        assert_eq!(evidence, f64::NEG_INFINITY);
+        assert!(belief.iter().all(|val| val.is_nan()));
+    }
+
+    /// The Jacobian of softmax or log softmax at `vals` with central differences
+    fn jacobian(vals: &[f64], log: bool) -> Vec<Vec<f64>> {
+        let eps = 1e-6;
+        let out = |vals: &[f64]| {
+            let (_, log_probs) = ln_normalize_collect(vals);
+            if log {
+                log_probs
+            } else {
+                log_probs.iter().map(|lp| lp.exp()).collect()
+            }
+        };
+        (0..vals.len())
+            .map(|j| {
+                let mut up = vals.to_vec();
+                up[j] += eps;
+                let mut down = vals.to_vec();
+                down[j] -= eps;
+                out(&up)
+                    .iter()
+                    .zip(out(&down))
+                    .map(|(u, d)| (u - d) / (2.0 * eps))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_softmax_jvp_vjp() {
+        let vals = [0.3, -1.2, 2.0, 0.5];
+        let vec = [1.0, -2.0, 0.5, 3.0];
+        let (_, log_probs) = ln_normalize_collect(&vals);
+        for (log, jvp, vjp) in [
+            (
+                false,
+                softmax_jvp(&log_probs, &vec),
+                softmax_vjp(&log_probs, &vec),
+            ),
+            (
+                true,
+                log_softmax_jvp(&log_probs, &vec),
+                log_softmax_vjp(&log_probs, &vec),
+            ),
+        ] {
+            // jac[j][i] is the derivative of output i with respect to input j
+            let jac = jacobian(&vals, log);
+            for (i, &res) in jvp.iter().enumerate() {
+                let expected: f64 = (0..vals.len()).map(|j| jac[j][i] * vec[j]).sum();
+                assert_close!(res, expected, atol = 1e-8);
+            }
+            for (j, &res) in vjp.iter().enumerate() {
+                let expected: f64 = (0..vals.len()).map(|i| jac[j][i] * vec[i]).sum();
+                assert_close!(res, expected, atol = 1e-8);
+            }
+        }
+
+        // impossible outcomes have no sensitivity
+        let log_probs = [f64::NEG_INFINITY, 0.0];
+        assert_eq!(softmax_jvp(&log_probs, &[1.0, 1.0]), [0.0, 0.0]);
+        assert_eq!(log_softmax_vjp(&log_probs, &[1.0, 2.0]), [1.0, -1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "log_probs and cotangent must have the same length")]
+    fn test_softmax_vjp_mismatch() {
+        let _ = softmax_vjp(&[0.0], &[1.0, 2.0]);
+    }
+}
@@ -0,0 +1,185 @@
+//! `ln_sum_exp` and softmax over sparse log vectors
+use super::math::{cast, exp, ln};
+use super::{LogAddExp, StreamingLnSumExp};
+use num_traits::Float;
+
+/// The `ln_sum_exp` of entries with `len - count` implicit copies of `fill`
+fn with_fill<T>(acc: &StreamingLnSumExp<T>, count: usize, len: usize, fill: T) -> T
+where
+    T: Float,
+{
+    assert!(count <= len, "more entries than the length");
+    let implicit = len - count;
+    if implicit == 0 {
+        acc.ln_sum_exp()
+    } else {
+        let ln_count = ln(cast::<T>(implicit));
+        acc.ln_sum_exp().ln_add_exp(fill + ln_count)
+    }
+}
+
+/// Compute `ln_sum_exp` of a sparse vector whose missing entries are negative infinity
+///
+/// Entries are `(index, value)` pairs, and since missing entries contribute nothing, the index
+/// and length of the vector don't matter.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::sparse_ln_sum_exp;
+///
+/// sparse_ln_sum_exp([(3, 1.0), (1000, 2.0)]); // (1_f64.exp() + 2_f64.exp()).ln()
+/// ```
+pub fn sparse_ln_sum_exp<I, T>(entries: I) -> T
+where
+    I: IntoIterator<Item = (usize, T)>,
+    T: Float,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for (_, val) in entries {
+        acc.push(val);
+    }
+    acc.ln_sum_exp()
+}
+
+/// Compute `ln_sum_exp` of a sparse vector of length `len` whose missing entries are `fill`
+///
+/// Indices must be unique.
+///
+/// # Panics
+///
+/// If any index is not less than `len`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::sparse_ln_sum_exp_with_fill;
+///
+/// sparse_ln_sum_exp_with_fill([(0, 2_f64.ln())], 3, 0.0); // 4_f64.ln()
+/// ```
+pub fn sparse_ln_sum_exp_with_fill<I, T>(entries: I, len: usize, fill: T) -> T
+where
+    I: IntoIterator<Item = (usize, T)>,
+    T: Float,
+{
+    let mut acc = StreamingLnSumExp::new();
+    let mut count = 0;
+    for (index, val) in entries {
+        assert!(index < len, "index out of bounds");
+        acc.push(val);
+        count += 1;
+    }
+    with_fill(&acc, count, len, fill)
+}
+
+/// Compute the softmax of a sparse vector whose missing entries are negative infinity
+///
+/// The result has the same indices as `entries`, and missing entries still have probability
+/// zero.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::sparse_softmax;
+///
+/// sparse_softmax(&[(3, 0.0), (1000, 0.0)]); // [(3, 0.5), (1000, 0.5)]
+/// ```
+#[must_use]
+pub fn sparse_softmax<T>(entries: &[(usize, T)]) -> Vec<(usize, T)>
+where
+    T: Float,
+{
+    let total = sparse_ln_sum_exp(entries.iter().copied());
+    entries
+        .iter()
+        .map(|&(index, val)| (index, exp(val - total)))
+        .collect()
+}
+
+/// Compute the softmax of a sparse vector of length `len` whose missing entries are `fill`
+///
+/// This returns the probabilities of the explicit entries along with the probability of every
+/// missing entry. Indices must be unique.
+///
+/// # Panics
+///
+/// If any index is not less than `len`.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::sparse_softmax_with_fill;
+///
+/// let (probs, fill) = sparse_softmax_with_fill(&[(0, 2_f64.ln())], 3, 0.0);
+/// // probs == [(0, 0.5)], fill == 0.25
+/// ```
+#[must_use]
+pub fn sparse_softmax_with_fill<T>(
+    entries: &[(usize, T)],
+    len: usize,
+    fill: T,
+) -> (Vec<(usize, T)>, T)
+where
+    T: Float,
+{
+    let total = sparse_ln_sum_exp_with_fill(entries.iter().copied(), len, fill);
+    let probs = entries
+        .iter()
+        .map(|&(index, val)| (index, exp(val - total)))
+        .collect();
+    (probs, exp(fill - total))
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        sparse_ln_sum_exp, sparse_ln_sum_exp_with_fill, sparse_softmax, sparse_softmax_with_fill,
+    };
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_sparse_ln_sum_exp() {
+        let entries = [(1, 1.0), (4, -2.0), (7, 3.0_f64)];
+        let dense = [-1.0, 1.0, -1.0, -1.0, -2.0, -1.0, -1.0, 3.0, -1.0, -1.0];
+
+        assert_close!(
+            sparse_ln_sum_exp(entries),
+            [1.0, -2.0, 3.0].into_iter().ln_sum_exp()
+        );
+        assert_close!(
+            sparse_ln_sum_exp_with_fill(entries, 10, -1.0),
+            dense.into_iter().ln_sum_exp()
+        );
+        assert_close!(
+            sparse_ln_sum_exp_with_fill([(0, 1.0), (1, -2.0), (2, 3.0)], 3, -1.0_f64),
+            [1.0, -2.0, 3.0].into_iter().ln_sum_exp()
+        );
+        assert_eq!(
+            sparse_ln_sum_exp(Vec::<(usize, f64)>::new()),
+            f64::NEG_INFINITY
+        );
+        assert_close!(
+            sparse_ln_sum_exp_with_fill(Vec::new(), 1_000_000, 0.0_f64),
+            1e6_f64.ln()
+        );
+    }
+
+    #[test]
+    fn test_sparse_softmax() {
+        let entries = [(1, 1.0), (4, -2.0), (7, 3.0_f64)];
+        let probs = sparse_softmax(&entries);
+        assert_eq!(probs.len(), 3);
+        assert_close!(probs.iter().map(|&(_, p)| p).sum::<f64>(), 1.0);
+        assert_eq!(probs[2].0, 7);
+
+        let (probs, fill) = sparse_softmax_with_fill(&entries, 10, -1.0);
+        assert_close!(probs.iter().map(|&(_, p)| p).sum::<f64>() + 7.0 * fill, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_sparse_out_of_bounds() {
+        let _ = sparse_ln_sum_exp_with_fill([(3, 1.0)], 3, 0.0);
+    }
+}
@@ -0,0 +1,520 @@
+//! An accumulator for computing `ln_sum_exp` one value at a time
+use super::math::{exp, ln};
+use super::slice::{slice_max, slice_sum_exp};
+use num_traits::Float;
+
+/// An accumulator that computes `ln_sum_exp` of values pushed one at a time
+///
+/// The state is the running maximum and the sum of the exponentials shifted by that maximum, so
+/// values can be added without keeping them around and the result is as stable as
+/// [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp].
+///
+/// Previously pushed values can also be removed. Removal is a stable
+/// [`ln_sub_exp`][crate::LogSubExp::ln_sub_exp] against the running total, but subtracting most
+/// of the mass away leaves only the rounding error of the original terms, so the accumulator
+/// tracks how much cancellation has happened. Once [`needs_recompute`][Self::needs_recompute]
+/// returns true, [`recompute`][Self::recompute] from the live values to restore an exact state.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::StreamingLnSumExp;
+///
+/// let mut acc = StreamingLnSumExp::new();
+/// acc.push(1.0);
+/// acc.push(2.0);
+/// acc.push(3.0);
+/// acc.remove(2.0);
+/// acc.ln_sum_exp(); // (1_f64.exp() + 3_f64.exp()).ln()
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct StreamingLnSumExp<T> {
+    max: T,
+    /// sum of `exp(val - max)`
+    sum: T,
+    /// sum of the magnitude of every addition and removal, scaled like `sum`
+    mass: T,
+    count: usize,
+}
+
+impl<T> StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create a new empty accumulator
+    #[must_use]
+    pub fn new() -> Self {
+        StreamingLnSumExp {
+            max: T::neg_infinity(),
+            sum: T::zero(),
+            mass: T::zero(),
+            count: 0,
+        }
+    }
+
+    /// The number of values in the accumulator
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// True if the accumulator contains no values
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The largest value pushed since the accumulator was last empty
+    ///
+    /// Removing values doesn't lower the maximum, so this is an upper bound on the live values
+    /// rather than their maximum. An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn current_max(&self) -> T {
+        self.max
+    }
+
+    /// Remove every value, so the accumulator can be reused
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// `exp(value - max)` with equal infinities treated as one
+    fn scaled(&self, value: T) -> T {
+        if value == self.max {
+            T::one()
+        } else {
+            exp(value - self.max)
+        }
+    }
+
+    /// Add a value to the accumulator
+    pub fn push(&mut self, value: T) {
+        self.count += 1;
+        if value > self.max {
+            let scale = self.scaled(value).recip();
+            self.sum = self.sum * scale + T::one();
+            self.mass = self.mass * scale + T::one();
+            self.max = value;
+        } else {
+            let term = self.scaled(value);
+            self.sum = self.sum + term;
+            self.mass = self.mass + term;
+        }
+    }
+
+    /// Add every value in a slice to the accumulator
+    ///
+    /// This is the same as pushing each value, but reduces the whole chunk with the vectorized
+    /// kernels of [`ln_sum_exp_slice`][crate::ln_sum_exp_slice], one max pass and one sum pass,
+    /// and then [merges][Self::merge] it, which is much faster for sources that deliver values in
+    /// blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::StreamingLnSumExp;
+    ///
+    /// let mut acc = StreamingLnSumExp::new();
+    /// acc.push_slice(&[0.0, 0.0, 0.0]);
+    /// acc.push_slice(&[0.0]);
+    /// acc.ln_sum_exp(); // 4_f64.ln()
+    /// ```
+    pub fn push_slice(&mut self, values: &[T]) {
+        let max = slice_max(values);
+        if max.is_finite() {
+            let sum = slice_sum_exp(values, max);
+            self.merge(&StreamingLnSumExp {
+                max,
+                sum,
+                mass: sum,
+                count: values.len(),
+            });
+        } else {
+            // infinities and empty chunks keep the exact semantics of push
+            self.extend(values);
+        }
+    }
+
+    /// Remove a value that was previously pushed
+    ///
+    /// Removing a value that was never pushed produces an unspecified result, usually NaN.
+    pub fn remove(&mut self, value: T) {
+        self.count = self.count.saturating_sub(1);
+        if self.count == 0 {
+            self.reset();
+        } else if self.max == T::infinity() && value == self.max {
+            // every finite value was absorbed by the infinity, so only a recompute can find them
+            self.sum = self.sum - T::one();
+            self.mass = T::infinity();
+        } else {
+            let term = self.scaled(value);
+            self.mass = self.mass + term;
+            let sum = self.sum - term;
+            self.sum = if sum.abs() <= T::epsilon() * self.mass {
+                T::zero()
+            } else if sum < T::zero() {
+                T::nan()
+            } else {
+                sum
+            };
+        }
+    }
+
+    /// Add every value from another accumulator
+    ///
+    /// This is the same as pushing all of the values that were pushed to `other`, so partial
+    /// accumulators can be computed independently, for example on different threads, and then
+    /// combined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logaddexp::StreamingLnSumExp;
+    ///
+    /// let mut left = StreamingLnSumExp::new();
+    /// left.push(1.0);
+    /// let mut right = StreamingLnSumExp::new();
+    /// right.push(2.0);
+    /// left.merge(&right);
+    /// left.ln_sum_exp(); // (1_f64.exp() + 2_f64.exp()).ln()
+    /// ```
+    pub fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        if other.max > self.max {
+            let scale = self.scaled(other.max).recip();
+            self.sum = self.sum * scale + other.sum;
+            self.mass = self.mass * scale + other.mass;
+            self.max = other.max;
+        } else {
+            let scale = self.scaled(other.max);
+            self.sum = self.sum + other.sum * scale;
+            self.mass = self.mass + other.mass * scale;
+        }
+    }
+
+    /// Reset the accumulator to exactly represent `values`
+    ///
+    /// This discards any error that accumulated from removals.
+    pub fn recompute<I>(&mut self, values: I)
+    where
+        I: IntoIterator<Item = T>,
+    {
+        self.reset();
+        self.extend(values);
+    }
+
+    /// True if removals have cancelled enough mass that the result has lost about half of its
+    /// precision
+    #[must_use]
+    pub fn needs_recompute(&self) -> bool {
+        self.mass * T::epsilon().sqrt() > self.sum
+    }
+
+    /// The `ln_sum_exp` of the values currently in the accumulator
+    ///
+    /// This is constant time and doesn't modify the accumulator, so it can be queried at any point
+    /// while values are still being pushed. An empty accumulator returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        if self.sum == T::zero() {
+            T::neg_infinity()
+        } else if self.max == T::infinity() || (self.max == T::neg_infinity() && !self.sum.is_nan())
+        {
+            // positive infinity wins over NaN, while negative infinity only stands without NaN
+            self.max
+        } else {
+            self.max + ln(self.sum)
+        }
+    }
+}
+
+impl<T> Default for StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collect values into an accumulator of their `ln_sum_exp`
+///
+/// Unlike [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] this only iterates once, so it works after
+/// any iterator adapters without requiring [Clone].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::StreamingLnSumExp;
+///
+/// let acc: StreamingLnSumExp<f64> = (1..4).map(|n| f64::from(n).ln()).collect();
+/// acc.ln_sum_exp(); // 6_f64.ln()
+/// ```
+impl<T> FromIterator<T> for StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut acc = Self::new();
+        acc.extend(iter);
+        acc
+    }
+}
+
+/// Push every value from an iterator
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::StreamingLnSumExp;
+///
+/// let mut acc = StreamingLnSumExp::new();
+/// acc.extend([0.0, 0.0]);
+/// acc.extend(&[0.0, 0.0]);
+/// acc.ln_sum_exp(); // 4_f64.ln()
+/// ```
+impl<T> Extend<T> for StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<'a, T> Extend<&'a T> for StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+/// Collect partial accumulators by [merging][StreamingLnSumExp::merge] them
+impl<T> FromIterator<StreamingLnSumExp<T>> for StreamingLnSumExp<T>
+where
+    T: Float,
+{
+    fn from_iter<I: IntoIterator<Item = StreamingLnSumExp<T>>>(iter: I) -> Self {
+        let mut acc = Self::new();
+        for other in iter {
+            acc.merge(&other);
+        }
+        acc
+    }
+}
+
+/// Compute `ln_sum_exp` of `f` applied to every item in a single pass
+///
+/// [`ln_sum_exp`][crate::LogSumExp::ln_sum_exp] iterates twice, so mapping an iterator with an
+/// expensive function, like a log density, evaluates it twice per item. This evaluates `f`
+/// exactly once per item, and doesn't require the iterator to be [Clone].
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_by;
+///
+/// let ln_pdf = |x: f64| -0.5 * x * x;
+/// ln_sum_exp_by([0.0, 1.0, 2.0], ln_pdf);
+/// ```
+pub fn ln_sum_exp_by<I, T, F>(iter: I, mut f: F) -> T
+where
+    I: IntoIterator,
+    T: Float,
+    F: FnMut(I::Item) -> T,
+{
+    let mut acc = StreamingLnSumExp::new();
+    for item in iter {
+        acc.push(f(item));
+    }
+    acc.ln_sum_exp()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_by, StreamingLnSumExp};
+    use crate::{LogAddExp, LogSumExp};
+
+    #[test]
+    fn test_push_remove() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let mut acc = StreamingLnSumExp::new();
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        for &val in &vals {
+            acc.push(val);
+        }
+        assert_close!(acc.ln_sum_exp(), vals.iter().copied().ln_sum_exp());
+
+        for (i, &val) in vals.iter().enumerate().take(10) {
+            acc.remove(val);
+            assert_close!(acc.ln_sum_exp(), vals[i + 1..].iter().copied().ln_sum_exp());
+        }
+        assert!(!acc.needs_recompute());
+        for &val in &vals[10..] {
+            acc.remove(val);
+        }
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        assert!(!acc.needs_recompute());
+    }
+
+    #[test]
+    fn test_merge() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        for split in [0, 1, 10, 18, 19] {
+            let mut left = StreamingLnSumExp::new();
+            let mut right = StreamingLnSumExp::new();
+            for &val in &vals[..split] {
+                left.push(val);
+            }
+            for &val in &vals[split..] {
+                right.push(val);
+            }
+            let mut swapped = right;
+            swapped.merge(&left);
+            left.merge(&right);
+            assert_close!(left.ln_sum_exp(), 190_f64.ln());
+            assert_close!(swapped.ln_sum_exp(), 190_f64.ln());
+            left.remove(vals[5]);
+            assert_close!(left.ln_sum_exp(), 184_f64.ln());
+        }
+
+        let mut acc = StreamingLnSumExp::new();
+        acc.merge(&StreamingLnSumExp::new());
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        let mut inf = StreamingLnSumExp::new();
+        inf.push(f64::INFINITY);
+        acc.merge(&inf);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let acc: StreamingLnSumExp<f64> = vals.iter().copied().collect();
+        assert_close!(acc.ln_sum_exp(), 190_f64.ln());
+
+        let acc: StreamingLnSumExp<f64> = vals
+            .chunks(4)
+            .map(|chunk| chunk.iter().copied().collect::<StreamingLnSumExp<_>>())
+            .collect();
+        assert_close!(acc.ln_sum_exp(), 190_f64.ln());
+
+        let acc: StreamingLnSumExp<f32> = std::iter::empty::<f32>().collect();
+        assert_eq!(acc.ln_sum_exp(), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_extend() {
+        let vals: Vec<f64> = (1..20).map(|n| f64::from(n).ln()).collect();
+        let mut acc = StreamingLnSumExp::new();
+        acc.extend(vals[..7].iter().copied());
+        acc.extend(&vals[7..]);
+        assert_close!(acc.ln_sum_exp(), 190_f64.ln());
+        acc.extend(&[]);
+        assert_close!(acc.ln_sum_exp(), 190_f64.ln());
+    }
+
+    #[test]
+    fn test_introspection() {
+        let mut acc = StreamingLnSumExp::new();
+        assert!(acc.is_empty());
+        assert_eq!(acc.current_max(), f64::NEG_INFINITY);
+        acc.extend([1.0, 3.0, 2.0]);
+        assert_eq!(acc.len(), 3);
+        assert!(!acc.is_empty());
+        assert_eq!(acc.current_max(), 3.0);
+        acc.remove(3.0);
+        assert_eq!(acc.len(), 2);
+        assert_eq!(acc.current_max(), 3.0);
+        assert_close!(acc.ln_sum_exp(), 1_f64.ln_add_exp(2.0));
+
+        acc.reset();
+        assert!(acc.is_empty());
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        acc.push(0.0);
+        assert_eq!(acc.ln_sum_exp(), 0.0);
+        assert_eq!(acc.current_max(), 0.0);
+    }
+
+    #[test]
+    fn test_push_slice() {
+        let vals: Vec<f64> = (0..100).map(|n| f64::from(n).sin() * 50.0).collect();
+        let mut acc = StreamingLnSumExp::new();
+        for chunk in vals.chunks(13) {
+            acc.push_slice(chunk);
+        }
+        assert_close!(acc.ln_sum_exp(), vals.iter().copied().ln_sum_exp());
+        acc.remove(vals[0]);
+        assert_close!(acc.ln_sum_exp(), vals[1..].iter().copied().ln_sum_exp());
+
+        let mut acc = StreamingLnSumExp::new();
+        acc.push_slice(&[]);
+        acc.push_slice(&[f64::NEG_INFINITY; 3]);
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        acc.push_slice(&[1.0, f64::INFINITY]);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+
+        let mut acc = StreamingLnSumExp::new();
+        acc.push_slice(&[1.0, f64::NAN, 2.0]);
+        assert!(acc.ln_sum_exp().is_nan());
+    }
+
+    #[test]
+    fn test_ln_sum_exp_by() {
+        let mut calls = 0;
+        let res = ln_sum_exp_by(1..20, |n| {
+            calls += 1;
+            f64::from(n).ln()
+        });
+        assert_eq!(calls, 19);
+        assert_close!(res, 190_f64.ln());
+        assert_eq!(ln_sum_exp_by(0..0, f64::from), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut acc = StreamingLnSumExp::new();
+        acc.push(0.0);
+        acc.push(40.0);
+        acc.remove(40.0);
+        assert!(acc.needs_recompute());
+        acc.recompute([0.0]);
+        assert!(!acc.needs_recompute());
+        assert_eq!(acc.ln_sum_exp(), 0.0);
+    }
+
+    #[test]
+    fn test_infinite() {
+        let mut acc = StreamingLnSumExp::new();
+        acc.push(f64::NEG_INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        acc.push(1.0);
+        acc.push(f64::INFINITY);
+        acc.push(f64::INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+        acc.remove(f64::INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+        acc.remove(f64::INFINITY);
+        assert!(acc.needs_recompute());
+
+        let mut acc = StreamingLnSumExp::new();
+        acc.push(f64::NEG_INFINITY);
+        acc.push(f64::NEG_INFINITY);
+        acc.remove(f64::NEG_INFINITY);
+        assert!(!acc.needs_recompute());
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+
+        let mut acc = StreamingLnSumExp::new();
+        acc.push(f64::NAN);
+        assert!(acc.ln_sum_exp().is_nan());
+        acc.push(1.0);
+        assert!(acc.ln_sum_exp().is_nan());
+        acc.push(f64::INFINITY);
+        assert_eq!(acc.ln_sum_exp(), f64::INFINITY);
+    }
+}
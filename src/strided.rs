@@ -0,0 +1,364 @@
+//! Axis reductions over flat buffers
+use super::math::{exp, ln};
+use super::{ln_sum_exp_slice, LogSumExp};
+use num_traits::Float;
+
+/// Compute `ln_sum_exp` of `count` values of `buf` starting at `offset`, `stride` apart
+///
+/// This reduces along one axis of a tensor stored in a flat buffer, e.g. a column of a row-major
+/// matrix is `ln_sum_exp_strided(buf, col, cols, rows)`. Zero values return negative infinity.
+///
+/// # Panics
+///
+/// If `stride` is zero, or the last value is out of bounds.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_strided;
+///
+/// let buf = [0.0, 9.0, 0.0, 9.0, 0.0];
+/// ln_sum_exp_strided(&buf, 0, 2, 3); // 3_f64.ln()
+/// ```
+#[must_use]
+pub fn ln_sum_exp_strided<T>(buf: &[T], offset: usize, stride: usize, count: usize) -> T
+where
+    T: Float,
+{
+    assert!(stride > 0, "stride must be positive");
+    if count == 0 {
+        return T::neg_infinity();
+    }
+    assert!(
+        (count - 1)
+            .checked_mul(stride)
+            .and_then(|last| last.checked_add(offset))
+            .is_some_and(|last| last < buf.len()),
+        "strided values must be in bounds"
+    );
+    buf[offset..]
+        .iter()
+        .step_by(stride)
+        .take(count)
+        .copied()
+        .ln_sum_exp()
+}
+
+/// Compute `ln_sum_exp` of every row of a row-major matrix
+///
+/// `buf` holds `rows * cols` values with each row contiguous, and `shape` is `(rows, cols)`. Each
+/// row is reduced with [`ln_sum_exp_slice`], and rows with no columns are negative infinity.
+///
+/// # Panics
+///
+/// If `buf` doesn't have `rows * cols` values.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_rows;
+///
+/// let buf = [0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+/// ln_sum_exp_rows(&buf, (2, 3)); // [(2.0 + 1_f64.exp()).ln(), 1.0 + 3_f64.ln()]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_rows<T>(buf: &[T], shape: (usize, usize)) -> Vec<T>
+where
+    T: Float,
+{
+    let (rows, cols) = shape;
+    assert_eq!(
+        buf.len(),
+        rows * cols,
+        "buffer must have rows * cols values"
+    );
+    if cols == 0 {
+        vec![T::neg_infinity(); rows]
+    } else {
+        buf.chunks_exact(cols).map(ln_sum_exp_slice).collect()
+    }
+}
+
+/// Compute `ln_sum_exp` of every column of a row-major matrix
+///
+/// This is the same as [`ln_sum_exp_strided`] for each column, but traverses the buffer a row at a
+/// time, keeping a running maximum and then a running sum for every column, so memory is only ever
+/// read contiguously. Columns with no rows are negative infinity.
+///
+/// # Panics
+///
+/// If `buf` doesn't have `rows * cols` values.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_cols;
+///
+/// let buf = [0.0, 1.0, 0.0, 1.0];
+/// ln_sum_exp_cols(&buf, (2, 2)); // [2_f64.ln(), 1.0 + 2_f64.ln()]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_cols<T>(buf: &[T], shape: (usize, usize)) -> Vec<T>
+where
+    T: Float,
+{
+    let (rows, cols) = shape;
+    assert_eq!(
+        buf.len(),
+        rows * cols,
+        "buffer must have rows * cols values"
+    );
+    ln_sum_exp_scatter(buf, cols, (0..buf.len()).map(|ind| ind % cols.max(1)))
+}
+
+/// Compute `ln_sum_exp` over several axes of a row-major tensor
+///
+/// `buf` holds a tensor with dimensions `shape`, with the last axis contiguous, and this reduces
+/// every axis in `axes`, like scipy's `logsumexp(a, axis=axes)`. The result is row-major with the
+/// reduced axes removed, e.g. reducing axes `[0, 2]` of a `[2, 3, 4]` tensor returns three values.
+/// This marginalizes a joint log probability table over several variables at once. The buffer is
+/// traversed in memory order, once for the maxima and once for the sums, regardless of which axes
+/// are reduced. Outputs that reduce no values, because a reduced axis is empty, are negative
+/// infinity.
+///
+/// # Panics
+///
+/// If `buf` doesn't have as many values as `shape` implies, or an axis is out of range or
+/// repeated.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_axes;
+///
+/// let buf = [0.0; 24];
+/// ln_sum_exp_axes(&buf, &[2, 3, 4], &[0, 2]); // [8_f64.ln(); 3]
+/// ```
+#[must_use]
+pub fn ln_sum_exp_axes<T>(buf: &[T], shape: &[usize], axes: &[usize]) -> Vec<T>
+where
+    T: Float,
+{
+    assert_eq!(
+        buf.len(),
+        shape.iter().product::<usize>(),
+        "buffer must have as many values as shape"
+    );
+    let mut reduced = vec![false; shape.len()];
+    for &axis in axes {
+        assert!(
+            axis < shape.len() && !reduced[axis],
+            "axes must be in range and unique"
+        );
+        reduced[axis] = true;
+    }
+    // the stride of each input axis in the output, which is zero for reduced axes
+    let mut strides = vec![0; shape.len()];
+    let mut len = 1;
+    for ((stride, &dim), &red) in strides.iter_mut().zip(shape).zip(&reduced).rev() {
+        if !red {
+            *stride = len;
+            len *= dim;
+        }
+    }
+    let indices = OutIndices {
+        shape,
+        strides: &strides,
+        index: vec![0; shape.len()],
+        offset: 0,
+    };
+    ln_sum_exp_scatter(buf, len, indices.take(buf.len()))
+}
+
+/// The output index of every input value of a row-major tensor, in memory order
+#[derive(Clone)]
+struct OutIndices<'a> {
+    shape: &'a [usize],
+    strides: &'a [usize],
+    index: Vec<usize>,
+    offset: usize,
+}
+
+impl Iterator for OutIndices<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let res = self.offset;
+        for ((ind, &dim), &stride) in self
+            .index
+            .iter_mut()
+            .zip(self.shape)
+            .zip(self.strides)
+            .rev()
+        {
+            *ind += 1;
+            self.offset += stride;
+            if *ind < dim {
+                break;
+            }
+            self.offset -= stride * *ind;
+            *ind = 0;
+        }
+        Some(res)
+    }
+}
+
+/// Compute `ln_sum_exp` of the values of `buf` that map to each of `len` outputs
+///
+/// `indices` yields the output of every value in order, and is cloned so the buffer can be
+/// traversed twice, once for the maxima and once for the sums.
+fn ln_sum_exp_scatter<T, I>(buf: &[T], len: usize, indices: I) -> Vec<T>
+where
+    T: Float,
+    I: Iterator<Item = usize> + Clone,
+{
+    let mut maxes = vec![T::neg_infinity(); len];
+    let mut has_nan = vec![false; len];
+    let mut infinite = 0;
+    for (&val, ind) in buf.iter().zip(indices.clone()) {
+        if val > maxes[ind] {
+            maxes[ind] = val;
+            if val == T::infinity() {
+                infinite += 1;
+                // nothing can change an infinite max, so stop once every output has one
+                if infinite == len {
+                    return maxes;
+                }
+            }
+        }
+        has_nan[ind] |= val.is_nan();
+    }
+    let mut sums = vec![T::zero(); len];
+    for (&val, ind) in buf.iter().zip(indices) {
+        let max = maxes[ind];
+        if max.is_finite() {
+            sums[ind] = sums[ind] + exp(val - max);
+        }
+    }
+    maxes
+        .into_iter()
+        .zip(sums)
+        .zip(has_nan)
+        .map(|((max, sum), nan)| {
+            if max.is_finite() {
+                ln(sum) + max
+            } else if max == T::neg_infinity() && nan {
+                T::nan()
+            } else {
+                max
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_axes, ln_sum_exp_cols, ln_sum_exp_rows, ln_sum_exp_strided};
+    use crate::ln_sum_exp_slice;
+
+    #[test]
+    fn test_ln_sum_exp_strided() {
+        let buf: Vec<f64> = (0..30).map(|n| f64::from(n).sin() * 10.0).collect();
+        let picked: Vec<f64> = buf[2..].iter().step_by(7).copied().collect();
+        let res = ln_sum_exp_strided(&buf, 2, 7, picked.len());
+        assert_close!(res, ln_sum_exp_slice(&picked));
+        assert_eq!(ln_sum_exp_strided(&buf, 29, 7, 1), buf[29]);
+        assert_eq!(ln_sum_exp_strided(&buf, 100, 7, 0), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "strided values must be in bounds")]
+    fn test_ln_sum_exp_strided_out_of_bounds() {
+        let _ = ln_sum_exp_strided(&[0.0; 10], 1, 3, 4);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_rows_cols() {
+        let (rows, cols) = (7, 5);
+        let buf: Vec<f64> = (0..35).map(|n| f64::from(n).cos() * 30.0).collect();
+        let by_rows = ln_sum_exp_rows(&buf, (rows, cols));
+        let by_cols = ln_sum_exp_cols(&buf, (rows, cols));
+        for (row, &res) in by_rows.iter().enumerate() {
+            assert_close!(res, ln_sum_exp_slice(&buf[row * cols..][..cols]));
+        }
+        for (col, &res) in by_cols.iter().enumerate() {
+            assert_close!(res, ln_sum_exp_strided(&buf, col, cols, rows));
+        }
+
+        assert_eq!(ln_sum_exp_rows::<f64>(&[], (3, 0)), [f64::NEG_INFINITY; 3]);
+        assert!(ln_sum_exp_cols::<f64>(&[], (3, 0)).is_empty());
+        assert!(ln_sum_exp_rows::<f64>(&[], (0, 3)).is_empty());
+        assert_eq!(ln_sum_exp_cols::<f64>(&[], (0, 3)), [f64::NEG_INFINITY; 3]);
+    }
+
+    #[test]
+    fn test_ln_sum_exp_cols_special() {
+        let inf = f64::INFINITY;
+        let buf = [
+            f64::NEG_INFINITY,
+            f64::NAN,
+            1.0,
+            inf,
+            f64::NEG_INFINITY,
+            0.0,
+            f64::NAN,
+            f64::NAN,
+        ];
+        let res = ln_sum_exp_cols(&buf, (2, 4));
+        assert_eq!(res[0], f64::NEG_INFINITY);
+        assert!(res[1].is_nan());
+        assert!(res[2].is_nan());
+        assert_eq!(res[3], inf);
+        assert_eq!(
+            ln_sum_exp_cols(&[inf, inf, f64::NAN, 1.0], (2, 2)),
+            [inf; 2]
+        );
+    }
+
+    #[test]
+    fn test_ln_sum_exp_axes() {
+        let shape = [2, 3, 4];
+        let buf: Vec<f64> = (0..24).map(|n| f64::from(n).sin() * 20.0).collect();
+        let at = |i: usize, j: usize, k: usize| buf[(i * 3 + j) * 4 + k];
+
+        let res = ln_sum_exp_axes(&buf, &shape, &[2, 0]);
+        assert_eq!(res.len(), 3);
+        for (j, &val) in res.iter().enumerate() {
+            let vals: Vec<f64> = (0..2)
+                .flat_map(|i| (0..4).map(move |k| (i, k)))
+                .map(|(i, k)| at(i, j, k))
+                .collect();
+            assert_close!(val, ln_sum_exp_slice(&vals));
+        }
+
+        let res = ln_sum_exp_axes(&buf, &shape, &[1]);
+        assert_eq!(res.len(), 8);
+        for (ind, &val) in res.iter().enumerate() {
+            let vals: Vec<f64> = (0..3).map(|j| at(ind / 4, j, ind % 4)).collect();
+            assert_close!(val, ln_sum_exp_slice(&vals));
+        }
+
+        // no axes is the identity, and all axes is a full reduction
+        assert_eq!(ln_sum_exp_axes(&buf, &shape, &[]), buf);
+        let res = ln_sum_exp_axes(&buf, &shape, &[0, 1, 2]);
+        assert_close!(res[0], ln_sum_exp_slice(&buf));
+        // matrices match the row and column reductions
+        assert_eq!(
+            ln_sum_exp_axes(&buf, &[6, 4], &[0]),
+            ln_sum_exp_cols(&buf, (6, 4))
+        );
+
+        // empty reduced axes produce negative infinity
+        assert_eq!(
+            ln_sum_exp_axes::<f64>(&[], &[3, 0], &[1]),
+            [f64::NEG_INFINITY; 3]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "axes must be in range and unique")]
+    fn test_ln_sum_exp_axes_repeated() {
+        let _ = ln_sum_exp_axes(&[0.0; 6], &[2, 3], &[1, 1]);
+    }
+}
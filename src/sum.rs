@@ -0,0 +1,68 @@
+//! Summing log values through the standard `Sum` trait
+use super::StreamingLnSumExp;
+use num_traits::Float;
+use std::iter::Sum;
+
+/// A log value whose [`Sum`] is the `ln_sum_exp`
+///
+/// Generic code that reduces with [`Sum`] can compute a stable `ln_sum_exp` by wrapping values
+/// in `LnExp`. Summing is a single pass with [`StreamingLnSumExp`], so it doesn't require the
+/// iterator to be [Clone], and an empty sum is negative infinity.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::LnExp;
+///
+/// let LnExp(total) = [1.0, 2.0, 3.0].into_iter().map(LnExp).sum();
+/// // total == 6_f64.ln()
+/// # let _: f64 = total;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct LnExp<T>(pub T);
+
+impl<T> Sum for LnExp<T>
+where
+    T: Float,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let acc: StreamingLnSumExp<T> = iter.map(|LnExp(val)| val).collect();
+        LnExp(acc.ln_sum_exp())
+    }
+}
+
+impl<'a, T> Sum<&'a LnExp<T>> for LnExp<T>
+where
+    T: Float,
+{
+    fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::LnExp;
+    use std::iter::Sum;
+
+    /// A reduction that only knows about `Sum`
+    fn total<S: Sum<S>>(vals: impl IntoIterator<Item = S>) -> S {
+        vals.into_iter().sum()
+    }
+
+    #[test]
+    fn test_sum() {
+        let vals: Vec<LnExp<f64>> = (1..20).map(|n| LnExp(f64::from(n).ln())).collect();
+        let LnExp(res) = total(vals.iter().copied());
+        assert_close!(res, 190_f64.ln());
+        let LnExp(res) = vals.iter().sum();
+        assert_close!(res, 190_f64.ln());
+
+        let LnExp(res) = [1000_f32, 1000.0].into_iter().map(LnExp).sum();
+        assert_close!(res, 1000.0 + 2_f32.ln());
+
+        let LnExp(res) = std::iter::empty::<LnExp<f64>>().sum();
+        assert_eq!(res, f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,192 @@
+//! Interop with libtorch tensors through `tch`
+//!
+//! [`logsumexp`] and [`log_softmax`] copy a tensor to the host as `f64` and reduce along a
+//! dimension with the slice kernels, so they can stand in for the libtorch ops when migrating
+//! code. [`torch_parity`] compares the result against libtorch's own `logsumexp` on the same
+//! input, to verify that the two agree on a particular workload.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use logaddexp::tch::{logsumexp, torch_parity};
+//! use tch::Tensor;
+//!
+//! let tensor = Tensor::from_slice(&[1.0, 2.0, 3.0, 4.0]).view([2, 2]);
+//! let totals = logsumexp(&tensor, 1)?; // shape [2]
+//! assert!(torch_parity(&tensor, 1)?.is_close(1e-12));
+//! # Ok::<(), tch::TchError>(())
+//! ```
+use super::ln_sum_exp_slice;
+use tch::{Kind, TchError, Tensor};
+
+/// The values of a tensor as `f64` with `dim` moved to the end, and the shape after the move
+fn lanes(tensor: &Tensor, dim: i64) -> Result<(Vec<f64>, Vec<i64>), TchError> {
+    let moved = tensor
+        .f_movedim([dim], [-1])?
+        .f_to_kind(Kind::Double)?
+        .f_contiguous()?;
+    let values = Vec::<f64>::try_from(&moved.f_reshape([-1])?)?;
+    Ok((values, moved.size()))
+}
+
+/// The length of every lane, where a 0-d tensor is a single lane of one value like in libtorch
+fn lane_len(shape: &[i64]) -> usize {
+    shape.last().map_or(1, |&len| usize::try_from(len).unwrap())
+}
+
+/// `ln_sum_exp` of every lane of `values`, where `shape` is the shape of the lanes
+fn lane_totals(values: &[f64], shape: &[i64]) -> Vec<f64> {
+    match lane_len(shape) {
+        0 => {
+            let count = shape[..shape.len() - 1]
+                .iter()
+                .map(|&dim| usize::try_from(dim).unwrap())
+                .product();
+            vec![f64::NEG_INFINITY; count]
+        }
+        len => values.chunks_exact(len).map(ln_sum_exp_slice).collect(),
+    }
+}
+
+/// Compute `ln_sum_exp` along `dim`, removing that dimension
+///
+/// The result is always a `Double` tensor on the CPU. Reducing a dimension of size zero produces
+/// negative infinity.
+///
+/// # Errors
+///
+/// If `dim` is out of range or the tensor can't be converted to `f64`.
+pub fn logsumexp(tensor: &Tensor, dim: i64) -> Result<Tensor, TchError> {
+    let (values, mut shape) = lanes(tensor, dim)?;
+    let totals = lane_totals(&values, &shape);
+    shape.pop();
+    Tensor::from_slice(&totals).f_reshape(shape)
+}
+
+/// Subtract the `ln_sum_exp` along `dim` from every value
+///
+/// The result is always a `Double` tensor on the CPU.
+///
+/// # Errors
+///
+/// If `dim` is out of range or the tensor can't be converted to `f64`.
+pub fn log_softmax(tensor: &Tensor, dim: i64) -> Result<Tensor, TchError> {
+    let (mut values, shape) = lanes(tensor, dim)?;
+    let totals = lane_totals(&values, &shape);
+    let len = lane_len(&shape);
+    if len > 0 {
+        for (lane, total) in values.chunks_exact_mut(len).zip(totals) {
+            for val in lane {
+                *val -= total;
+            }
+        }
+    }
+    Tensor::from_slice(&values)
+        .f_reshape(shape)?
+        .f_movedim([-1], [dim])
+}
+
+/// How closely [`logsumexp`] agrees with libtorch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parity {
+    /// The largest absolute difference between results that are both finite
+    pub max_abs_diff: f64,
+    /// The number of results where only one is finite, or they are different non-finite values
+    pub mismatches: usize,
+}
+
+impl Parity {
+    /// True if there are no mismatches and every finite result is within `atol`
+    #[must_use]
+    pub fn is_close(&self, atol: f64) -> bool {
+        self.mismatches == 0 && self.max_abs_diff <= atol
+    }
+}
+
+/// Compare [`logsumexp`] against libtorch's `logsumexp` along `dim`
+///
+/// Both are computed in `f64`. Two NaN results, or two equal infinities, count as agreeing.
+///
+/// # Errors
+///
+/// If `dim` is out of range or the tensor can't be converted to `f64`.
+pub fn torch_parity(tensor: &Tensor, dim: i64) -> Result<Parity, TchError> {
+    let ours = Vec::<f64>::try_from(&logsumexp(tensor, dim)?.f_reshape([-1])?)?;
+    let theirs = tensor
+        .f_to_kind(Kind::Double)?
+        .f_logsumexp([dim], false)?
+        .f_to_device(tch::Device::Cpu)?
+        .f_reshape([-1])?;
+    let theirs = Vec::<f64>::try_from(&theirs)?;
+    let mut parity = Parity {
+        max_abs_diff: 0.0,
+        mismatches: 0,
+    };
+    for (our, their) in ours.into_iter().zip(theirs) {
+        if our.is_finite() && their.is_finite() {
+            parity.max_abs_diff = parity.max_abs_diff.max((our - their).abs());
+        } else if !(our == their || our.is_nan() && their.is_nan()) {
+            parity.mismatches += 1;
+        }
+    }
+    Ok(parity)
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{log_softmax, logsumexp, torch_parity};
+    use crate::LogSumExp;
+    use tch::Tensor;
+
+    #[test]
+    fn test_logsumexp() {
+        let vals: Vec<f64> = (0..24).map(|n| f64::from(n).sin() * 10.0).collect();
+        let tensor = Tensor::from_slice(&vals).view([2, 3, 4]);
+
+        let res = logsumexp(&tensor, -1).unwrap();
+        assert_eq!(res.size(), [2, 3]);
+        let expected = vals[20..].iter().copied().ln_sum_exp();
+        assert_close!(res.double_value(&[1, 2]), expected);
+
+        let res = logsumexp(&tensor, 1).unwrap();
+        assert_eq!(res.size(), [2, 4]);
+        let lane = [vals[13], vals[17], vals[21]];
+        assert_close!(res.double_value(&[1, 1]), lane.into_iter().ln_sum_exp());
+
+        let res = logsumexp(&Tensor::from_slice::<f32>(&[]).view([2, 0]), 1).unwrap();
+        assert_eq!(Vec::<f64>::try_from(&res).unwrap(), [f64::NEG_INFINITY; 2]);
+
+        let res = logsumexp(&Tensor::from(2.0_f64), 0).unwrap();
+        assert!(res.size().is_empty());
+        assert_eq!(res.double_value(&[]), 2.0);
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let tensor = Tensor::from_slice(&[0.0, f64::NEG_INFINITY, 0.0, 1.0]).view([2, 2]);
+        let res = log_softmax(&tensor, 0).unwrap();
+        assert_eq!(res.size(), [2, 2]);
+        assert_close!(res.double_value(&[0, 0]), 0.5_f64.ln());
+        assert_close!(res.double_value(&[1, 0]), 0.5_f64.ln());
+        assert_eq!(res.double_value(&[0, 1]), f64::NEG_INFINITY);
+        assert_eq!(res.double_value(&[1, 1]), 0.0);
+
+        let res = log_softmax(&Tensor::from(2.0_f64), -1).unwrap();
+        assert!(res.size().is_empty());
+        assert_eq!(res.double_value(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_torch_parity() {
+        let vals: Vec<f64> = (0..60).map(|n| f64::from(n).cos() * 100.0).collect();
+        let tensor = Tensor::from_slice(&vals).view([3, 4, 5]);
+        for dim in 0..3 {
+            assert!(torch_parity(&tensor, dim).unwrap().is_close(1e-12));
+        }
+        let special =
+            Tensor::from_slice(&[f64::NEG_INFINITY, f64::NEG_INFINITY, f64::INFINITY, 1.0]);
+        let parity = torch_parity(&special.view([2, 2]), 1).unwrap();
+        assert_eq!(parity.mismatches, 0);
+    }
+}
@@ -0,0 +1,179 @@
+//! `ln_sum_exp` restricted to the largest values
+use super::math::{exp, ln};
+use super::slice::slice_max;
+use super::{ln_sum_exp_slice, StreamingLnSumExp};
+use num_traits::Float;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// A float ordered for the heap, NaN is filtered out before construction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Ordered<T>(pub(crate) T);
+
+impl<T: Float> Eq for Ordered<T> {}
+
+impl<T: Float> PartialOrd for Ordered<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Float> Ord for Ordered<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Compute `ln_sum_exp` of the `k` largest values and of everything else
+///
+/// This returns `(top, rest)` from a single pass that keeps a heap of at most `k` values, so it
+/// takes O(n log k) time and O(k) memory. If any value is NaN both results are NaN, unless they're
+/// positive infinity.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_top_k_with_rest;
+///
+/// let (top, rest) = ln_sum_exp_top_k_with_rest([1.0, 4.0, 2.0, 3.0], 2);
+/// // top == (3_f64.exp() + 4_f64.exp()).ln()
+/// // rest == (1_f64.exp() + 2_f64.exp()).ln()
+/// ```
+pub fn ln_sum_exp_top_k_with_rest<I, T>(iter: I, k: usize) -> (T, T)
+where
+    I: IntoIterator<Item = T>,
+    T: Float,
+{
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    let mut rest = StreamingLnSumExp::new();
+    let mut nan = false;
+    for val in iter {
+        if val.is_nan() {
+            nan = true;
+        } else {
+            heap.push(Reverse(Ordered(val)));
+            if heap.len() > k {
+                if let Some(Reverse(Ordered(smallest))) = heap.pop() {
+                    rest.push(smallest);
+                }
+            }
+        }
+    }
+    let top: Vec<T> = heap.into_iter().map(|Reverse(Ordered(val))| val).collect();
+    let with_nan = |res: T| {
+        if nan && res != T::infinity() {
+            T::nan()
+        } else {
+            res
+        }
+    };
+    (
+        with_nan(ln_sum_exp_slice(&top)),
+        with_nan(rest.ln_sum_exp()),
+    )
+}
+
+/// Compute `ln_sum_exp` of the `k` largest values
+///
+/// See [`ln_sum_exp_top_k_with_rest`] for details.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_top_k;
+///
+/// ln_sum_exp_top_k([1.0, 4.0, 2.0, 3.0], 2); // (3_f64.exp() + 4_f64.exp()).ln()
+/// ```
+pub fn ln_sum_exp_top_k<I, T>(iter: I, k: usize) -> T
+where
+    I: IntoIterator<Item = T>,
+    T: Float,
+{
+    ln_sum_exp_top_k_with_rest(iter, k).0
+}
+
+/// Compute `ln_sum_exp` of a slice skipping values more than `delta` below the maximum
+///
+/// Skipped values never have `exp` evaluated, which is a large speedup when most of the mass is
+/// concentrated in a few values. The result is never larger than the exact value, and if `m`
+/// values were skipped, it's smaller by at most `ln(1 + m * exp(-delta)) <= m * exp(-delta)`. A
+/// `delta` of `-T::epsilon().ln()` (about 36 for `f64` and 16 for `f32`) skips only values that
+/// individually change the sum by less than an ulp.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::ln_sum_exp_truncated;
+///
+/// ln_sum_exp_truncated(&[0.0, -1.0, -500.0, -1000.0], 40.0); // (1.0 + (-1_f64).exp()).ln()
+/// ```
+#[must_use]
+pub fn ln_sum_exp_truncated<T>(values: &[T], delta: T) -> T
+where
+    T: Float,
+{
+    let max = slice_max(values);
+    if max.is_finite() {
+        let cutoff = max - delta;
+        let sum = values
+            .iter()
+            .filter(|&&val| val >= cutoff || val.is_nan())
+            .fold(T::zero(), |sum, &val| sum + exp(val - max));
+        ln(sum) + max
+    } else {
+        ln_sum_exp_slice(values)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{ln_sum_exp_top_k, ln_sum_exp_top_k_with_rest, ln_sum_exp_truncated};
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_ln_sum_exp_top_k() {
+        let vals: Vec<f64> = (0..30).map(|n| f64::from(n).sin() * 10.0).collect();
+        let mut sorted = vals.clone();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+
+        for k in 1..vals.len() {
+            let (top, rest) = ln_sum_exp_top_k_with_rest(vals.iter().copied(), k);
+            assert_close!(top, sorted[..k].iter().copied().ln_sum_exp());
+            assert_close!(rest, sorted[k..].iter().copied().ln_sum_exp());
+        }
+
+        let (top, rest) = ln_sum_exp_top_k_with_rest(vals.iter().copied(), 0);
+        assert_eq!(top, f64::NEG_INFINITY);
+        assert_close!(rest, vals.iter().copied().ln_sum_exp());
+
+        let (top, rest) = ln_sum_exp_top_k_with_rest(vals.iter().copied(), 100);
+        assert_close!(top, vals.iter().copied().ln_sum_exp());
+        assert_eq!(rest, f64::NEG_INFINITY);
+
+        assert!(ln_sum_exp_top_k([1.0, f64::NAN], 1).is_nan());
+        let (top, rest) = ln_sum_exp_top_k_with_rest([f64::INFINITY, f64::NAN, 1.0], 1);
+        assert_eq!(top, f64::INFINITY);
+        assert!(rest.is_nan());
+    }
+
+    #[test]
+    fn test_ln_sum_exp_truncated() {
+        let vals: Vec<f64> = (0..100).map(|n| -f64::from(n) * 5.0).collect();
+        let exact = vals.iter().copied().ln_sum_exp();
+        let delta = -f64::EPSILON.ln();
+        assert_close!(ln_sum_exp_truncated(&vals, delta), exact, rtol = 1e-15);
+
+        let approx = ln_sum_exp_truncated(&vals, 3.0);
+        assert!(approx <= exact);
+        assert!(exact - approx <= 99.0 * (-3_f64).exp());
+
+        assert_eq!(ln_sum_exp_truncated(&[], 1.0), f64::NEG_INFINITY);
+        assert_eq!(ln_sum_exp_truncated(&[0.0, -10.0], 1.0), 0.0);
+        assert_eq!(
+            ln_sum_exp_truncated(&[f64::INFINITY, 0.0], 1.0),
+            f64::INFINITY
+        );
+        assert!(ln_sum_exp_truncated(&[f64::NAN, 0.0], 1.0).is_nan());
+    }
+}
@@ -0,0 +1,121 @@
+//! Bindings for JavaScript through `wasm-bindgen`
+//!
+//! These export the `f64` numerics to JavaScript, where slices are passed as `Float64Array`s.
+//! Since numbers in JavaScript are always `f64` there aren't `f32` versions.
+//!
+//! `wasm-bindgen` passes a `&[f64]` as a view of the array in the module's memory, so the
+//! reductions only read their input in place. The `_in_place` variants write their result back
+//! into the array passed in, so they don't allocate a second array for the output.
+//!
+//! When the `simd128` target feature is enabled, `ln_sum_exp` and `softmax` use kernels
+//! that evaluate `exp` two lanes at a time with wasm SIMD:
+//!
+//! ```text
+//! RUSTFLAGS="-C target-feature=+simd128" cargo rustc --release ...
+//! ```
+//!
+//! To build a module, compile the crate as a `cdylib` and run `wasm-bindgen` on the output:
+//!
+//! ```text
+//! cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/release/logaddexp.wasm --out-dir pkg
+//! ```
+use super::{ln_normalize_collect, LogAddExp};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+use super::ln_sum_exp_slice;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use super::simd128::{exp_in_place, ln_sum_exp as ln_sum_exp_slice};
+
+/// Replace every value with `exp(val - shift)`
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+fn exp_in_place(values: &mut [f64], shift: f64) {
+    for val in values {
+        *val = super::math::exp(*val - shift);
+    }
+}
+
+/// Compute `ln(exp(a) + exp(b))`
+#[wasm_bindgen]
+#[must_use]
+pub fn ln_add_exp(a: f64, b: f64) -> f64 {
+    a.ln_add_exp(b)
+}
+
+/// Compute `ln(sum(exp(values)))`
+///
+/// An empty array returns negative infinity.
+#[wasm_bindgen]
+#[must_use]
+pub fn ln_sum_exp(values: &[f64]) -> f64 {
+    ln_sum_exp_slice(values)
+}
+
+/// The softmax of `values` as a new array
+#[wasm_bindgen]
+#[must_use]
+pub fn softmax(values: &[f64]) -> Vec<f64> {
+    let mut probs = values.to_vec();
+    softmax_in_place(&mut probs);
+    probs
+}
+
+/// The log softmax of `values` as a new array
+#[wasm_bindgen]
+#[must_use]
+pub fn log_softmax(values: &[f64]) -> Vec<f64> {
+    ln_normalize_collect(values).1
+}
+
+/// Replace `values` with their softmax and return their `ln_sum_exp`
+#[wasm_bindgen]
+pub fn softmax_in_place(values: &mut [f64]) -> f64 {
+    let total = ln_sum_exp_slice(values);
+    exp_in_place(values, total);
+    total
+}
+
+/// Replace `values` with their log softmax and return their `ln_sum_exp`
+#[wasm_bindgen]
+pub fn log_softmax_in_place(values: &mut [f64]) -> f64 {
+    let total = ln_sum_exp_slice(values);
+    for val in values {
+        *val -= total;
+    }
+    total
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::{
+        ln_add_exp, ln_sum_exp, log_softmax, log_softmax_in_place, softmax, softmax_in_place,
+    };
+
+    #[test]
+    fn test_bindings() {
+        let vals = [1.0, 2.0, 4.0, -3.0];
+        let total = ln_sum_exp(&vals);
+        assert_close!(
+            total,
+            ln_add_exp(ln_add_exp(1.0, 2.0), ln_add_exp(4.0, -3.0))
+        );
+        assert_eq!(ln_sum_exp(&[]), f64::NEG_INFINITY);
+
+        let probs = softmax(&vals);
+        let log_probs = log_softmax(&vals);
+        let mut in_place = vals;
+        assert_close!(softmax_in_place(&mut in_place), total);
+        for ((&prob, &log_prob), &res) in probs.iter().zip(&log_probs).zip(&in_place) {
+            assert_close!(prob, log_prob.exp());
+            assert_close!(res, prob);
+        }
+
+        let mut in_place = vals;
+        assert_close!(log_softmax_in_place(&mut in_place), total);
+        for (&log_prob, &res) in log_probs.iter().zip(&in_place) {
+            assert_close!(res, log_prob);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! A `ln_sum_exp` over a trailing window of time
+use super::StreamingLnSumExp;
+use num_traits::Float;
+
+/// An accumulator of the `ln_sum_exp` of values pushed during the last few units of time
+///
+/// Values are pushed into the bucket for the current unit of time, and time is advanced
+/// explicitly with [`tick`][Self::tick] or [`advance`][Self::advance], so the unit can be seconds,
+/// events, or anything else. The buckets form a ring, and advancing past the end of the window
+/// clears the oldest bucket instead of removing its values one at a time, so expiry never loses
+/// precision to cancellation. Querying merges every bucket, which is linear in the number of
+/// buckets but independent of the number of values.
+///
+/// # Examples
+///
+/// ```
+/// use logaddexp::TimeWindowedLnSumExp;
+///
+/// let mut acc = TimeWindowedLnSumExp::new(2);
+/// acc.push(0.0);
+/// acc.tick();
+/// acc.push(0.0);
+/// acc.ln_sum_exp(); // 2_f64.ln()
+/// acc.tick();
+/// acc.ln_sum_exp(); // 0.0
+/// ```
+#[derive(Debug, Clone)]
+pub struct TimeWindowedLnSumExp<T> {
+    buckets: Vec<StreamingLnSumExp<T>>,
+    /// the bucket for the current unit of time
+    head: usize,
+}
+
+impl<T> TimeWindowedLnSumExp<T>
+where
+    T: Float,
+{
+    /// Create a new empty window covering the current unit of time and the `buckets - 1` before it
+    ///
+    /// # Panics
+    ///
+    /// If `buckets` is zero.
+    #[must_use]
+    pub fn new(buckets: usize) -> Self {
+        assert!(buckets > 0, "window must have at least one bucket");
+        TimeWindowedLnSumExp {
+            buckets: vec![StreamingLnSumExp::new(); buckets],
+            head: 0,
+        }
+    }
+
+    /// The number of values in the window
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(StreamingLnSumExp::len).sum()
+    }
+
+    /// True if the window contains no values
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(StreamingLnSumExp::is_empty)
+    }
+
+    /// Add a value at the current time
+    pub fn push(&mut self, value: T) {
+        self.buckets[self.head].push(value);
+    }
+
+    /// Advance time by one unit, expiring the values pushed `buckets` units ago
+    pub fn tick(&mut self) {
+        self.head = (self.head + 1) % self.buckets.len();
+        self.buckets[self.head].reset();
+    }
+
+    /// Advance time by `elapsed` units
+    pub fn advance(&mut self, elapsed: usize) {
+        for _ in 0..elapsed.min(self.buckets.len()) {
+            self.tick();
+        }
+    }
+
+    /// The `ln_sum_exp` of every value in the window
+    ///
+    /// An empty window returns negative infinity.
+    #[must_use]
+    pub fn ln_sum_exp(&self) -> T {
+        let total: StreamingLnSumExp<T> = self.buckets.iter().copied().collect();
+        total.ln_sum_exp()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::float_cmp)]
+mod tests {
+    use super::TimeWindowedLnSumExp;
+    use crate::LogSumExp;
+
+    #[test]
+    fn test_time_windowed_ln_sum_exp() {
+        let vals: Vec<f64> = (0..50).map(|n| f64::from(n).sin() * 10.0).collect();
+        let mut acc = TimeWindowedLnSumExp::new(3);
+        assert!(acc.is_empty());
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+        // two values per unit of time
+        for (time, pair) in vals.chunks(2).enumerate() {
+            acc.push(pair[0]);
+            acc.push(pair[1]);
+            let start = time.saturating_sub(2) * 2;
+            let expected = vals[start..(time + 1) * 2].iter().copied().ln_sum_exp();
+            assert_close!(acc.ln_sum_exp(), expected);
+            assert_eq!(acc.len(), (time + 1).min(3) * 2);
+            acc.tick();
+        }
+
+        acc.push(1.0);
+        acc.advance(2);
+        assert_eq!(acc.ln_sum_exp(), 1.0);
+        acc.advance(100);
+        assert!(acc.is_empty());
+        assert_eq!(acc.ln_sum_exp(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    #[should_panic(expected = "window must have at least one bucket")]
+    fn test_time_windowed_ln_sum_exp_empty() {
+        let _ = TimeWindowedLnSumExp::<f64>::new(0);
+    }
+}